@@ -1,6 +1,5 @@
 use futures::prelude::*;
 use rupnp::{
-    http::Uri,
     ssdp::{SearchTarget, URN},
     DeviceSpec, Error, Service,
 };
@@ -31,7 +30,7 @@ async fn main() -> Result<(), Error> {
     let mut handles = Vec::new();
 
     for device in devices {
-        print(&device, device.url(), 0, &path, &mut handles)?;
+        print(&device, 0, &path, &mut handles)?;
         println!();
     }
 
@@ -44,7 +43,6 @@ async fn main() -> Result<(), Error> {
 
 fn print(
     device: &DeviceSpec,
-    url: &Uri,
     indentation: usize,
     path: &Path,
     handles: &mut Vec<JoinHandle<Result<(), rupnp::Error>>>,
@@ -61,7 +59,6 @@ fn print(
 
         println!("{i}  - {svc}");
 
-        let url = url.clone();
         let path = path.clone();
         let service = service.clone();
 
@@ -72,7 +69,7 @@ fn print(
             let mut svc_file = std::fs::File::create(path.join(&svc))?;
 
             let mut buf = Vec::with_capacity(128);
-            write_service(&mut buf, service, url).await?;
+            write_service(&mut buf, service).await?;
 
             svc_file.write_all(&buf)?;
             // svc_file.write_all(&buf).await?;
@@ -81,18 +78,14 @@ fn print(
     }
 
     for device in device.devices() {
-        print(device, url, indentation + 1, &path, handles)?;
+        print(device, indentation + 1, &path, handles)?;
     }
 
     Ok(())
 }
 
-async fn write_service(
-    mut w: impl std::io::Write,
-    service: Service,
-    url: Uri,
-) -> Result<(), Error> {
-    let scpd = service.scpd(&url).await?;
+async fn write_service(mut w: impl std::io::Write, service: Service) -> Result<(), Error> {
+    let scpd = service.scpd().await?;
 
     writeln!(w, "StateVars {{")?;
     for state_var in scpd.state_variables() {
@@ -0,0 +1,25 @@
+use futures::prelude::*;
+use rupnp::Announcement;
+
+#[tokio::main]
+async fn main() -> Result<(), rupnp::Error> {
+    let announcements = rupnp::listen().await?;
+    let mut announcements = std::pin::pin!(announcements);
+
+    while let Some(announcement) = announcements.next().await {
+        match announcement {
+            Ok(Announcement::Alive(alive)) => println!(
+                "alive:  {} @ {} (max-age {}s)",
+                alive.usn(),
+                alive.location(),
+                alive.max_age().as_secs()
+            ),
+            Ok(Announcement::ByeBye(byebye)) => println!("byebye: {}", byebye.usn()),
+            Ok(Announcement::Update(update)) => println!("update: {}", update.usn()),
+            Ok(_) => {}
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+
+    Ok(())
+}
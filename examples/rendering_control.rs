@@ -1,4 +1,4 @@
-use rupnp::{http::Uri, ssdp::URN, Device};
+use rupnp::{http::Uri, ssdp::URN, ActionArgs, Device};
 
 #[tokio::main]
 async fn main() -> Result<(), rupnp::Error> {
@@ -8,14 +8,13 @@ async fn main() -> Result<(), rupnp::Error> {
     let device = Device::from_url(url).await?;
     let service = device.find_service(&service_urn).unwrap();
 
-    let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
+    let args = ActionArgs::new()
+        .arg("InstanceID", 0)
+        .arg("Channel", "Master");
     let response: u8 = service
-        .action(device.url(), "GetVolume", args)
+        .call("GetVolume", &args)
         .await?
-        .get("CurrentVolume")
-        .unwrap()
-        .parse()
-        .map_err(rupnp::Error::invalid_response)?;
+        .get_as("CurrentVolume")?;
 
     println!("{response}");
 
@@ -16,7 +16,7 @@ async fn main() -> anyhow::Result<()> {
             .expect("searched for RenderingControl, got something else");
 
         let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
-        let response = service.action(device.url(), "GetVolume", args).await?;
+        let response = service.action("GetVolume", args).await?;
 
         let volume = response.get("CurrentVolume").unwrap();
 
@@ -1,6 +1,5 @@
 use futures::prelude::*;
-use rupnp::{http::Uri, ssdp::URN, Device};
-use std::collections::HashMap;
+use rupnp::{http::Uri, ssdp::URN, Device, Event};
 
 #[tokio::main]
 async fn main() -> Result<(), rupnp::Error> {
@@ -10,19 +9,22 @@ async fn main() -> Result<(), rupnp::Error> {
     let device = Device::from_url(url).await?;
     let service = device.find_service(&service_urn).unwrap();
 
-    let (sid, mut stream) = service.subscribe(device.url(), 10).await?;
+    let mut subscription = service.subscription(300).await?;
+    println!("subscribed with {}", subscription.sid());
 
-    while let Some(state_vars) = stream.try_next().await? {
-        handle(state_vars);
-        service.renew_subscription(device.url(), &sid, 10).await?;
+    while let Some(event) = subscription.try_next().await? {
+        handle(event);
     }
 
-    Ok(())
+    subscription.unsubscribe().await
 }
 
-fn handle(state_vars: HashMap<String, String>) {
-    println!("Change {{");
-    for (key, value) in state_vars {
+fn handle(event: Event) {
+    if let Some(gap) = event.gap() {
+        println!("missed events: {gap:?}");
+    }
+    println!("Change #{} {{", event.seq());
+    for (key, value) in event.iter() {
         if value.len() > 256 {
             println!("  {key}: ...");
         } else {
@@ -0,0 +1,613 @@
+//! Generates typed [`rupnp`] clients for UPnP services from their SCPD (Service Control Protocol
+//! Description) xml.
+//!
+//! The generated code contains a struct named after the service with one `async` method per
+//! action, taking and returning Rust types derived from the `dataType` of each argument.
+//! State variables with an `allowedValueList` become enums.
+//!
+//! The code can be generated from a `build.rs` script, or with the `upnp_service!` macro of the
+//! `rupnp-macros` crate.
+//!
+//! # Example usage:
+//! ```rust,no_run
+//! // build.rs
+//! fn main() -> Result<(), rupnp_codegen::Error> {
+//!     println!("cargo:rerun-if-changed=RenderingControl1.xml");
+//!
+//!     let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//!     rupnp_codegen::Generator::from_file("RenderingControl1.xml")?
+//!         .write_to(out_dir.join("rendering_control.rs"))
+//! }
+//! ```
+//! ```rust,ignore
+//! // src/lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/rendering_control.rs"));
+//!
+//! # async fn volume(device: rupnp::Device) -> Result<(), rupnp::Error> {
+//! let rendering_control = RenderingControl::from_device(&device).unwrap();
+//! let volume = rendering_control.get_volume(0, Channel::Master).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use rupnp::scpd::{Action, Argument, DataType, StateVariable, StateVariableKind, SCPD};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+pub use rupnp::ssdp::URN;
+
+/// An error generating a client.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    IO(std::io::Error),
+    /// The SCPD could not be parsed.
+    Scpd(rupnp::Error),
+    /// The service type could not be derived from the file name and was not set explicitly.
+    MissingUrn,
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IO(err) => write!(f, "failed to read or write file: {err}"),
+            Error::Scpd(err) => write!(f, "invalid SCPD: {err}"),
+            Error::MissingUrn => write!(f, "the service type (urn) is unknown"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IO(err) => Some(err),
+            Error::Scpd(err) => Some(err),
+            Error::MissingUrn => None,
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+impl From<rupnp::Error> for Error {
+    fn from(err: rupnp::Error) -> Self {
+        Error::Scpd(err)
+    }
+}
+
+/// Generates the client code of a single service.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    xml: String,
+    urn: Option<URN>,
+    name: Option<String>,
+}
+
+impl Generator {
+    /// Creates a generator for the SCPD `xml`.
+    /// The service type has to be set with [`urn`](Generator::urn).
+    pub fn new(xml: impl Into<String>) -> Self {
+        Generator {
+            xml: xml.into(),
+            urn: None,
+            name: None,
+        }
+    }
+
+    /// Reads the SCPD from a file.
+    ///
+    /// The service type is derived from the file name the way the UPnP Forum names its
+    /// description files: `RenderingControl1.xml` is
+    /// `urn:schemas-upnp-org:service:RenderingControl:1`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let xml = std::fs::read_to_string(path)?;
+
+        let mut generator = Generator::new(xml);
+        generator.urn = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(urn_from_file_stem);
+        Ok(generator)
+    }
+
+    /// Sets the service type, which is used to find the service on a device.
+    pub fn urn(mut self, urn: URN) -> Self {
+        self.urn = Some(urn);
+        self
+    }
+
+    /// Sets the name of the generated struct. Defaults to the service type, e.g.
+    /// `RenderingControl`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Generates the code.
+    pub fn generate(&self) -> Result<TokenStream, Error> {
+        let urn = self.urn.clone().ok_or(Error::MissingUrn)?;
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => upper_camel_case(urn.typ()),
+        };
+        let scpd = SCPD::from_xml_str(urn, &self.xml)?;
+
+        Ok(Service::new(&scpd, &name).generate())
+    }
+
+    /// Generates the code and writes it to `path`, e.g. in `OUT_DIR`.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let code = self.generate()?;
+        std::fs::write(path, code.to_string())?;
+        Ok(())
+    }
+}
+
+/// `RenderingControl1` -> `urn:schemas-upnp-org:service:RenderingControl:1`
+fn urn_from_file_stem(stem: &str) -> Option<URN> {
+    let typ = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    if typ.is_empty() {
+        return None;
+    }
+    let version = stem[typ.len()..].parse().unwrap_or(1);
+
+    let urn = format!("urn:schemas-upnp-org:service:{typ}:{version}");
+    urn.parse().ok()
+}
+
+struct Service<'a> {
+    scpd: &'a SCPD,
+    name: Ident,
+    /// The method of each action, by action name.
+    methods: HashMap<&'a str, Ident>,
+    /// The structs returned by actions with multiple output arguments, by action name.
+    responses: HashMap<&'a str, Ident>,
+    /// The enums generated for state variables with an `allowedValueList`, by variable name.
+    enums: HashMap<&'a str, Ident>,
+}
+
+impl<'a> Service<'a> {
+    fn new(scpd: &'a SCPD, name: &str) -> Self {
+        let mut types = Names::default();
+        let name = types.unique(name);
+
+        let mut methods = Names::reserved(&["urn", "from_device", "new", "with_client", "service"]);
+        let methods = scpd
+            .actions()
+            .iter()
+            .map(|action| {
+                (
+                    action.name().as_str(),
+                    methods.unique(&snake_case(action.name())),
+                )
+            })
+            .collect();
+        let responses = scpd
+            .actions()
+            .iter()
+            .filter(|action| action.output_arguments().count() > 1)
+            .map(|action| {
+                let response = format!("{}Response", upper_camel_case(action.name()));
+                (action.name().as_str(), types.unique(&response))
+            })
+            .collect();
+        let enums = scpd
+            .state_variables()
+            .iter()
+            .filter(|state_var| matches!(state_var.kind(), StateVariableKind::Enum(_)))
+            .filter(|state_var| is_used(scpd, state_var))
+            .map(|state_var| {
+                let name = state_var.name();
+                let name = name.strip_prefix("A_ARG_TYPE_").unwrap_or(name);
+                (state_var.name(), types.unique(&upper_camel_case(name)))
+            })
+            .collect();
+
+        Service {
+            scpd,
+            name,
+            methods,
+            responses,
+            enums,
+        }
+    }
+
+    fn generate(&self) -> TokenStream {
+        let name = &self.name;
+        let urn = self.scpd.urn();
+        let (domain, typ, version) = (urn.domain_name(), urn.typ(), urn.version());
+        let doc = format!("A typed client for the `{urn}` service.");
+
+        let methods = self.scpd.actions().iter().map(|action| self.method(action));
+        let responses = self.scpd.actions().iter().filter_map(|a| self.response(a));
+        let enums = self
+            .scpd
+            .state_variables()
+            .iter()
+            .filter(|state_var| self.enums.contains_key(state_var.name()))
+            .map(|state_var| self.enumeration(state_var));
+
+        quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone)]
+            pub struct #name {
+                service: ::rupnp::Service,
+                client: ::rupnp::Client,
+            }
+
+            impl #name {
+                /// The service type of this service.
+                pub fn urn() -> ::rupnp::ssdp::URN {
+                    ::rupnp::ssdp::URN::service(#domain, #typ, #version)
+                }
+
+                /// Looks up the service on the `device`.
+                pub fn from_device(device: &::rupnp::Device) -> ::std::option::Option<Self> {
+                    let service = device.find_service(&Self::urn())?;
+                    ::std::option::Option::Some(Self::new(service.clone()))
+                }
+
+                /// Creates a client for the `service`.
+                pub fn new(service: ::rupnp::Service) -> Self {
+                    #name {
+                        service,
+                        client: ::rupnp::Client::new(),
+                    }
+                }
+
+                /// Sets the client used to execute the actions.
+                pub fn with_client(mut self, client: ::rupnp::Client) -> Self {
+                    self.client = client;
+                    self
+                }
+
+                pub fn service(&self) -> &::rupnp::Service {
+                    &self.service
+                }
+
+                #(#methods)*
+            }
+
+            #(#responses)*
+
+            #(#enums)*
+        }
+    }
+
+    fn method(&self, action: &Action) -> TokenStream {
+        let method = &self.methods[action.name().as_str()];
+        let action_name = action.name();
+        let doc = format!("`{action}`");
+
+        let inputs = fields(action.input_arguments());
+        let params = inputs.iter().map(|(param, argument)| {
+            let ty = self.input_type(argument);
+            quote! { #param: #ty }
+        });
+        let args = inputs.iter().map(|(param, argument)| {
+            let name = argument.name();
+            quote! { .arg(#name, #param) }
+        });
+
+        let outputs = fields(action.output_arguments());
+        let (output_type, output) = match outputs.as_slice() {
+            [] => (quote! { () }, quote! { ::std::result::Result::Ok(()) }),
+            [(_, argument)] => {
+                let name = argument.name();
+                let ty = self.output_type(argument);
+                (ty.clone(), quote! { response.get_as::<#ty>(#name) })
+            }
+            arguments => {
+                let response = &self.responses[action.name().as_str()];
+                let fields = arguments.iter().map(|(field, argument)| {
+                    let name = argument.name();
+                    let ty = self.output_type(argument);
+                    quote! { #field: response.get_as::<#ty>(#name)? }
+                });
+                let output = quote! { ::std::result::Result::Ok(#response { #(#fields,)* }) };
+                (quote! { #response }, output)
+            }
+        };
+        let response = if outputs.is_empty() {
+            quote! { _response }
+        } else {
+            quote! { response }
+        };
+
+        quote! {
+            #[doc = #doc]
+            pub async fn #method(&self, #(#params),*) -> ::std::result::Result<#output_type, ::rupnp::Error> {
+                let args = ::rupnp::ActionArgs::new() #(#args)*;
+                let #response = self
+                    .client
+                    .call(&self.service, #action_name, &args)
+                    .await?;
+                #output
+            }
+        }
+    }
+
+    /// The struct returned by actions with multiple output arguments.
+    fn response(&self, action: &Action) -> Option<TokenStream> {
+        let name = self.responses.get(action.name().as_str())?;
+        let doc = format!("The output arguments of `{}`.", action.name());
+        let fields = fields(action.output_arguments());
+        let fields = fields.iter().map(|(field, argument)| {
+            let doc = format!("`{}`", argument.name());
+            let ty = self.output_type(argument);
+            quote! {
+                #[doc = #doc]
+                pub #field: #ty
+            }
+        });
+
+        Some(quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct #name {
+                #(#fields,)*
+            }
+        })
+    }
+
+    fn enumeration(&self, state_var: &StateVariable) -> TokenStream {
+        let name = &self.enums[state_var.name()];
+        let doc = format!("The allowed values of `{}`.", state_var.name());
+        let values = match state_var.kind() {
+            StateVariableKind::Enum(values) => values,
+            _ => unreachable!("only generated for enumerations"),
+        };
+
+        let mut names = Names::reserved(&["Other"]);
+        let variants: Vec<Ident> = values
+            .iter()
+            .map(|value| match upper_camel_case(value) {
+                v if v.is_empty() => names.unique("Empty"),
+                v if v.starts_with(|c: char| c.is_ascii_digit()) => names.unique(&format!("V{v}")),
+                v => names.unique(&v),
+            })
+            .collect();
+
+        let docs = values.iter().map(|value| format!("`{value}`"));
+        let as_str = variants.iter().zip(values).map(|(variant, value)| {
+            quote! { #name::#variant => #value, }
+        });
+        let from_str = variants.iter().zip(values).map(|(variant, value)| {
+            quote! { #value => #name::#variant, }
+        });
+
+        quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+            pub enum #name {
+                #(
+                    #[doc = #docs]
+                    #variants,
+                )*
+                /// A value which is not in the `allowedValueList`, e.g. a vendor extension.
+                Other(::std::string::String),
+            }
+
+            impl #name {
+                pub fn as_str(&self) -> &str {
+                    match self {
+                        #(#as_str)*
+                        #name::Other(value) => value,
+                    }
+                }
+            }
+
+            impl ::std::fmt::Display for #name {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    f.write_str(self.as_str())
+                }
+            }
+
+            impl ::std::convert::From<&str> for #name {
+                fn from(value: &str) -> Self {
+                    match value {
+                        #(#from_str)*
+                        other => #name::Other(other.to_string()),
+                    }
+                }
+            }
+
+            impl ::std::convert::From<#name> for ::rupnp::ArgValue {
+                fn from(value: #name) -> Self {
+                    ::rupnp::ArgValue::from(value.as_str())
+                }
+            }
+
+            impl ::rupnp::FromArgValue for #name {
+                fn from_arg_value(value: &str) -> ::std::result::Result<Self, ::rupnp::Error> {
+                    ::std::result::Result::Ok(#name::from(value))
+                }
+            }
+        }
+    }
+
+    fn input_type(&self, argument: &Argument) -> TokenStream {
+        match self.enums.get(argument.related_state_variable().name()) {
+            Some(name) => quote! { #name },
+            None => match rust_type(argument.related_state_variable().datatype()) {
+                RustType::String => quote! { &str },
+                RustType::Other(ty) => ty,
+            },
+        }
+    }
+
+    fn output_type(&self, argument: &Argument) -> TokenStream {
+        match self.enums.get(argument.related_state_variable().name()) {
+            Some(name) => quote! { #name },
+            None => match rust_type(argument.related_state_variable().datatype()) {
+                RustType::String => quote! { ::std::string::String },
+                RustType::Other(ty) => ty,
+            },
+        }
+    }
+}
+
+fn is_used(scpd: &SCPD, state_var: &StateVariable) -> bool {
+    scpd.actions().iter().any(|action| {
+        action
+            .input_arguments()
+            .chain(action.output_arguments())
+            .any(|argument| argument.related_state_variable().name() == state_var.name())
+    })
+}
+
+/// The parameters or response fields for `arguments`.
+fn fields<'a>(arguments: impl Iterator<Item = &'a Argument>) -> Vec<(Ident, &'a Argument)> {
+    let mut names = Names::default();
+    arguments
+        .map(|argument| (names.unique(&snake_case(argument.name())), argument))
+        .collect()
+}
+
+/// Hands out identifiers which are unique within one namespace, e.g. the variants of an enum.
+///
+/// Distinct UPnP names can map to the same Rust name (`A_ARG_TYPE_Channel` and `Channel`,
+/// or `LF` and `Lf`), so names which are already taken get a numeric suffix.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    fn reserved(names: &[&str]) -> Self {
+        Names(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    fn unique(&mut self, name: &str) -> Ident {
+        let mut candidate = ident(name);
+        let mut n = 2;
+        while !self.0.insert(candidate.to_string()) {
+            candidate = ident(&format!("{name}{n}"));
+            n += 1;
+        }
+        candidate
+    }
+}
+
+enum RustType {
+    /// Passed as `&str`, returned as `String`.
+    String,
+    Other(TokenStream),
+}
+
+fn rust_type(datatype: DataType) -> RustType {
+    let ty = match datatype {
+        DataType::ui1 => quote! { u8 },
+        DataType::ui2 => quote! { u16 },
+        DataType::ui4 => quote! { u32 },
+        DataType::ui8 => quote! { u64 },
+        DataType::i1 => quote! { i8 },
+        DataType::i2 => quote! { i16 },
+        DataType::i4 => quote! { i32 },
+        DataType::int => quote! { i64 },
+        DataType::r4 => quote! { f32 },
+        DataType::r8 | DataType::Number | DataType::Float | DataType::Fixed14_4 => {
+            quote! { f64 }
+        }
+        DataType::Char => quote! { char },
+        DataType::Boolean => quote! { bool },
+        DataType::Date => quote! { ::rupnp::scpd::Date },
+        DataType::DateTime => quote! { ::rupnp::scpd::DateTime },
+        DataType::Time => quote! { ::rupnp::scpd::Time },
+        // everything else is passed through in its textual representation
+        _ => return RustType::String,
+    };
+    RustType::Other(ty)
+}
+
+/// `SetAVTransportURI` -> `set_av_transport_uri`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            continue;
+        }
+
+        if c.is_ascii_uppercase() && i > 0 && !snake.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake.trim_end_matches('_').to_string()
+}
+
+/// `NO_MEDIA_PRESENT` -> `NoMediaPresent`
+fn upper_camel_case(name: &str) -> String {
+    snake_case(name)
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// Creates an identifier, escaping keywords and names which don't start with a letter.
+fn ident(name: &str) -> Ident {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do",
+        "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        Ident::new(&format!("_{name}"), Span::call_site())
+    } else if KEYWORDS.contains(&name) {
+        Ident::new(&format!("{name}_"), Span::call_site())
+    } else {
+        Ident::new(name, Span::call_site())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("SetAVTransportURI"), "set_av_transport_uri");
+        assert_eq!(snake_case("InstanceID"), "instance_id");
+        assert_eq!(snake_case("GetMediaInfo_Ext"), "get_media_info_ext");
+        assert_eq!(upper_camel_case("NO_MEDIA_PRESENT"), "NoMediaPresent");
+        assert_eq!(upper_camel_case("x-rincon-queue"), "XRinconQueue");
+        assert_eq!(ident("type").to_string(), "type_");
+    }
+
+    #[test]
+    fn urn_from_file_name() {
+        assert_eq!(
+            urn_from_file_stem("RenderingControl1"),
+            Some(URN::service("schemas-upnp-org", "RenderingControl", 1))
+        );
+        assert_eq!(
+            urn_from_file_stem("AVTransport"),
+            Some(URN::service("schemas-upnp-org", "AVTransport", 1))
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! The [`upnp_service!`] macro, generating a typed [`rupnp`](https://docs.rs/rupnp) client from
+//! an SCPD file. See [`rupnp-codegen`](https://docs.rs/rupnp-codegen) for the generated code.
+
+use proc_macro::TokenStream;
+use rupnp_codegen::{Generator, URN};
+use std::path::PathBuf;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Ident, LitStr, Token,
+};
+
+/// Generates a client for the service described by an SCPD file.
+///
+/// The path is relative to the directory of the crate's `Cargo.toml`.
+/// The service type is derived from the file name (`RenderingControl1.xml` is
+/// `urn:schemas-upnp-org:service:RenderingControl:1`), unless it is set with `urn = "..."`.
+/// The name of the generated struct can be changed with `name = "..."`.
+///
+/// # Example usage:
+/// ```rust,ignore
+/// rupnp_macros::upnp_service!("RenderingControl1.xml");
+/// rupnp_macros::upnp_service!(
+///     "scpd/GroupRenderingControl.xml",
+///     urn = "urn:schemas-upnp-org:service:GroupRenderingControl:1",
+///     name = "GroupVolume"
+/// );
+///
+/// # async fn volume(device: rupnp::Device) -> Result<(), rupnp::Error> {
+/// let rendering_control = RenderingControl::from_device(&device).unwrap();
+/// rendering_control.set_volume(0, Channel::Master, 25).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[proc_macro]
+pub fn upnp_service(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+
+    match input.expand() {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct Input {
+    path: LitStr,
+    urn: Option<LitStr>,
+    name: Option<LitStr>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut parsed = Input {
+            path: input.parse()?,
+            urn: None,
+            name: None,
+        };
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            match key.to_string().as_str() {
+                "urn" => parsed.urn = Some(value),
+                "name" => parsed.name = Some(value),
+                _ => return Err(syn::Error::new(key.span(), "expected `urn` or `name`")),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl Input {
+    fn expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let error = |message: String| syn::Error::new(self.path.span(), message);
+
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+        let path = PathBuf::from(manifest_dir).join(self.path.value());
+
+        let mut generator = Generator::from_file(&path)
+            .map_err(|err| error(format!("{}: {err}", path.display())))?;
+        if let Some(urn) = &self.urn {
+            let urn: URN = urn
+                .value()
+                .parse()
+                .map_err(|err| syn::Error::new(urn.span(), format!("invalid urn: {err}")))?;
+            generator = generator.urn(urn);
+        }
+        if let Some(name) = &self.name {
+            let ident: Ident = syn::parse_str(&name.value()).map_err(|_| {
+                syn::Error::new(name.span(), "the name has to be a valid Rust identifier")
+            })?;
+            generator = generator.name(ident.to_string());
+        }
+
+        let code = generator.generate().map_err(|err| error(err.to_string()))?;
+
+        // makes cargo recompile when the file changes
+        let path = path.to_string_lossy();
+        Ok(quote::quote! {
+            const _: &[u8] = include_bytes!(#path);
+            #code
+        })
+    }
+}
@@ -0,0 +1,33 @@
+//! Distinct UPnP names which map to the same Rust name still generate code that compiles.
+
+use rupnp::FromArgValue;
+
+rupnp_macros::upnp_service!("tests/Collisions1.xml");
+
+#[allow(dead_code)]
+async fn signatures(collisions: Collisions) -> Result<(), rupnp::Error> {
+    let GetChannelsResponse {
+        status,
+        channel,
+        channel2,
+    } = collisions
+        .get_channels(Channel::Master, Channel2::Lf)
+        .await?;
+    let _: (GetChannelsResponse2, Channel, Channel2) = (status, channel, channel2);
+    collisions.get_channels2().await?;
+    collisions.service2().await?;
+    Ok(())
+}
+
+#[test]
+fn disambiguated_names() {
+    assert_eq!(Channel::from("Master"), Channel::Master);
+    assert_eq!(Channel::from("MASTER"), Channel::Master2);
+    assert_eq!(Channel::from("Master2"), Channel::Master22);
+    assert_eq!(Channel::from("Other"), Channel::Other2);
+    assert_eq!(Channel::from("LFE"), Channel::Other("LFE".to_string()));
+    assert_eq!(Channel::Master2.as_str(), "MASTER");
+
+    assert_eq!(Channel2::from_arg_value("RF").unwrap(), Channel2::Rf);
+    assert_eq!(GetChannelsResponse2::from("OK"), GetChannelsResponse2::Ok);
+}
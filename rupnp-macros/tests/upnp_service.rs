@@ -0,0 +1,47 @@
+use rupnp::{ActionArgs, ArgValue, FromArgValue};
+
+rupnp_macros::upnp_service!("tests/RenderingControl1.xml");
+
+mod renamed {
+    rupnp_macros::upnp_service!(
+        "tests/RenderingControl1.xml",
+        urn = "urn:schemas-sonos-com:service:RenderingControl:1",
+        name = "SonosRenderingControl",
+    );
+}
+
+#[allow(dead_code)]
+async fn signatures(rendering_control: RenderingControl) -> Result<(), rupnp::Error> {
+    let volume: u16 = rendering_control.get_volume(0, Channel::Master).await?;
+    rendering_control
+        .set_volume(0, Channel::Lf, volume + 1)
+        .await?;
+    let GetVolumeDbRangeResponse {
+        min_value,
+        max_value,
+    } = rendering_control
+        .get_volume_db_range(0, Channel::Master)
+        .await?;
+    let _: (i16, i16) = (min_value, max_value);
+    let _: bool = rendering_control.get_mute(0, Channel::Rf).await?;
+    Ok(())
+}
+
+#[test]
+fn generated_service() {
+    assert_eq!(
+        RenderingControl::urn().to_string(),
+        "urn:schemas-upnp-org:service:RenderingControl:1"
+    );
+    assert_eq!(
+        renamed::SonosRenderingControl::urn().to_string(),
+        "urn:schemas-sonos-com:service:RenderingControl:1"
+    );
+
+    assert_eq!(Channel::from("LF"), Channel::Lf);
+    assert_eq!(Channel::from("LFE"), Channel::Other("LFE".to_string()));
+    assert_eq!(Channel::from_arg_value("RF").unwrap(), Channel::Rf);
+
+    let args = ActionArgs::new().arg("Channel", Channel::Master);
+    assert_eq!(args.get("Channel"), Some(&ArgValue::from("Master")));
+}
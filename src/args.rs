@@ -0,0 +1,391 @@
+use crate::{
+    scpd::{ArgumentError, DataType, Date, DateTime, Time, Value},
+    utils, Error, Result,
+};
+use std::{fmt, iter::FromIterator};
+
+/// The value of a single action argument, see [`ActionArgs`].
+///
+/// Values are stored in their textual UPnP representation, e.g. booleans as `1` or `0`.
+/// They are escaped when the request is built, so any string can be passed as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgValue(String);
+
+impl ArgValue {
+    /// The value as it is sent, before it is escaped.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ArgValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for ArgValue {
+    fn from(value: String) -> Self {
+        ArgValue(value)
+    }
+}
+impl From<&String> for ArgValue {
+    fn from(value: &String) -> Self {
+        ArgValue(value.clone())
+    }
+}
+impl From<&str> for ArgValue {
+    fn from(value: &str) -> Self {
+        ArgValue(value.to_string())
+    }
+}
+impl From<Value> for ArgValue {
+    fn from(value: Value) -> Self {
+        ArgValue(value.to_string())
+    }
+}
+impl From<&Value> for ArgValue {
+    fn from(value: &Value) -> Self {
+        ArgValue(value.to_string())
+    }
+}
+impl From<bool> for ArgValue {
+    fn from(value: bool) -> Self {
+        ArgValue(if value { "1" } else { "0" }.to_string())
+    }
+}
+
+macro_rules! impl_from_display {
+    ( $($ty:ty),* ) => {
+        $(
+            impl From<$ty> for ArgValue {
+                fn from(value: $ty) -> Self {
+                    ArgValue(value.to_string())
+                }
+            }
+        )*
+    };
+}
+impl_from_display!(
+    u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, char, Date, DateTime, Time
+);
+
+/// The ordered list of input arguments of an action.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn set_uri(service: rupnp::Service, device: rupnp::Device) -> Result<(), rupnp::Error> {
+/// use rupnp::ActionArgs;
+///
+/// let metadata = r#"<DIDL-Lite xmlns="urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/">...</DIDL-Lite>"#;
+/// let args = ActionArgs::new()
+///     .arg("InstanceID", 0)
+///     .arg("CurrentURI", "http://192.168.1.10/track.mp3?a=1&b=2")
+///     .arg("CurrentURIMetaData", metadata);
+///
+/// service.call("SetAVTransportURI", &args).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionArgs {
+    args: Vec<(String, ArgValue)>,
+}
+
+impl ActionArgs {
+    /// Creates an empty argument list.
+    pub fn new() -> Self {
+        ActionArgs::default()
+    }
+
+    /// Appends an argument. See [`push`](ActionArgs::push).
+    pub fn arg(mut self, name: impl Into<String>, value: impl Into<ArgValue>) -> Self {
+        self.push(name, value);
+        self
+    }
+
+    /// Appends an argument, failing if its name is invalid. See [`try_push`](ActionArgs::try_push).
+    pub fn try_arg(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<ArgValue>,
+    ) -> Result<Self, ArgumentError> {
+        self.try_push(name, value)?;
+        Ok(self)
+    }
+
+    /// Appends an argument.
+    ///
+    /// The `name` has to be a valid xml element name without a namespace prefix, e.g. it can't
+    /// contain `<` or spaces. An argument with an invalid name is still added, but calling an
+    /// action with these arguments fails with [`ArgumentError::InvalidName`].
+    /// Use [`try_push`](ActionArgs::try_push) to reject it right away.
+    pub fn push(&mut self, name: impl Into<String>, value: impl Into<ArgValue>) {
+        self.args.push((name.into(), value.into()));
+    }
+
+    /// Appends an argument if its `name` is a valid xml element name, see
+    /// [`push`](ActionArgs::push).
+    pub fn try_push(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<ArgValue>,
+    ) -> Result<(), ArgumentError> {
+        let name = name.into();
+        if !utils::is_ncname(&name) {
+            return Err(ArgumentError::InvalidName(name));
+        }
+        self.args.push((name, value.into()));
+        Ok(())
+    }
+
+    /// Returns the value of the first argument called `name`.
+    pub fn get(&self, name: &str) -> Option<&ArgValue> {
+        self.args
+            .iter()
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Iterates over the arguments in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ArgValue)> {
+        self.args.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// The number of arguments, including those with an invalid name.
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Whether no argument was added.
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// The first name which can't be used as an xml element name.
+    pub(crate) fn invalid_name(&self) -> Option<&str> {
+        self.args
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .find(|name| !utils::is_ncname(name))
+    }
+
+    /// The arguments as the xml payload of a SOAP request, with all values escaped.
+    pub(crate) fn to_xml(&self) -> Result<String, ArgumentError> {
+        if let Some(name) = self.invalid_name() {
+            return Err(ArgumentError::InvalidName(name.to_string()));
+        }
+
+        let mut xml = String::new();
+        for (name, value) in &self.args {
+            xml.push_str(&format!(
+                "<{name}>{}</{name}>",
+                crate::utils::escape_xml(value.as_str())
+            ));
+        }
+        Ok(xml)
+    }
+}
+
+impl<N: Into<String>, V: Into<ArgValue>> FromIterator<(N, V)> for ActionArgs {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> Self {
+        let mut args = ActionArgs::new();
+        for (name, value) in iter {
+            args.push(name, value);
+        }
+        args
+    }
+}
+
+/// The output arguments of an action, in the order the device sent them.
+///
+/// Elements without text are kept as empty strings.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn get_volume(service: rupnp::Service, device: rupnp::Device) -> Result<(), rupnp::Error> {
+/// use rupnp::ActionArgs;
+///
+/// let args = ActionArgs::new().arg("InstanceID", 0).arg("Channel", "Master");
+/// let response = service.call("GetVolume", &args).await?;
+///
+/// let volume: u16 = response.get_as("CurrentVolume")?;
+/// for (name, value) in response.iter() {
+///     println!("{name} = {value}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActionResponse {
+    action: String,
+    values: Vec<(String, String)>,
+    xml: String,
+}
+
+impl ActionResponse {
+    pub(crate) fn new(action: &str, values: Vec<(String, String)>, xml: String) -> Self {
+        ActionResponse {
+            action: action.to_string(),
+            values,
+            xml,
+        }
+    }
+
+    /// Returns the value of the first output argument called `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the output argument called `name`, parsed as `T`.
+    /// Fails if the argument is missing or can't be parsed.
+    pub fn get_as<T: FromArgValue>(&self, name: &str) -> Result<T> {
+        let value = self.get(name).ok_or_else(|| {
+            Error::XmlMissingElement(format!("{}Response", self.action), name.to_string())
+        })?;
+        T::from_arg_value(value)
+    }
+
+    /// Iterates over the output arguments in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The number of output arguments.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the response has no output arguments.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The raw SOAP response.
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+}
+
+/// Types which can be parsed from the value of an output argument,
+/// see [`ActionResponse::get_as`].
+///
+/// Unlike their `FromStr` implementations, booleans also accept the UPnP forms `1`, `0`, `yes`
+/// and `no`.
+pub trait FromArgValue: Sized {
+    fn from_arg_value(value: &str) -> Result<Self>;
+}
+
+impl FromArgValue for String {
+    fn from_arg_value(value: &str) -> Result<Self> {
+        Ok(value.to_string())
+    }
+}
+impl FromArgValue for bool {
+    fn from_arg_value(value: &str) -> Result<Self> {
+        match Value::parse(DataType::Boolean, value).map_err(Error::invalid_response)? {
+            Value::Boolean(value) => Ok(value),
+            _ => unreachable!(),
+        }
+    }
+}
+
+macro_rules! impl_from_arg_value_fromstr {
+    ( $($ty:ty),* ) => {
+        $(
+            impl FromArgValue for $ty {
+                fn from_arg_value(value: &str) -> Result<Self> {
+                    value.trim().parse().map_err(Error::invalid_response)
+                }
+            }
+        )*
+    };
+}
+impl_from_arg_value_fromstr!(
+    u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, char, Date, DateTime, Time
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{ActionArgs, ActionResponse};
+    use crate::scpd::ArgumentError;
+
+    #[test]
+    fn args_are_escaped_in_order() {
+        let args = ActionArgs::new()
+            .arg("InstanceID", 0u32)
+            .arg("CurrentURI", "http://host/a?b=1&c=2")
+            .arg(
+                "CurrentURIMetaData",
+                r#"<DIDL-Lite id="1">Rock 'n' Roll</DIDL-Lite>"#,
+            )
+            .arg("Mute", true);
+
+        assert_eq!(
+            args.to_xml().unwrap(),
+            "<InstanceID>0</InstanceID>\
+             <CurrentURI>http://host/a?b=1&amp;c=2</CurrentURI>\
+             <CurrentURIMetaData>&lt;DIDL-Lite id=&quot;1&quot;&gt;Rock &apos;n&apos; Roll&lt;/DIDL-Lite&gt;</CurrentURIMetaData>\
+             <Mute>1</Mute>"
+        );
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let args = ActionArgs::new()
+            .arg("InstanceID", 0u32)
+            .arg("Desired Volume", 10u16)
+            .arg("a><b", "")
+            .arg("_x-1.\u{e9}", "");
+        assert_eq!(args.len(), 4);
+        assert_eq!(
+            args.get("Desired Volume").map(|value| value.as_str()),
+            Some("10")
+        );
+        assert_eq!(
+            args.to_xml(),
+            Err(ArgumentError::InvalidName("Desired Volume".to_string()))
+        );
+
+        let args = ActionArgs::new().try_arg("InstanceID", 0u32).unwrap();
+        assert_eq!(
+            args.try_arg("a><b", ""),
+            Err(ArgumentError::InvalidName("a><b".to_string()))
+        );
+
+        for name in ["", "1st", "-a", "u:Name", "a/b", "a&b"] {
+            let args: ActionArgs = std::iter::once((name, "")).collect();
+            assert!(args.to_xml().is_err(), "{:?} should be rejected", name);
+        }
+        let args: ActionArgs = std::iter::once(("_x-1.\u{e9}", "")).collect();
+        assert_eq!(args.to_xml().unwrap(), "<_x-1.\u{e9}></_x-1.\u{e9}>");
+    }
+
+    #[test]
+    fn response_values() {
+        let response = ActionResponse::new(
+            "GetVolume",
+            vec![
+                ("CurrentVolume".to_string(), "25".to_string()),
+                ("Mute".to_string(), "1".to_string()),
+                ("Result".to_string(), String::new()),
+            ],
+            String::new(),
+        );
+
+        assert_eq!(response.get_as::<u16>("CurrentVolume").unwrap(), 25);
+        assert!(response.get_as::<bool>("Mute").unwrap());
+        assert_eq!(response.get("Result"), Some(""));
+        assert!(response.get_as::<u8>("Result").is_err());
+        assert!(response.get_as::<String>("Missing").is_err());
+        assert_eq!(
+            response.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["CurrentVolume", "Mute", "Result"]
+        );
+    }
+}
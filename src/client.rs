@@ -0,0 +1,723 @@
+use crate::{
+    error::TimeoutPhase,
+    httpu,
+    recording::{self, Replay, Transport},
+    scpd::SCPD,
+    utils::HttpResponseExt,
+    ActionArgs, ActionResponse, Device, Error, Fixture, MulticastGroup, Recorder, Result, Service,
+};
+#[cfg(feature = "subscribe")]
+use crate::{eventing, Event, EventServer, Subscription};
+use bytes::{Bytes, BytesMut};
+use futures_core::stream::Stream;
+use futures_util::{
+    future::{self, Either},
+    stream::{StreamExt, TryStreamExt},
+};
+use http::{header, HeaderValue, Request, Response, StatusCode, Uri};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper_util::{
+    client::legacy::{
+        connect::{capture_connection, HttpConnector},
+        Client as HyperClient,
+    },
+    rt::{TokioExecutor, TokioTimer},
+};
+use ssdp_client::SearchTarget;
+use std::{error::Error as _, future::Future, io, sync::Arc, time::Duration};
+
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A UPnP control point client.
+///
+/// It holds a pool of HTTP connections which is reused by all requests made through it, so
+/// repeatedly calling actions on the same device doesn't open a new connection every time.
+/// Cloning a `Client` is cheap and shares the connection pool.
+///
+/// The functions like [`discover`](crate::discover) or [`Service::action`] are convenience
+/// wrappers which use a new `Client` with the default settings for every call.
+///
+/// Requests which take too long fail with [`Error::Timeout`], see
+/// [`ClientBuilder::connect_timeout`], [`ClientBuilder::read_timeout`] and
+/// [`ClientBuilder::timeout`]. Dropping the future of a request cancels it.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn client() -> Result<(), rupnp::Error> {
+/// # let some_url = unimplemented!();
+/// use rupnp::{ssdp::URN, Client};
+///
+/// let client = Client::builder()
+///     .user_agent("MyApp/1.0 UPnP/2.0 rupnp/3.0")?
+///     .build();
+///
+/// let device = client.device(some_url).await?;
+/// let service = device
+///     .find_service(&URN::service("schemas-upnp-org", "RenderingControl", 1))
+///     .expect("service exists");
+///
+/// let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
+/// for _ in 0..10 {
+///     let response = client.action(service, "GetVolume", args).await?;
+///     println!("{}", response.get("CurrentVolume").unwrap());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: HyperClient<HttpConnector, Full<Bytes>>,
+    user_agent: Option<HeaderValue>,
+    max_body_size: usize,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "subscribe")]
+    event_server: Option<EventServer>,
+    transport: Transport,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::builder().build()
+    }
+}
+
+/// Builder for a [`Client`] with custom settings.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    user_agent: Option<HeaderValue>,
+    max_body_size: usize,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "subscribe")]
+    event_server: Option<EventServer>,
+    transport: Transport,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        let user_agent = format!(
+            "{} UPnP/2.0 rupnp/{}",
+            std::env::consts::OS,
+            env!("CARGO_PKG_VERSION")
+        );
+
+        ClientBuilder {
+            user_agent: HeaderValue::from_str(&user_agent).ok(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: usize::MAX,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            read_timeout: Some(DEFAULT_READ_TIMEOUT),
+            timeout: None,
+            #[cfg(feature = "subscribe")]
+            event_server: None,
+            transport: Transport::Network,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// The `USER-AGENT` header sent with every request.
+    /// Defaults to `<os> UPnP/2.0 rupnp/<version>`.
+    ///
+    /// Fails if the `user_agent` is not a valid header value, e.g. because it contains a
+    /// line break.
+    pub fn user_agent(mut self, user_agent: &str) -> Result<Self> {
+        let user_agent = HeaderValue::from_str(user_agent)
+            .map_err(|_| Error::ParseError("the user agent is not a valid header value"))?;
+        self.user_agent = Some(user_agent);
+        Ok(self)
+    }
+
+    /// Don't send a `USER-AGENT` header.
+    pub fn no_user_agent(mut self) -> Self {
+        self.user_agent = None;
+        self
+    }
+
+    /// Responses with a larger body are rejected with [`Error::ResponseTooLarge`].
+    /// Defaults to 16 MiB.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// How long idle connections are kept open. Defaults to 90 seconds, `None` keeps them
+    /// open until the device closes them.
+    pub fn pool_idle_timeout(mut self, pool_idle_timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = pool_idle_timeout;
+        self
+    }
+
+    /// How many idle connections are kept per device. Defaults to no limit.
+    pub fn pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
+        self.pool_max_idle_per_host = pool_max_idle_per_host;
+        self
+    }
+
+    /// How long to wait for a TCP connection to be established. Defaults to 10 seconds.
+    pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// How long to wait for the response headers once connected, and then for each chunk of
+    /// the response body. Defaults to 30 seconds.
+    pub fn read_timeout(mut self, read_timeout: Option<Duration>) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// How long a request may take as a whole, including connecting and reading the body.
+    /// Defaults to no limit.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Receive the events of all subscriptions made through the client on a shared
+    /// [`EventServer`], instead of binding a new port for each.
+    #[cfg(feature = "subscribe")]
+    pub fn event_server(mut self, event_server: EventServer) -> Self {
+        self.event_server = Some(event_server);
+        self
+    }
+
+    /// Record every request and response, and the events of subscriptions, to `recorder`.
+    /// See [`Fixture`].
+    pub fn record(mut self, recorder: Recorder) -> Self {
+        self.transport = Transport::Record(recorder);
+        self
+    }
+
+    /// Answer requests with the responses recorded in `fixture` instead of sending them to
+    /// the device. Requests are matched by method, URL and `SOAPAction`, those which weren't
+    /// recorded fail with `404 Not Found`. Subscriptions receive the recorded events.
+    pub fn replay(mut self, fixture: Fixture) -> Self {
+        self.transport = Transport::Replay(Arc::new(Replay::new(fixture)));
+        self
+    }
+
+    /// Creates the client. Settings which weren't changed keep their defaults: a
+    /// `<os> UPnP/2.0 rupnp/<version>` user agent, a 16 MiB body limit, idle connections kept
+    /// for 90 seconds, a 10 second connect and 30 second read timeout, no total timeout, a new
+    /// event listener per subscription and requests sent over the network.
+    pub fn build(self) -> Client {
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(self.connect_timeout);
+
+        let http = HyperClient::builder(TokioExecutor::new())
+            .pool_timer(TokioTimer::new())
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .build(connector);
+
+        Client {
+            http,
+            user_agent: self.user_agent,
+            max_body_size: self.max_body_size,
+            read_timeout: self.read_timeout,
+            timeout: self.timeout,
+            #[cfg(feature = "subscribe")]
+            event_server: self.event_server,
+            transport: self.transport,
+        }
+    }
+}
+
+impl Client {
+    /// Creates a client with the default settings.
+    pub fn new() -> Self {
+        Client::default()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Returns a client sharing the connection pool of this one, but with a different total
+    /// timeout. Useful for single requests which are known to be slow or urgent.
+    ///
+    /// ```rust,no_run
+    /// # async fn with_timeout(client: rupnp::Client, device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+    /// use std::time::Duration;
+    ///
+    /// let quick = client.with_timeout(Some(Duration::from_millis(500)));
+    /// let response = quick.action(&service, "GetTransportInfo", "<InstanceID>0</InstanceID>").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout(&self, timeout: Option<Duration>) -> Client {
+        Client {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    /// Creates a UPnP device from the given url.
+    /// See [`Device::from_url`].
+    pub async fn device(&self, url: Uri) -> Result<Device> {
+        self.device_with_properties(url, &[]).await
+    }
+
+    /// Creates a UPnP device from the given url, defining extra device properties.
+    /// See [`Device::from_url_and_properties`].
+    pub async fn device_with_properties(&self, url: Uri, extra_keys: &[&str]) -> Result<Device> {
+        let body = self.get(url.clone()).await?;
+        Device::from_xml(url, &body, extra_keys)
+    }
+
+    /// Discovers UPnP devices on the network.
+    /// See [`discover`](crate::discover).
+    pub async fn discover(
+        &self,
+        search_target: &SearchTarget,
+        timeout: Duration,
+        ttl: Option<u32>,
+    ) -> Result<impl Stream<Item = Result<Device>>> {
+        self.discover_with_properties(search_target, timeout, ttl, &[])
+            .await
+    }
+
+    /// Discovers UPnP devices on the network and saves extra_fields in device descriptions.
+    /// See [`discover_with_properties`](crate::discover_with_properties).
+    pub async fn discover_with_properties<'a>(
+        &self,
+        search_target: &SearchTarget,
+        timeout: Duration,
+        ttl: Option<u32>,
+        extra_keys: &'a [&'a str],
+    ) -> Result<impl Stream<Item = Result<Device>> + 'a> {
+        let client = self.clone();
+
+        Ok(ssdp_client::search(search_target, timeout, 3, ttl)
+            .await?
+            .map_err(Error::SSDPError)
+            .map(|res| Ok(res?.location().parse()?))
+            .and_then(move |url| {
+                let client = client.clone();
+                async move { client.device_with_properties(url, extra_keys).await }
+            }))
+    }
+
+    /// Discovers UPnP devices by searching on the given multicast group.
+    /// See [`discover_on`](crate::discover_on).
+    pub async fn discover_on(
+        &self,
+        group: MulticastGroup,
+        search_target: &SearchTarget,
+        timeout: Duration,
+        ttl: Option<u32>,
+    ) -> Result<impl Stream<Item = Result<Device>>> {
+        let client = self.clone();
+
+        Ok(httpu::search(group.addr(), search_target, timeout, 3, ttl)
+            .await?
+            .and_then(move |response| {
+                let client = client.clone();
+                async move { client.device(response.location).await }
+            }))
+    }
+
+    /// Fetches the [`SCPD`](scpd/struct.SCPD.html) of a service.
+    /// See [`Service::scpd`].
+    pub async fn scpd(&self, service: &Service) -> Result<SCPD> {
+        let body = self.get(service.scpd_url().clone()).await?;
+        SCPD::from_xml(&body, service.service_type().clone())
+    }
+
+    /// Execute some UPnP Action on a service.
+    /// See [`Service::action`].
+    pub async fn action(
+        &self,
+        service: &Service,
+        action: &str,
+        payload: &str,
+    ) -> Result<ActionResponse> {
+        let request = service.action_request(action, payload);
+        let response = self.request(request).await?;
+
+        // devices report failed actions as a SOAP fault with status 500
+        if response.status() == StatusCode::INTERNAL_SERVER_ERROR {
+            if let Err(err @ Error::UPnPError(_)) =
+                Service::parse_action_response(action, response.body())
+            {
+                return Err(err);
+            }
+        }
+        let response = response.err_if_not_200()?;
+
+        Service::parse_action_response(action, response.body())
+    }
+
+    /// Execute some UPnP Action on a service, with escaped arguments.
+    /// See [`Service::call`].
+    pub async fn call(
+        &self,
+        service: &Service,
+        action: &str,
+        args: &ActionArgs,
+    ) -> Result<ActionResponse> {
+        self.action(service, action, &args.to_xml()?).await
+    }
+
+    /// Execute some UPnP Action on a service after checking the arguments against its
+    /// [`SCPD`](scpd/struct.SCPD.html).
+    /// See [`Service::call_validated`].
+    pub async fn call_validated(
+        &self,
+        service: &Service,
+        scpd: &SCPD,
+        action: &str,
+        args: &ActionArgs,
+    ) -> Result<ActionResponse> {
+        scpd.validate(action, args)?;
+        self.call(service, action, args).await
+    }
+
+    /// Subscribe for state variable changes.
+    /// See [`Service::subscribe`].
+    #[cfg(feature = "subscribe")]
+    pub async fn subscribe(
+        &self,
+        service: &Service,
+        timeout_secs: u32,
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        service.subscribe_with(self, timeout_secs, &[]).await
+    }
+
+    /// Subscribe for changes of only some state variables.
+    /// See [`Service::subscribe_to`].
+    #[cfg(feature = "subscribe")]
+    pub async fn subscribe_to(
+        &self,
+        service: &Service,
+        timeout_secs: u32,
+        state_variables: &[&str],
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        service
+            .subscribe_with(self, timeout_secs, state_variables)
+            .await
+    }
+
+    /// Subscribe for state variable changes and keep the subscription alive in the background.
+    /// See [`Service::subscription`].
+    #[cfg(feature = "subscribe")]
+    pub async fn subscription(&self, service: &Service, timeout_secs: u32) -> Result<Subscription> {
+        self.subscription_to(service, timeout_secs, &[]).await
+    }
+
+    /// Subscribe for changes of only some state variables and keep the subscription alive in
+    /// the background.
+    /// See [`Service::subscription_to`].
+    #[cfg(feature = "subscribe")]
+    pub async fn subscription_to(
+        &self,
+        service: &Service,
+        timeout_secs: u32,
+        state_variables: &[&str],
+    ) -> Result<Subscription> {
+        let state_variables = eventing::state_variable_names(state_variables)?;
+        Subscription::new(self.clone(), service.clone(), timeout_secs, state_variables).await
+    }
+
+    /// Returns a callback URL for a new subscription to `service` and the stream of events sent
+    /// to it.
+    #[cfg(feature = "subscribe")]
+    pub(crate) async fn event_listener(
+        &self,
+        service: &Service,
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        let url = service.event_sub_url();
+        let recording = match &self.transport {
+            Transport::Network => None,
+            Transport::Record(recorder) => Some((recorder.clone(), url.clone())),
+            Transport::Replay(replay) => {
+                let (callback, events) = replay.listen();
+                return Ok((callback, events.left_stream()));
+            }
+        };
+
+        let (callback, events) = match &self.event_server {
+            Some(event_server) => event_server.register(url, recording).await?,
+            // only listen on the interface the device can reach
+            None => {
+                let addr = crate::utils::local_socket_addr_for(url).await?;
+                EventServer::bind(addr)
+                    .await?
+                    .register(url, recording)
+                    .await?
+            }
+        };
+        Ok((callback, events.right_stream()))
+    }
+
+    /// Sends a `SUBSCRIBE` request and returns the SID and the granted timeout,
+    /// `None` if the subscription doesn't expire.
+    #[cfg(feature = "subscribe")]
+    pub(crate) async fn send_subscription_request(
+        &self,
+        request: Request<Full<Bytes>>,
+        timeout_secs: u32,
+    ) -> Result<(String, Option<Duration>)> {
+        let response = self.request(request).await?.err_if_not_200()?;
+        eventing::parse_subscription_headers(response.headers(), timeout_secs)
+    }
+
+    /// Renew a subscription.
+    /// See [`Service::renew_subscription`].
+    pub async fn renew_subscription(
+        &self,
+        service: &Service,
+        sid: &str,
+        timeout_secs: u32,
+    ) -> Result<()> {
+        let request = service.renew_subscription_request(sid, timeout_secs);
+        self.request(request).await?.err_if_not_200()?;
+
+        Ok(())
+    }
+
+    /// Unsubscribe from further event notifications.
+    /// See [`Service::unsubscribe`].
+    pub async fn unsubscribe(&self, service: &Service, sid: &str) -> Result<()> {
+        let request = service.unsubscribe_request(sid);
+        self.request(request).await?.err_if_not_200()?;
+
+        Ok(())
+    }
+
+    /// Sends a `GET` request and returns the body if the response was successful.
+    pub(crate) async fn get(&self, url: Uri) -> Result<Bytes> {
+        let request = Request::get(url).body(Full::default()).expect("infallible");
+
+        Ok(self.request(request).await?.err_if_not_200()?.into_body())
+    }
+
+    /// Sends a request and reads the whole response body, regardless of the status code.
+    pub(crate) async fn request(&self, request: Request<Full<Bytes>>) -> Result<Response<Bytes>> {
+        let url = request.uri().clone();
+        let recording = match &self.transport {
+            Transport::Network => None,
+            Transport::Record(recorder) => {
+                Some((recorder.clone(), recording::copy_request(&request)))
+            }
+            Transport::Replay(replay) => return Ok(replay.respond(&request)),
+        };
+
+        let response = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.request_inner(request))
+                .await
+                .map_err(|_| Error::Timeout {
+                    url,
+                    phase: TimeoutPhase::Total,
+                })??,
+            None => self.request_inner(request).await?,
+        };
+
+        if let Some((recorder, request)) = recording {
+            recorder.record_exchange(request, &response).await;
+        }
+        Ok(response)
+    }
+
+    async fn request_inner(&self, mut request: Request<Full<Bytes>>) -> Result<Response<Bytes>> {
+        let url = request.uri().clone();
+        if let Some(user_agent) = &self.user_agent {
+            request
+                .headers_mut()
+                .entry(header::USER_AGENT)
+                .or_insert_with(|| user_agent.clone());
+        }
+
+        // the read timeout only starts once a connection is established or taken from the pool,
+        // connecting is limited by the connect timeout
+        let mut connection = capture_connection(&mut request);
+        let response = self.http.request(request);
+        let connected = async {
+            connection.wait_for_connection_metadata().await;
+        };
+        let response = match future::select(response, std::pin::pin!(connected)).await {
+            Either::Left((response, _)) => response,
+            Either::Right((_, response)) => self.read(&url, response).await?,
+        };
+        let response = response.map_err(|err| {
+            if err.is_connect() && is_timeout(&err) {
+                Error::Timeout {
+                    url: url.clone(),
+                    phase: TimeoutPhase::Connect,
+                }
+            } else {
+                Error::NetworkClientError(err)
+            }
+        })?;
+
+        let (parts, body) = response.into_parts();
+        let mut body = Limited::new(body, self.max_body_size);
+        let mut bytes = BytesMut::new();
+
+        while let Some(frame) = self.read(&url, body.frame()).await? {
+            let frame = frame.map_err(|err| match err.downcast::<hyper::Error>() {
+                Ok(err) => Error::NetworkError(*err),
+                Err(err) if err.is::<LengthLimitError>() => {
+                    Error::ResponseTooLarge(self.max_body_size)
+                }
+                Err(err) => Error::InvalidResponse(err),
+            })?;
+            if let Ok(data) = frame.into_data() {
+                bytes.extend_from_slice(&data);
+            }
+        }
+
+        Ok(Response::from_parts(parts, bytes.freeze()))
+    }
+
+    /// Applies the read timeout to `future`.
+    async fn read<F: Future>(&self, url: &Uri, future: F) -> Result<F::Output> {
+        match self.read_timeout {
+            Some(read_timeout) => tokio::time::timeout(read_timeout, future)
+                .await
+                .map_err(|_| Error::Timeout {
+                    url: url.clone(),
+                    phase: TimeoutPhase::Read,
+                }),
+            None => Ok(future.await),
+        }
+    }
+}
+
+fn is_timeout(err: &hyper_util::client::legacy::Error) -> bool {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            return err.kind() == io::ErrorKind::TimedOut;
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url: Uri = format!("http://{}/description.xml", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        // accept the connection, but never answer
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(stream);
+        });
+
+        let client = Client::builder()
+            .read_timeout(Some(Duration::from_millis(100)))
+            .build();
+        let err = client.get(url.clone()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Timeout { url: ref err_url, phase: TimeoutPhase::Read } if err_url == &url
+        ));
+
+        let client = client.with_timeout(Some(Duration::from_millis(50)));
+        let err = client.get(url).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Timeout {
+                phase: TimeoutPhase::Total,
+                ..
+            }
+        ));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn connect_timeout() {
+        // a listener which never accepts, so further connections hang once its backlog is full
+        let socket =
+            socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+        socket
+            .bind(&std::net::SocketAddr::from(([127, 0, 0, 1], 0)).into())
+            .unwrap();
+        socket.listen(0).unwrap();
+        let addr = socket.local_addr().unwrap().as_socket().unwrap();
+        let mut backlog = Vec::new();
+        while let Ok(Ok(stream)) = tokio::time::timeout(
+            Duration::from_millis(100),
+            tokio::net::TcpStream::connect(addr),
+        )
+        .await
+        {
+            backlog.push(stream);
+        }
+
+        // connecting takes longer than the read timeout, but isn't limited by it
+        let url: Uri = format!("http://{addr}/description.xml").parse().unwrap();
+        let client = Client::builder()
+            .connect_timeout(Some(Duration::from_millis(300)))
+            .read_timeout(Some(Duration::from_millis(50)))
+            .build();
+        let err = client.get(url.clone()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Timeout { url: ref err_url, phase: TimeoutPhase::Connect } if err_url == &url
+        ));
+    }
+
+    #[test]
+    fn invalid_user_agent() {
+        assert!(Client::builder().user_agent("MyApp/1.0 UPnP/2.0").is_ok());
+        assert!(matches!(
+            Client::builder().user_agent("MyApp/1.0\r\nX-Injected: 1"),
+            Err(Error::ParseError(_))
+        ));
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn event_listener_on_routing_interface() {
+        let url = Uri::from_static("http://127.0.0.1:1400/description.xml");
+        let description = r#"<root xmlns="urn:schemas-upnp-org:device-1-0"><device>
+            <deviceType>urn:schemas-upnp-org:device:Basic:1</deviceType>
+            <friendlyName>Device</friendlyName>
+            <manufacturer>rupnp</manufacturer>
+            <modelName>Test</modelName>
+            <UDN>uuid:test</UDN>
+            <serviceList><service>
+              <serviceType>urn:schemas-upnp-org:service:Test:1</serviceType>
+              <serviceId>urn:upnp-org:serviceId:Test</serviceId>
+              <SCPDURL>/scpd.xml</SCPDURL>
+              <controlURL>/control</controlURL>
+              <eventSubURL>/event</eventSubURL>
+            </service></serviceList>
+          </device></root>"#;
+        let device = Device::from_xml(url.clone(), description.as_bytes(), &[]).unwrap();
+
+        let (callback, _events) = Client::new()
+            .event_listener(&device.services()[0])
+            .await
+            .unwrap();
+        let callback: Uri = callback.parse().unwrap();
+        assert_eq!(callback.host(), Some("127.0.0.1"));
+
+        // only the interface routing to the device listens, not all of them
+        let port = callback.port_u16().unwrap();
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok());
+        assert!(tokio::net::TcpStream::connect(("::1", port)).await.is_err());
+    }
+}
@@ -1,12 +1,5 @@
-use crate::{
-    find_in_xml,
-    utils::{self, HttpResponseExt, HyperBodyExt},
-    Result, Service,
-};
-use bytes::Bytes;
+use crate::{find_in_xml, utils, Client, Result, Service};
 use http::Uri;
-use http_body_util::Empty;
-use hyper_util::rt::TokioExecutor;
 use roxmltree::{Document, Node};
 use ssdp_client::URN;
 use std::collections::HashMap;
@@ -36,25 +29,32 @@ impl Device {
     /// Creates a UPnP device from the given url, defining extra device properties
     /// to be accessed with `get_extra_property`.
     pub async fn from_url_and_properties(url: Uri, extra_keys: &[&str]) -> Result<Self> {
-        let body = hyper_util::client::legacy::Client::builder(TokioExecutor::new())
-            .build_http::<Empty<Bytes>>()
-            .get(url.clone())
-            .await?
-            .err_if_not_200()?
-            .into_body()
-            .bytes()
-            .await?;
-
-        // .into_body()
-        // .text()
-        // .await?;
-        let body = std::str::from_utf8(&body)?;
+        Client::new().device_with_properties(url, extra_keys).await
+    }
 
-        let document = Document::parse(body)?;
-        let device = utils::find_root(&document, "device", "Device Description")?;
-        let device_spec = DeviceSpec::from_xml(device, extra_keys)?;
+    /// Creates a UPnP device from a [`DeviceSpec`], e.g. one parsed from a cached description
+    /// with [`DeviceSpec::from_xml_str`].
+    /// The `url` is the location the description was fetched from.
+    ///
+    /// # Example usage:
+    /// ```rust,no_run
+    /// # fn cached() -> Result<(), rupnp::Error> {
+    /// use rupnp::{Device, DeviceSpec};
+    ///
+    /// let url: rupnp::http::Uri = "http://192.168.1.2:1400/xml/device_description.xml".parse()?;
+    /// let description = std::fs::read_to_string("renderer.xml")?;
+    /// let spec = DeviceSpec::from_xml_str(&url, &description, &[])?;
+    /// let device = Device::from_parts(url, spec);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_parts(url: Uri, device_spec: DeviceSpec) -> Self {
+        Device { url, device_spec }
+    }
 
-        Ok(Self { url, device_spec })
+    pub(crate) fn from_xml(url: Uri, body: &[u8], extra_keys: &[&str]) -> Result<Self> {
+        let device_spec = DeviceSpec::from_xml_str(&url, std::str::from_utf8(body)?, extra_keys)?;
+        Ok(Device::from_parts(url, device_spec))
     }
 }
 impl std::ops::Deref for Device {
@@ -78,10 +78,10 @@ impl Eq for Device {}
 
 /// Information about a device.
 ///
-/// By default it only includes its *friendly name*, device type, a list of subdevices and
+/// By default it only includes its *friendly name*, UDN, device type, a list of subdevices and
 /// services, and a `HashMap` of extra properties in order to keep the structs size small.
 ///
-/// If you also want the `ManufacturerURL`, `Model{Description,Number,Url}`, `serial number`, `UDN` and
+/// If you also want the `ManufacturerURL`, `Model{Description,Number,Url}`, `serial number` and
 /// `UPC` as struct fields, enable the `full_device_spec` feature.
 #[derive(Debug, Clone)]
 pub struct DeviceSpec {
@@ -107,7 +107,6 @@ pub struct DeviceSpec {
     model_url: Option<String>,
     #[cfg(feature = "full_device_spec")]
     serial_number: Option<String>,
-    #[cfg(feature = "full_device_spec")]
     udn: String,
     #[cfg(feature = "full_device_spec")]
     upc: Option<String>,
@@ -116,11 +115,34 @@ pub struct DeviceSpec {
 }
 
 impl DeviceSpec {
-    fn from_xml<'a, 'input: 'a>(node: Node<'a, 'input>, extra_keys: &[&str]) -> Result<Self> {
+    /// Parses a device description, e.g. one which was saved to a file, defining extra device
+    /// properties to be accessed with `get_extra_property`.
+    /// The URLs of the services are resolved against the deprecated `URLBase` if the
+    /// description contains one, or the `url` it was fetched from otherwise.
+    pub fn from_xml_str(url: &Uri, body: &str, extra_keys: &[&str]) -> Result<Self> {
+        let document = Document::parse(body)?;
+        let url_base = document
+            .root_element()
+            .children()
+            .find(|n| n.tag_name().name().eq_ignore_ascii_case("URLBase"))
+            .and_then(|n| n.text())
+            .map(str::trim)
+            .filter(|url_base| !url_base.is_empty())
+            .map(str::parse::<Uri>)
+            .transpose()?;
+        let device = utils::find_root(&document, "device", "Device Description")?;
+        DeviceSpec::from_xml(device, url_base.as_ref().unwrap_or(url), extra_keys)
+    }
+
+    fn from_xml<'a, 'input: 'a>(
+        node: Node<'a, 'input>,
+        base: &Uri,
+        extra_keys: &[&str],
+    ) -> Result<Self> {
         #[rustfmt::skip]
         #[allow(non_snake_case)]
-        let (device_type, friendly_name, services, devices, extra_properties) =
-            find_in_xml! { node => deviceType, friendlyName, ?serviceList, ?deviceList, #extra_keys };
+        let (device_type, friendly_name, udn, services, devices, extra_properties) =
+            find_in_xml! { node => deviceType, friendlyName, UDN, ?serviceList, ?deviceList, #extra_keys };
 
         #[cfg(feature = "full_device_spec")]
         #[allow(non_snake_case)]
@@ -132,10 +154,9 @@ impl DeviceSpec {
             model_number,
             model_url,
             serial_number,
-            udn,
             upc,
             presentation_url,
-        ) = find_in_xml! { node => manufacturer, ?manufacturerURL, modelName, ?modelDescription, ?modelNumber, ?modelURL, ?serialNumber, UDN, ?UPC, ?PresentationURL};
+        ) = find_in_xml! { node => manufacturer, ?manufacturerURL, modelName, ?modelDescription, ?modelNumber, ?modelURL, ?serialNumber, ?UPC, ?PresentationURL};
 
         #[cfg(feature = "full_device_spec")]
         let manufacturer_url = manufacturer_url.map(utils::parse_node_text).transpose()?;
@@ -156,7 +177,7 @@ impl DeviceSpec {
             Some(d) => d
                 .children()
                 .filter(Node::is_element)
-                .map(|node| DeviceSpec::from_xml(node, extra_keys))
+                .map(|node| DeviceSpec::from_xml(node, base, extra_keys))
                 .collect::<Result<_>>()?,
             None => Vec::new(),
         };
@@ -164,7 +185,7 @@ impl DeviceSpec {
             Some(s) => s
                 .children()
                 .filter(Node::is_element)
-                .map(Service::from_xml)
+                .map(|node| Service::from_xml(node, base))
                 .collect::<Result<_>>()?,
             None => Vec::new(),
         };
@@ -174,7 +195,6 @@ impl DeviceSpec {
             friendly_name: utils::parse_node_text(friendly_name)?,
             #[cfg(feature = "full_device_spec")]
             manufacturer: utils::parse_node_text(manufacturer)?,
-            #[cfg(feature = "full_device_spec")]
             udn: utils::parse_node_text(udn)?,
             #[cfg(feature = "full_device_spec")]
             manufacturer_url,
@@ -239,7 +259,6 @@ impl DeviceSpec {
     pub fn serial_number(&self) -> Option<&str> {
         self.serial_number.as_deref()
     }
-    #[cfg(feature = "full_device_spec")]
     pub fn udn(&self) -> &str {
         &self.udn
     }
@@ -287,3 +306,56 @@ impl DeviceSpec {
         self.devices_iter().find(|d| &d.device_type == device_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <URLBase>http://192.168.1.2:1400/</URLBase>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+    <friendlyName>Living Room</friendlyName>
+    <manufacturer>rupnp</manufacturer>
+    <modelName>Renderer</modelName>
+    <UDN>uuid:renderer</UDN>
+    <roomName>Living Room</roomName>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+        <serviceId>urn:upnp-org:serviceId:RenderingControl</serviceId>
+        <SCPDURL>/xml/RenderingControl1.xml</SCPDURL>
+        <controlURL>/MediaRenderer/RenderingControl/Control</controlURL>
+        <eventSubURL>/MediaRenderer/RenderingControl/Event</eventSubURL>
+      </service>
+    </serviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn from_parts() {
+        let url = Uri::from_static("http://10.0.0.1:1400/xml/device_description.xml");
+        let spec = DeviceSpec::from_xml_str(&url, DESCRIPTION, &["roomName"]).unwrap();
+        assert_eq!(spec.friendly_name(), "Living Room");
+        assert_eq!(spec.get_extra_property("roomName"), Some("Living Room"));
+
+        let device = Device::from_parts(url.clone(), spec);
+        assert_eq!(device.url(), &url);
+
+        let service = device
+            .find_service(&URN::service("schemas-upnp-org", "RenderingControl", 1))
+            .unwrap();
+        // resolved against the `URLBase` of the description, not the device url
+        assert_eq!(
+            service.control_url(),
+            "http://192.168.1.2:1400/MediaRenderer/RenderingControl/Control"
+        );
+
+        assert!(matches!(
+            DeviceSpec::from_xml_str(&url, "<root></root>", &[]),
+            Err(crate::Error::XmlMissingElement(..))
+        ));
+    }
+}
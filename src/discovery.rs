@@ -1,7 +1,35 @@
-use crate::{Device, Error, Result};
-use futures_util::stream::{Stream, StreamExt, TryStreamExt};
+use crate::{httpu, Client, Device, Result};
+use futures_util::stream::Stream;
 use ssdp_client::SearchTarget;
-use std::time::Duration;
+use std::{net::SocketAddr, time::Duration};
+
+/// A multicast group SSDP messages are exchanged on, see [`discover_on`] and
+/// [`listen_on`](crate::listen_on).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MulticastGroup {
+    /// `239.255.255.250`, which all UPnP devices support.
+    Ipv4,
+    /// `FF02::C`, reaching the devices on the same link.
+    Ipv6LinkLocal,
+    /// `FF05::C`, reaching the devices on the same site.
+    Ipv6SiteLocal,
+}
+
+impl MulticastGroup {
+    /// The address of the group, including the SSDP port.
+    pub fn addr(self) -> SocketAddr {
+        match self {
+            MulticastGroup::Ipv4 => (httpu::SSDP_MULTICAST_V4, httpu::SSDP_PORT).into(),
+            MulticastGroup::Ipv6LinkLocal => {
+                (httpu::SSDP_MULTICAST_V6_LINK_LOCAL, httpu::SSDP_PORT).into()
+            }
+            MulticastGroup::Ipv6SiteLocal => {
+                (httpu::SSDP_MULTICAST_V6_SITE_LOCAL, httpu::SSDP_PORT).into()
+            }
+        }
+    }
+}
 
 /// Discovers UPnP devices on the network.
 ///
@@ -66,9 +94,45 @@ pub async fn discover_with_properties<'a>(
     ttl: Option<u32>,
     extra_keys: &'a [&'a str],
 ) -> Result<impl Stream<Item = Result<Device>> + 'a> {
-    Ok(ssdp_client::search(search_target, timeout, 3, ttl)
-        .await?
-        .map_err(Error::SSDPError)
-        .map(|res| Ok(res?.location().parse()?))
-        .and_then(move |url| Device::from_url_and_properties(url, extra_keys)))
+    Client::new()
+        .discover_with_properties(search_target, timeout, ttl, extra_keys)
+        .await
+}
+
+/// Discovers UPnP devices by searching on the given multicast group, e.g. for IPv6 devices.
+///
+/// Link-local IPv6 device URLs are scoped to the interface the response arrived on, like
+/// `http://[fe80::1%2]:1400/description.xml`.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// use futures::prelude::*;
+/// use std::time::Duration;
+/// use rupnp::{ssdp::SearchTarget, MulticastGroup};
+///
+/// # async fn discover_on() -> Result<(), rupnp::Error> {
+/// let devices = rupnp::discover_on(
+///     MulticastGroup::Ipv6LinkLocal,
+///     &SearchTarget::RootDevice,
+///     Duration::from_secs(3),
+///     None,
+/// )
+/// .await?;
+/// let mut devices = std::pin::pin!(devices);
+///
+/// while let Some(device) = devices.try_next().await? {
+///     println!("{} @ {}", device.friendly_name(), device.url());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn discover_on(
+    group: MulticastGroup,
+    search_target: &SearchTarget,
+    timeout: Duration,
+    ttl: Option<u32>,
+) -> Result<impl Stream<Item = Result<Device>>> {
+    Client::new()
+        .discover_on(group, search_target, timeout, ttl)
+        .await
 }
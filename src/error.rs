@@ -15,6 +15,10 @@ pub enum Error {
     InvalidUtf8(Utf8Error),
     ParseError(&'static str),
     HttpErrorCode(http::StatusCode),
+    MissingHeader(&'static str),
+    ResponseTooLarge(usize),
+    Timeout { url: http::Uri, phase: TimeoutPhase },
+    InvalidArguments(crate::scpd::ArgumentError),
     XmlError(roxmltree::Error),
     XmlMissingElement(String, String),
     InvalidResponse(Box<dyn std::error::Error + Send + Sync + 'static>),
@@ -39,7 +43,7 @@ impl fmt::Display for Error {
             }
             Error::NoLocalInterfaceOpen => write!(
                 f,
-                "could not subscribe to events: no local ipv4 interface open"
+                "could not subscribe to events: no suitable local interface open"
             ),
             Error::InvalidUrl(err) => write!(f, "invalid url: {err}"),
             Error::InvalidUtf8(err) => write!(f, "invalid utf8: {err}"),
@@ -48,6 +52,12 @@ impl fmt::Display for Error {
             Error::HttpErrorCode(code) => {
                 write!(f, "The control point responded with status code {code}")
             }
+            Error::MissingHeader(header) => write!(f, "missing http header `{header}`"),
+            Error::ResponseTooLarge(limit) => {
+                write!(f, "the response body exceeds the limit of {limit} bytes")
+            }
+            Error::Timeout { url, phase } => write!(f, "request to {url} timed out ({phase})"),
+            Error::InvalidArguments(err) => write!(f, "invalid action arguments: {err}"),
             Error::XmlError(err) => write!(f, "failed to parse xml: {err}"),
             Error::XmlMissingElement(parent, child) => write!(
                 f,
@@ -65,6 +75,7 @@ impl std::error::Error for Error {
             Error::IO(err) => Some(err),
             Error::InvalidUrl(err) => Some(err),
             Error::InvalidUtf8(err) => Some(err),
+            Error::InvalidArguments(err) => Some(err),
             Error::XmlError(err) => Some(err),
             _ => None,
         }
@@ -105,18 +116,44 @@ impl From<UPnPError> for Error {
         Error::UPnPError(err)
     }
 }
+impl From<crate::scpd::ArgumentError> for Error {
+    fn from(err: crate::scpd::ArgumentError) -> Self {
+        Error::InvalidArguments(err)
+    }
+}
 impl From<Utf8Error> for Error {
     fn from(err: Utf8Error) -> Self {
         Error::InvalidUtf8(err)
     }
 }
 
-#[derive(Debug)]
+/// The part of a request which took too long, see [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// Establishing the TCP connection.
+    Connect,
+    /// Waiting for the response headers or the next chunk of the body.
+    Read,
+    /// The request as a whole.
+    Total,
+}
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutPhase::Connect => write!(f, "connect"),
+            TimeoutPhase::Read => write!(f, "read"),
+            TimeoutPhase::Total => write!(f, "total"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct UPnPError {
     #[allow(unused)]
     fault_code: String,
     fault_string: String,
     err_code: u16,
+    description: Option<String>,
 }
 
 impl std::error::Error for UPnPError {}
@@ -127,16 +164,33 @@ impl fmt::Display for UPnPError {
             "{} {}: {}",
             self.fault_string,
             self.err_code,
-            self.err_code_description()
+            self.description()
+                .unwrap_or_else(|| self.err_code_description())
         )
     }
 }
 
 impl UPnPError {
+    /// Creates the error a hosted device responds with when an action failed, e.g.
+    /// `UPnPError::new(714, "Illegal MIME-type")`.
+    pub fn new(err_code: u16, description: impl Into<String>) -> Self {
+        UPnPError {
+            fault_code: "s:Client".to_string(),
+            fault_string: "UPnPError".to_string(),
+            err_code,
+            description: Some(description.into()),
+        }
+    }
+
     pub fn err_code(&self) -> u16 {
         self.err_code
     }
 
+    /// The `errorDescription` sent by the device, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
     pub fn err_code_description(&self) -> &str {
         match self.err_code {
             401 => "No action by that name at this service.",
@@ -170,11 +224,17 @@ impl UPnPError {
             .unwrap_or_default()
             .parse()
             .map_err(Error::invalid_response)?;
+        let description = detail
+            .descendants()
+            .find(|n| n.tag_name().name().eq_ignore_ascii_case("errorDescription"))
+            .and_then(|n| n.text())
+            .map(str::to_string);
 
         Ok(UPnPError {
             fault_code,
             fault_string,
             err_code,
+            description,
         })
     }
 }
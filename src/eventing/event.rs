@@ -0,0 +1,283 @@
+use crate::Result;
+use futures_core::stream::Stream;
+use futures_util::{future, stream::StreamExt};
+
+/// A state variable change notification of a subscribed service.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # use futures::prelude::*;
+/// # async fn subscribe_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+/// let (_sid, mut events) = service.subscribe(300).await?;
+///
+/// while let Some(event) = events.try_next().await? {
+///     if let Some(gap) = event.gap() {
+///         eprintln!("missed events of {}: {:?}", event.sid(), gap);
+///     }
+///     for (name, value) in event.iter() {
+///         println!("{name} => {value}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    sid: String,
+    seq: u32,
+    properties: Vec<(String, String)>,
+    gap: Option<SequenceGap>,
+}
+
+impl Event {
+    pub(crate) fn new(sid: String, seq: u32, properties: Vec<(String, String)>) -> Self {
+        Event {
+            sid,
+            seq,
+            properties,
+            gap: None,
+        }
+    }
+
+    /// The subscription identifier (`SID` header) the event belongs to.
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+
+    /// The event key (`SEQ` header). The initial event, containing all evented state
+    /// variables, is `0`, and every following event increments it by one.
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    /// Whether events were missed or the sequence restarted before this event, or whether it
+    /// arrived after a newer one.
+    /// The state of the service may be out of sync in that case; resubscribing delivers a new
+    /// initial event with the value of every state variable.
+    pub fn gap(&self) -> Option<SequenceGap> {
+        self.gap
+    }
+
+    /// Returns the new value of the state variable called `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over the changed state variables in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Consumes the event, returning the changed state variables.
+    pub fn into_properties(self) -> Vec<(String, String)> {
+        self.properties
+    }
+}
+
+/// Only keeps the state variables called one of `names`, skipping events without any of them
+/// unless they follow a gap. Keeps all events if `names` is empty.
+pub(crate) fn filter_variables<S>(
+    events: S,
+    names: Vec<String>,
+) -> impl Stream<Item = Result<Event>>
+where
+    S: Stream<Item = Result<Event>>,
+{
+    events.filter_map(move |event| {
+        let event = match event {
+            Ok(mut event) if !names.is_empty() => {
+                event.properties.retain(|(name, _)| names.contains(name));
+                if event.properties.is_empty() && event.gap.is_none() {
+                    None
+                } else {
+                    Some(Ok(event))
+                }
+            }
+            event => Some(event),
+        };
+        future::ready(event)
+    })
+}
+
+/// An unexpected `SEQ` of an [`Event`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SequenceGap {
+    /// The event key is not the successor of the previous one, so at least one event was lost
+    /// (or arrived out of order).
+    Skipped { expected: u32, received: u32 },
+    /// The event key went back to `0`, e.g. because the device rebooted.
+    Reset,
+    /// The event arrived after one with a newer event key, e.g. because they were sent over
+    /// different connections, or it was sent twice. Its values may be older than the ones
+    /// already received.
+    Late { last: u32, received: u32 },
+}
+
+/// How many subscriptions a [`SequenceTracker`] remembers. A callback is only used by one
+/// subscription at a time, the others ended when it had to be made again.
+const TRACKED_SUBSCRIPTIONS: usize = 8;
+
+/// Tracks the last event key of the most recent subscriptions to detect gaps.
+#[derive(Debug, Default)]
+pub(crate) struct SequenceTracker {
+    /// The SIDs and their last event key, the most recently notified last.
+    last_seq: Vec<(String, u32)>,
+}
+
+impl SequenceTracker {
+    pub(crate) fn track(&mut self, event: &mut Event) {
+        let previous = match self.last_seq.iter().position(|(sid, _)| *sid == event.sid) {
+            Some(i) => Some(self.last_seq.remove(i).1),
+            None => None,
+        };
+
+        let (last, gap) = match previous {
+            None => (event.seq, Self::gap(0, event.seq)),
+            Some(_) if event.seq == 0 => (0, Some(SequenceGap::Reset)),
+            // keys at most half of the range behind the last one are late, taking into
+            // account that they wrap around
+            Some(last) if event.seq.wrapping_sub(last).wrapping_sub(1) >= u32::MAX / 2 => {
+                let late = SequenceGap::Late {
+                    last,
+                    received: event.seq,
+                };
+                (last, Some(late))
+            }
+            // wraps to 1, since 0 is reserved for the initial event
+            Some(u32::MAX) => (event.seq, Self::gap(1, event.seq)),
+            Some(previous) => (event.seq, Self::gap(previous + 1, event.seq)),
+        };
+        event.gap = gap;
+
+        self.last_seq.push((event.sid.clone(), last));
+        if self.last_seq.len() > TRACKED_SUBSCRIPTIONS {
+            self.last_seq.remove(0);
+        }
+    }
+
+    fn gap(expected: u32, received: u32) -> Option<SequenceGap> {
+        (expected != received).then_some(SequenceGap::Skipped { expected, received })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(tracker: &mut SequenceTracker, sid: &str, seq: u32) -> Option<SequenceGap> {
+        let mut event = Event::new(sid.to_string(), seq, Vec::new());
+        tracker.track(&mut event);
+        event.gap()
+    }
+
+    #[test]
+    fn sequence_gaps() {
+        let mut tracker = SequenceTracker::default();
+
+        assert_eq!(track(&mut tracker, "uuid:a", 0), None);
+        assert_eq!(track(&mut tracker, "uuid:a", 1), None);
+        assert_eq!(track(&mut tracker, "uuid:b", 0), None);
+        assert_eq!(
+            track(&mut tracker, "uuid:a", 3),
+            Some(SequenceGap::Skipped {
+                expected: 2,
+                received: 3
+            })
+        );
+        assert_eq!(track(&mut tracker, "uuid:a", 4), None);
+        assert_eq!(track(&mut tracker, "uuid:a", 0), Some(SequenceGap::Reset));
+
+        assert_eq!(
+            track(&mut tracker, "uuid:c", u32::MAX),
+            Some(SequenceGap::Skipped {
+                expected: 0,
+                received: u32::MAX
+            })
+        );
+        assert_eq!(track(&mut tracker, "uuid:c", 1), None);
+    }
+
+    #[test]
+    fn late_events() {
+        let mut tracker = SequenceTracker::default();
+
+        assert_eq!(track(&mut tracker, "uuid:a", 0), None);
+        assert_eq!(
+            track(&mut tracker, "uuid:a", 2),
+            Some(SequenceGap::Skipped {
+                expected: 1,
+                received: 2
+            })
+        );
+        assert_eq!(
+            track(&mut tracker, "uuid:a", 1),
+            Some(SequenceGap::Late {
+                last: 2,
+                received: 1
+            })
+        );
+        assert_eq!(
+            track(&mut tracker, "uuid:a", 2),
+            Some(SequenceGap::Late {
+                last: 2,
+                received: 2
+            })
+        );
+        assert_eq!(track(&mut tracker, "uuid:a", 3), None);
+
+        // across the wrap from `u32::MAX` to 1
+        assert_eq!(track(&mut tracker, "uuid:b", 0), None);
+        tracker.last_seq.last_mut().unwrap().1 = 1;
+        assert_eq!(
+            track(&mut tracker, "uuid:b", u32::MAX),
+            Some(SequenceGap::Late {
+                last: 1,
+                received: u32::MAX
+            })
+        );
+        assert_eq!(track(&mut tracker, "uuid:b", 2), None);
+    }
+
+    #[test]
+    fn forget_ended_subscriptions() {
+        let mut tracker = SequenceTracker::default();
+
+        for i in 0..=TRACKED_SUBSCRIPTIONS {
+            assert_eq!(track(&mut tracker, &format!("uuid:{i}"), 0), None);
+        }
+        assert_eq!(tracker.last_seq.len(), TRACKED_SUBSCRIPTIONS);
+        assert!(tracker.last_seq.iter().all(|(sid, _)| sid != "uuid:0"));
+        assert_eq!(track(&mut tracker, "uuid:1", 1), None);
+    }
+
+    #[tokio::test]
+    async fn filtered_variables() {
+        let event = |seq, properties: &[&str]| {
+            let properties = properties
+                .iter()
+                .map(|name| (name.to_string(), String::new()))
+                .collect();
+            Ok(Event::new("uuid:1".to_string(), seq, properties))
+        };
+        let events = futures_util::stream::iter(vec![
+            event(0, &["ZoneGroupState", "AvailableSoftwareUpdate"]),
+            event(1, &["AvailableSoftwareUpdate"]),
+            event(2, &["ZoneGroupState"]),
+        ]);
+
+        let filtered: Vec<_> = filter_variables(events, vec!["ZoneGroupState".to_string()])
+            .map(|event| {
+                let event = event.unwrap();
+                (event.seq(), event.iter().count())
+            })
+            .collect()
+            .await;
+        assert_eq!(filtered, [(0, 1), (2, 1)]);
+    }
+}
@@ -0,0 +1,309 @@
+use super::Event;
+use crate::{Error, Result};
+use roxmltree::{Document, Node};
+use std::collections::BTreeMap;
+
+/// The decoded `LastChange` state variable of services like `AVTransport` and
+/// `RenderingControl`, which moderate their events by sending all changes of one or more
+/// instances in a single XML document:
+///
+/// ```xml
+/// <Event xmlns="urn:schemas-upnp-org:metadata-1-0/RCS/">
+///   <InstanceID val="0">
+///     <Volume channel="Master" val="30"/>
+///     <Mute channel="Master" val="0"/>
+///   </InstanceID>
+/// </Event>
+/// ```
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # use futures::prelude::*;
+/// # async fn last_change(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+/// use rupnp::{LastChange, LastChangeState};
+///
+/// let mut state = LastChangeState::new();
+/// let mut subscription = service.subscription(300).await?;
+///
+/// while let Some(event) = subscription.try_next().await? {
+///     if let Some(last_change) = LastChange::from_event(&event) {
+///         state.apply(&last_change?);
+///     }
+///     println!("volume: {:?}", state.get_with(0, "Volume", "channel", "Master"));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastChange {
+    instances: Instances,
+}
+
+/// A single changed state variable of a [`LastChange`], like
+/// `<Volume channel="Master" val="30"/>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange {
+    name: String,
+    value: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl StateChange {
+    /// The name of the state variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The new value, taken from the `val` attribute.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the attribute called `name`, other than `val`, e.g. the `channel` of a volume.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over the attributes other than `val`, in document order.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Whether both describe the same state variable, e.g. the volume of the same channel.
+    fn same_variable(&self, other: &StateChange) -> bool {
+        self.name == other.name && self.attributes == other.attributes
+    }
+}
+
+impl LastChange {
+    /// Parses the value of a `LastChange` state variable.
+    pub fn parse(xml: &str) -> Result<Self> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+        if root.tag_name().name() != "Event" {
+            return Err(Error::XmlMissingElement(
+                "LastChange".to_string(),
+                "Event".to_string(),
+            ));
+        }
+
+        let mut instances = Instances::default();
+        for instance in root.children().filter(|node| node.is_element()) {
+            if instance.tag_name().name() != "InstanceID" {
+                continue;
+            }
+            let id = instance
+                .attribute("val")
+                .ok_or_else(|| {
+                    Error::XmlMissingElement("InstanceID".to_string(), "val".to_string())
+                })?
+                .trim()
+                .parse()
+                .map_err(Error::invalid_response)?;
+
+            let changes = instances.0.entry(id).or_default();
+            for variable in instance.children().filter(|node| node.is_element()) {
+                changes.push(parse_change(variable));
+            }
+        }
+
+        Ok(LastChange { instances })
+    }
+
+    /// Parses the `LastChange` state variable of the event, `None` if it doesn't contain one.
+    pub fn from_event(event: &Event) -> Option<Result<Self>> {
+        event.get("LastChange").map(LastChange::parse)
+    }
+
+    /// The ids of the instances with changes, in ascending order.
+    pub fn instances(&self) -> impl Iterator<Item = u32> + '_ {
+        self.instances.0.keys().copied()
+    }
+
+    /// Returns the new value of the state variable called `name` of an instance.
+    /// For variables with attributes like `channel`, use [`get_with`](Self::get_with).
+    pub fn get(&self, instance: u32, name: &str) -> Option<&str> {
+        self.instances.get(instance, name, None)
+    }
+
+    /// Returns the new value of the state variable called `name` of an instance, whose
+    /// `attribute` is `value`, e.g. the `Volume` whose `channel` is `Master`.
+    pub fn get_with(
+        &self,
+        instance: u32,
+        name: &str,
+        attribute: &str,
+        value: &str,
+    ) -> Option<&str> {
+        self.instances.get(instance, name, Some((attribute, value)))
+    }
+
+    /// Iterates over all changes, ordered by instance and then in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &StateChange)> {
+        self.instances.iter()
+    }
+}
+
+/// The current state of all instances of a service with a `LastChange` state variable, which
+/// is kept up to date by [`apply`](LastChangeState::apply)ing every [`LastChange`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastChangeState {
+    instances: Instances,
+}
+
+impl LastChangeState {
+    pub fn new() -> Self {
+        LastChangeState::default()
+    }
+
+    /// Updates the state variables contained in `changes`. A variable with attributes only
+    /// replaces the one with the same attributes, so the volume of one channel doesn't
+    /// overwrite that of another.
+    pub fn apply(&mut self, changes: &LastChange) {
+        for (&id, changes) in &changes.instances.0 {
+            let state = self.instances.0.entry(id).or_default();
+            for change in changes {
+                match state.iter_mut().find(|known| known.same_variable(change)) {
+                    Some(known) => known.value.clone_from(&change.value),
+                    None => state.push(change.clone()),
+                }
+            }
+        }
+    }
+
+    /// The ids of all known instances, in ascending order.
+    pub fn instances(&self) -> impl Iterator<Item = u32> + '_ {
+        self.instances.0.keys().copied()
+    }
+
+    /// Returns the value of the state variable called `name` of an instance.
+    /// For variables with attributes like `channel`, use [`get_with`](Self::get_with).
+    pub fn get(&self, instance: u32, name: &str) -> Option<&str> {
+        self.instances.get(instance, name, None)
+    }
+
+    /// Returns the value of the state variable called `name` of an instance, whose
+    /// `attribute` is `value`, e.g. the `Volume` whose `channel` is `Master`.
+    pub fn get_with(
+        &self,
+        instance: u32,
+        name: &str,
+        attribute: &str,
+        value: &str,
+    ) -> Option<&str> {
+        self.instances.get(instance, name, Some((attribute, value)))
+    }
+
+    /// Iterates over all state variables, ordered by instance and then by their first
+    /// appearance.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &StateChange)> {
+        self.instances.iter()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Instances(BTreeMap<u32, Vec<StateChange>>);
+
+impl Instances {
+    fn get(&self, instance: u32, name: &str, attribute: Option<(&str, &str)>) -> Option<&str> {
+        self.0
+            .get(&instance)?
+            .iter()
+            .filter(|change| change.name == name)
+            .find(|change| match attribute {
+                Some((attribute, value)) => change.attribute(attribute) == Some(value),
+                None => true,
+            })
+            .map(StateChange::value)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (u32, &StateChange)> {
+        self.0
+            .iter()
+            .flat_map(|(&id, changes)| changes.iter().map(move |change| (id, change)))
+    }
+}
+
+fn parse_change(variable: Node<'_, '_>) -> StateChange {
+    let mut value = String::new();
+    let mut attributes = Vec::new();
+    for attribute in variable.attributes() {
+        if attribute.name() == "val" {
+            value = attribute.value().to_string();
+        } else {
+            attributes.push((attribute.name().to_string(), attribute.value().to_string()));
+        }
+    }
+
+    StateChange {
+        name: variable.tag_name().name().to_string(),
+        value,
+        attributes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering_control() {
+        let xml = r#"<Event xmlns="urn:schemas-upnp-org:metadata-1-0/RCS/">
+            <InstanceID val="0">
+                <Volume channel="Master" val="30"/>
+                <Volume channel="LF" val="100"/>
+                <Mute channel="Master" val="0"/>
+                <PresetNameList val="FactoryDefaults"/>
+            </InstanceID>
+        </Event>"#;
+
+        let last_change = LastChange::parse(xml).unwrap();
+        assert_eq!(last_change.instances().collect::<Vec<_>>(), [0]);
+        assert_eq!(
+            last_change.get_with(0, "Volume", "channel", "LF"),
+            Some("100")
+        );
+        assert_eq!(
+            last_change.get(0, "PresetNameList"),
+            Some("FactoryDefaults")
+        );
+        assert_eq!(last_change.get(1, "PresetNameList"), None);
+
+        let mut state = LastChangeState::new();
+        state.apply(&last_change);
+
+        let update = r#"<Event xmlns="urn:schemas-upnp-org:metadata-1-0/RCS/">
+            <InstanceID val="0"><Volume channel="Master" val="25"/></InstanceID>
+            <InstanceID val="1"><Mute channel="Master" val="1"/></InstanceID>
+        </Event>"#;
+        state.apply(&LastChange::parse(update).unwrap());
+
+        assert_eq!(state.get_with(0, "Volume", "channel", "Master"), Some("25"));
+        assert_eq!(state.get_with(0, "Volume", "channel", "LF"), Some("100"));
+        assert_eq!(state.get_with(1, "Mute", "channel", "Master"), Some("1"));
+        assert_eq!(state.iter().count(), 5);
+    }
+
+    #[test]
+    fn escaped_metadata() {
+        let xml = r#"<Event xmlns="urn:schemas-upnp-org:metadata-1-0/AVT/">
+            <InstanceID val="0">
+                <TransportState val="PLAYING"/>
+                <CurrentTrackMetaData val="&lt;DIDL-Lite&gt;&lt;/DIDL-Lite&gt;"/>
+            </InstanceID>
+        </Event>"#;
+
+        let last_change = LastChange::parse(xml).unwrap();
+        assert_eq!(
+            last_change.get(0, "CurrentTrackMetaData"),
+            Some("<DIDL-Lite></DIDL-Lite>")
+        );
+        assert!(LastChange::parse("<propertyset/>").is_err());
+        assert!(LastChange::parse(r#"<Event><InstanceID/></Event>"#).is_err());
+    }
+}
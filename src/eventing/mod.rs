@@ -0,0 +1,20 @@
+//! GENA (General Event Notification Architecture), receiving the state variable changes of
+//! subscribed services.
+
+mod event;
+mod last_change;
+mod multicast;
+mod notify;
+mod server;
+mod state;
+mod subscription;
+
+pub(crate) use event::{filter_variables, SequenceTracker};
+pub use event::{Event, SequenceGap};
+pub use last_change::{LastChange, LastChangeState, StateChange};
+pub use multicast::{listen_multicast_events, MulticastEvent};
+pub(crate) use notify::parse_notify;
+pub use server::{EventServer, EventServerBuilder};
+pub use state::ServiceState;
+pub use subscription::Subscription;
+pub(crate) use subscription::{parse_subscription_headers, state_variable_names};
@@ -0,0 +1,222 @@
+use super::notify::parse_propertyset;
+use crate::{
+    httpu::{self, HttpuMessage},
+    utils::yield_try,
+    Error, Result,
+};
+use futures_core::stream::Stream;
+use genawaiter::sync::{Co, Gen};
+use ssdp_client::URN;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+
+/// The IPv4 multicast group multicast events are sent to.
+const MULTICAST_EVENTS_V4: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 246);
+const MULTICAST_EVENTS_PORT: u16 = 7900;
+
+/// A state variable change of a [multicast](crate::scpd::StateVariable::is_multicast) state
+/// variable, see [`listen_multicast_events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulticastEvent {
+    usn: String,
+    service_id: String,
+    level: Option<String>,
+    seq: u32,
+    boot_id: Option<u32>,
+    from: SocketAddr,
+    properties: Vec<(String, String)>,
+}
+
+impl MulticastEvent {
+    /// Unique Service Name of the originating service, e.g.
+    /// `uuid:<udn>::urn:schemas-upnp-org:service:RenderingControl:1`.
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+
+    /// The `UDN` of the originating device, taken from the `USN`.
+    pub fn udn(&self) -> &str {
+        self.usn.split("::").next().unwrap_or_default()
+    }
+
+    /// The type of the originating service, taken from the `USN`.
+    pub fn service_type(&self) -> Option<URN> {
+        self.usn.split_once("::")?.1.parse().ok()
+    }
+
+    /// The `serviceId` of the originating service (`SVCID` header).
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// The event level (`LVL` header), e.g. `upnp:/info` or `upnp:/warning`.
+    pub fn level(&self) -> Option<&str> {
+        self.level.as_deref()
+    }
+
+    /// The event key (`SEQ` header), incremented for each event of the service.
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    /// The `BOOTID.UPNP.ORG` header.
+    pub fn boot_id(&self) -> Option<u32> {
+        self.boot_id
+    }
+
+    /// The address the event was sent from.
+    pub fn from(&self) -> SocketAddr {
+        self.from
+    }
+
+    /// Returns the new value of the state variable called `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over the names and new values of the changed state variables, in document
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    fn from_message(message: &HttpuMessage<'_>, from: SocketAddr) -> Result<Self> {
+        let seq = message
+            .required_header("SEQ")?
+            .parse()
+            .map_err(Error::invalid_response)?;
+        let boot_id = message
+            .header("BOOTID.UPNP.ORG")
+            .map(|boot_id| boot_id.parse().map_err(Error::invalid_response))
+            .transpose()?;
+
+        Ok(MulticastEvent {
+            usn: message.required_header("USN")?.to_string(),
+            service_id: message.required_header("SVCID")?.to_string(),
+            level: message.header("LVL").map(str::to_string),
+            seq,
+            boot_id,
+            from,
+            properties: parse_propertyset(message.body())?,
+        })
+    }
+}
+
+/// Listens for the events of multicast state variables, which devices implementing UPnP 2.0
+/// send to `239.255.255.246:7900` without any subscription.
+///
+/// Messages which aren't valid event notifications are ignored, only errors of the socket are
+/// yielded.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// use futures::prelude::*;
+///
+/// # async fn multicast_events() -> Result<(), rupnp::Error> {
+/// let events = rupnp::listen_multicast_events().await?;
+/// let mut events = std::pin::pin!(events);
+///
+/// while let Some(event) = events.try_next().await? {
+///     for (name, value) in event.iter() {
+///         println!("{} ({}): {name} => {value}", event.udn(), event.service_id());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn listen_multicast_events() -> Result<impl Stream<Item = Result<MulticastEvent>>> {
+    let socket = httpu::bind_multicast_v4(MULTICAST_EVENTS_V4, MULTICAST_EVENTS_PORT)?;
+
+    Ok(Gen::new(move |co| listen_stream(socket, co)))
+}
+
+async fn listen_stream(socket: UdpSocket, co: Co<Result<MulticastEvent>>) {
+    let mut buf = vec![0u8; 65536];
+    loop {
+        let (read, from) = yield_try!(co => socket.recv_from(&mut buf).await);
+        // anyone can send to the multicast group, so malformed messages are skipped instead
+        // of ending the stream
+        if let Some(event) = parse_event(&buf[..read], from) {
+            co.yield_(Ok(event)).await;
+        }
+    }
+}
+
+/// Parses a datagram received on the multicast group, `None` if it isn't a valid event
+/// notification.
+fn parse_event(datagram: &[u8], from: SocketAddr) -> Option<MulticastEvent> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    let message = HttpuMessage::parse(text).ok()?;
+    if !is_event_notification(&message) {
+        return None;
+    }
+    MulticastEvent::from_message(&message, from).ok()
+}
+
+fn is_event_notification(message: &HttpuMessage<'_>) -> bool {
+    message.method() == Some("NOTIFY")
+        && message.header("NT") == Some("upnp:event")
+        && message.header("NTS") == Some("upnp:propchange")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_multicast_event() {
+        let text = "NOTIFY * HTTP/1.1\r\n\
+            HOST: 239.255.255.246:7900\r\n\
+            CONTENT-TYPE: text/xml; charset=\"utf-8\"\r\n\
+            USN: uuid:RINCON_000E58::urn:schemas-upnp-org:service:RenderingControl:1\r\n\
+            SVCID: urn:upnp-org:serviceId:RenderingControl\r\n\
+            NT: upnp:event\r\n\
+            NTS: upnp:propchange\r\n\
+            SEQ: 3\r\n\
+            LVL: upnp:/info\r\n\
+            BOOTID.UPNP.ORG: 7\r\n\r\n\
+            <e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">\
+            <e:property><Volume>25</Volume></e:property></e:propertyset>";
+        let message = HttpuMessage::parse(text).unwrap();
+        assert!(is_event_notification(&message));
+
+        let from = SocketAddr::from(([192, 168, 1, 2], 7900));
+        let event = MulticastEvent::from_message(&message, from).unwrap();
+        assert_eq!(event.udn(), "uuid:RINCON_000E58");
+        assert_eq!(
+            event.service_type(),
+            Some(URN::service("schemas-upnp-org", "RenderingControl", 1))
+        );
+        assert_eq!(
+            event.service_id(),
+            "urn:upnp-org:serviceId:RenderingControl"
+        );
+        assert_eq!((event.seq(), event.boot_id()), (3, Some(7)));
+        assert_eq!(event.level(), Some("upnp:/info"));
+        assert_eq!(event.get("Volume"), Some("25"));
+    }
+
+    #[test]
+    fn skip_malformed_events() {
+        let from = SocketAddr::from(([192, 168, 1, 2], 7900));
+        let event = |headers: &str| {
+            let text = format!(
+                "NOTIFY * HTTP/1.1\r\nNT: upnp:event\r\nNTS: upnp:propchange\r\n{headers}\r\n\
+                 <e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">\
+                 <e:property><Volume>25</Volume></e:property></e:propertyset>"
+            );
+            parse_event(text.as_bytes(), from)
+        };
+
+        assert!(event("USN: uuid:a::urn:x\r\nSVCID: urn:y\r\nSEQ: 1\r\n").is_some());
+        assert!(event("USN: uuid:a::urn:x\r\nSVCID: urn:y\r\nSEQ: one\r\n").is_none());
+        assert!(event("USN: uuid:a::urn:x\r\nSEQ: 1\r\n").is_none());
+        assert!(parse_event(b"\xff\xfe", from).is_none());
+        assert!(parse_event(b"M-SEARCH * HTTP/1.1\r\n\r\n", from).is_none());
+    }
+}
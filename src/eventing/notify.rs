@@ -0,0 +1,241 @@
+use super::Event;
+use crate::{Error, Result};
+use bytes::Bytes;
+use http::{header::HeaderMap, Method, StatusCode};
+use http_body_util::{BodyExt, Limited};
+use hyper::body::Incoming;
+use roxmltree::Document;
+
+/// Property sets larger than this are rejected.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+const EVENT_NAMESPACE: &str = "urn:schemas-upnp-org:event-1-0";
+
+/// Reads the body of a `NOTIFY` request, or returns the status code to reject it with.
+pub(super) async fn read_body(body: Incoming) -> Result<Bytes, StatusCode> {
+    Ok(Limited::new(body, MAX_BODY_SIZE)
+        .collect()
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+        .to_bytes())
+}
+
+/// Turns a `NOTIFY` request into an [`Event`], or the status code to reject it with.
+pub(crate) fn parse_notify(
+    method: &Method,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Event, StatusCode> {
+    let (sid, seq) = check_headers(method, headers)?;
+
+    let body = std::str::from_utf8(body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let properties = parse_propertyset(body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    Ok(Event::new(sid, seq, properties))
+}
+
+/// Validates the headers of an event notification as described in section 4.3.2 of the
+/// UPnP Device Architecture.
+///
+/// The value of the `SID` isn't checked: the initial event may arrive before the response to
+/// the `SUBSCRIBE` request containing the SID.
+/// Returns the `SID` and `SEQ`.
+fn check_headers(method: &Method, headers: &HeaderMap) -> Result<(String, u32), StatusCode> {
+    if method.as_str() != "NOTIFY" {
+        return Err(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let (nt, nts) = match (header("NT"), header("NTS")) {
+        (Some(nt), Some(nts)) => (nt, nts),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    if nt != "upnp:event" || nts != "upnp:propchange" {
+        return Err(StatusCode::PRECONDITION_FAILED);
+    }
+    let sid = match header("SID").map(str::trim) {
+        Some(sid) if !sid.is_empty() => sid.to_string(),
+        _ => return Err(StatusCode::PRECONDITION_FAILED),
+    };
+    let seq = header("SEQ")
+        .and_then(|seq| seq.trim().parse().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    Ok((sid, seq))
+}
+
+/// Parses an `<e:propertyset>` into the names and values of its state variables, in document
+/// order.
+pub(crate) fn parse_propertyset(body: &str) -> Result<Vec<(String, String)>> {
+    let document = Document::parse(body)?;
+    let root = document.root_element();
+    if !root.has_tag_name((EVENT_NAMESPACE, "propertyset")) {
+        return Err(Error::XmlMissingElement(
+            "Event".to_string(),
+            "propertyset".to_string(),
+        ));
+    }
+
+    let properties = root
+        .children()
+        .filter(|node| node.has_tag_name((EVENT_NAMESPACE, "property")))
+        .flat_map(|property| property.children().filter(|node| node.is_element()))
+        .map(|variable| {
+            let value = variable.text().unwrap_or_default();
+            (variable.tag_name().name().to_string(), value.to_string())
+        })
+        .collect();
+
+    Ok(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{eventing::EventServer, Client};
+    use futures_util::StreamExt;
+    use http::Request;
+    use http_body_util::Full;
+
+    #[test]
+    fn propertyset_with_any_prefix() {
+        let body = r#"<?xml version="1.0"?>
+            <event:propertyset xmlns:event="urn:schemas-upnp-org:event-1-0">
+              <event:property><Volume>25</Volume></event:property>
+              <event:property><Mute/></event:property>
+            </event:propertyset>"#;
+
+        assert_eq!(
+            parse_propertyset(body).unwrap(),
+            [
+                ("Volume".to_string(), "25".to_string()),
+                ("Mute".to_string(), String::new())
+            ]
+        );
+        assert!(parse_propertyset("<propertyset><property/></propertyset>").is_err());
+    }
+
+    #[test]
+    fn notify_headers() {
+        let notify = Method::from_bytes(b"NOTIFY").unwrap();
+        let headers = |headers: &[(&'static str, &'static str)]| {
+            headers
+                .iter()
+                .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+
+        let valid = headers(&[
+            ("NT", "upnp:event"),
+            ("NTS", "upnp:propchange"),
+            ("SID", "uuid:1"),
+            ("SEQ", "7"),
+        ]);
+        assert_eq!(
+            check_headers(&notify, &valid),
+            Ok(("uuid:1".to_string(), 7))
+        );
+        assert_eq!(
+            check_headers(&Method::POST, &valid),
+            Err(StatusCode::METHOD_NOT_ALLOWED)
+        );
+        assert_eq!(
+            check_headers(
+                &notify,
+                &headers(&[("NT", "upnp:event"), ("SID", "uuid:1")])
+            ),
+            Err(StatusCode::BAD_REQUEST)
+        );
+        assert_eq!(
+            check_headers(
+                &notify,
+                &headers(&[
+                    ("NT", "upnp:event"),
+                    ("NTS", "ssdp:alive"),
+                    ("SID", "uuid:1")
+                ])
+            ),
+            Err(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            check_headers(
+                &notify,
+                &headers(&[("NT", "upnp:event"), ("NTS", "upnp:propchange")])
+            ),
+            Err(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            check_headers(
+                &notify,
+                &headers(&[
+                    ("NT", "upnp:event"),
+                    ("NTS", "upnp:propchange"),
+                    ("SID", "uuid:1")
+                ])
+            ),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[tokio::test]
+    async fn serve_notify() {
+        let server = EventServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let device = http::Uri::from_static("http://127.0.0.1:1400/");
+        let (url, notifications) = server.register(&device, None).await.unwrap();
+        let (other_url, other_notifications) = server.register(&device, None).await.unwrap();
+        let mut notifications = std::pin::pin!(notifications);
+
+        let body = "<e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">\
+            <e:property><Volume>25</Volume></e:property></e:propertyset>";
+        let request = Request::builder()
+            .method("NOTIFY")
+            .uri(&url)
+            .header("NT", "upnp:event")
+            .header("NTS", "upnp:propchange")
+            .header("SID", "uuid:1")
+            .header("SEQ", "0")
+            .body(Full::from(body))
+            .unwrap();
+
+        let client = Client::new();
+        let response = client.request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let event = notifications.next().await.unwrap().unwrap();
+        assert_eq!((event.sid(), event.seq()), ("uuid:1", 0));
+        assert_eq!(event.get("Volume"), Some("25"));
+        assert_eq!(event.gap(), None);
+
+        let request = Request::builder()
+            .method("NOTIFY")
+            .uri(&url)
+            .header("NT", "upnp:event")
+            .body(Full::from(body))
+            .unwrap();
+        let response = client.request(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // notifications are routed by callback path, unknown paths are rejected
+        drop(other_notifications);
+        for (url, status) in [
+            (other_url, StatusCode::PRECONDITION_FAILED),
+            (
+                format!("http://{}/", server.local_addr()),
+                StatusCode::PRECONDITION_FAILED,
+            ),
+        ] {
+            let request = Request::builder()
+                .method("NOTIFY")
+                .uri(&url)
+                .header("NT", "upnp:event")
+                .header("NTS", "upnp:propchange")
+                .header("SID", "uuid:2")
+                .header("SEQ", "0")
+                .body(Full::from(body))
+                .unwrap();
+            let response = client.request(request).await.unwrap();
+            assert_eq!(response.status(), status);
+        }
+    }
+}
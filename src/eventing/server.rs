@@ -0,0 +1,475 @@
+use super::{
+    event::SequenceTracker,
+    notify::{parse_notify, read_body},
+    Event,
+};
+use crate::{utils, Error, Recorder, Result};
+use bytes::Bytes;
+use futures_core::stream::Stream;
+use genawaiter::sync::{Co, Gen};
+use http::{Request, Response, StatusCode, Uri};
+use http_body_util::Full;
+use hyper::{body::Incoming, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
+
+/// How long to wait before accepting connections again after it failed, e.g. because the
+/// process ran out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// How many events of a subscription are buffered until its stream is polled.
+const EVENT_BUFFER: usize = 16;
+
+type Routes = Arc<Mutex<HashMap<String, Route>>>;
+
+/// Where the notifications to one callback path go.
+struct Route {
+    events: mpsc::Sender<Result<Event>>,
+    /// Records the notifications as sent by the device with the event URL.
+    recording: Option<(Recorder, Uri)>,
+}
+
+/// An HTTP server receiving the event notifications of any number of subscriptions.
+///
+/// Every subscription gets its own callback path on the server, so a single port serves all
+/// services of all devices. Cloning an `EventServer` is cheap and shares the socket; it stops
+/// listening once all clones and all streams of subscriptions made through it are dropped.
+///
+/// Subscriptions are made through a [`Client`](crate::Client) configured with
+/// [`ClientBuilder::event_server`](crate::ClientBuilder::event_server).
+/// Without one, every subscription binds its own port on the interface which routes to the
+/// device.
+///
+/// Notifications are answered without waiting for the stream of their subscription to be
+/// polled. If it falls behind by more than 16 events, further ones are dropped, which the next
+/// event reports as a [`SequenceGap`](crate::SequenceGap).
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn event_server(devices: Vec<rupnp::Device>) -> Result<(), rupnp::Error> {
+/// use rupnp::{Client, EventServer};
+///
+/// let server = EventServer::builder().port_range(7000..=7010).build().await?;
+/// let client = Client::builder().event_server(server).build();
+///
+/// let mut subscriptions = Vec::new();
+/// for device in &devices {
+///     for service in device.services() {
+///         subscriptions.push(client.subscription(service, 300).await?);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct EventServer {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    local_addr: SocketAddr,
+    callback_url: Option<String>,
+    routes: Routes,
+    next_id: AtomicU64,
+    accept: JoinHandle<()>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// Builder for an [`EventServer`] with custom settings.
+#[derive(Debug, Clone, Default)]
+pub struct EventServerBuilder {
+    // a socket address to keep the scope id of link-local IPv6 addresses, the port is ignored
+    bind_addr: Option<SocketAddr>,
+    ports: Option<RangeInclusive<u16>>,
+    callback_url: Option<Uri>,
+}
+
+impl EventServerBuilder {
+    /// The local address to listen on. Defaults to all IPv6 and IPv4 interfaces, or only the
+    /// IPv4 ones if IPv6 is unavailable. When listening on all interfaces, the callback URL
+    /// sent to each device contains the address of the interface that routes to it.
+    pub fn bind_addr(mut self, bind_addr: IpAddr) -> Self {
+        self.bind_addr = Some(SocketAddr::new(bind_addr, 0));
+        self
+    }
+
+    /// Listen on the first free port of the range, e.g. the ones opened in a firewall.
+    /// Defaults to a port chosen by the operating system.
+    pub fn port_range(mut self, ports: RangeInclusive<u16>) -> Self {
+        self.ports = Some(ports);
+        self
+    }
+
+    /// Listen on exactly this port.
+    pub fn port(self, port: u16) -> Self {
+        self.port_range(port..=port)
+    }
+
+    /// The URL the devices should send their events to, instead of the address of the
+    /// server, e.g. when it is behind NAT or a reverse proxy. The path of each subscription is
+    /// appended to it.
+    pub fn callback_url(mut self, callback_url: Uri) -> Self {
+        self.callback_url = Some(callback_url);
+        self
+    }
+
+    pub async fn build(self) -> Result<EventServer> {
+        let listener = match self.ports {
+            None => bind(self.bind_addr, 0).await?,
+            Some(ports) => bind_in_range(self.bind_addr, ports).await?,
+        };
+        let local_addr = listener.local_addr()?;
+
+        let routes = Routes::default();
+        let accept = tokio::spawn(accept_loop(listener, Arc::clone(&routes)));
+
+        let callback_url = self
+            .callback_url
+            .map(|url| url.to_string().trim_end_matches('/').to_string());
+
+        Ok(EventServer {
+            inner: Arc::new(Inner {
+                local_addr,
+                callback_url,
+                routes,
+                next_id: AtomicU64::new(0),
+                accept,
+            }),
+        })
+    }
+}
+
+async fn bind_in_range(
+    addr: Option<SocketAddr>,
+    ports: RangeInclusive<u16>,
+) -> Result<TcpListener> {
+    for port in ports {
+        match bind(addr, port).await {
+            Ok(listener) => return Ok(listener),
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(Error::IO(io::Error::new(
+        io::ErrorKind::AddrInUse,
+        "no free port in the range",
+    )))
+}
+
+async fn bind(addr: Option<SocketAddr>, port: u16) -> io::Result<TcpListener> {
+    match addr {
+        Some(mut addr) => {
+            addr.set_port(port);
+            TcpListener::bind(addr).await
+        }
+        None => match bind_dual_stack(port) {
+            Err(err) if err.kind() != io::ErrorKind::AddrInUse => {
+                TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).await
+            }
+            bound => bound,
+        },
+    }
+}
+
+/// Listens on all interfaces for both IPv6 and IPv4 connections, which isn't the default on
+/// every platform.
+fn bind_dual_stack(port: u16) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_only_v6(false)?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+    socket.listen(1024)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+impl EventServer {
+    /// Starts listening on all interfaces, on a port chosen by the operating system.
+    pub async fn new() -> Result<Self> {
+        EventServer::builder().build().await
+    }
+
+    /// Starts listening on `addr`, including the scope id of link-local IPv6 addresses.
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        EventServerBuilder {
+            bind_addr: Some(addr),
+            ..EventServer::builder()
+        }
+        .port(addr.port())
+        .build()
+        .await
+    }
+
+    pub fn builder() -> EventServerBuilder {
+        EventServerBuilder::default()
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+
+    /// Registers a new callback path for a subscription to a service of the device at `url`.
+    /// Returns the callback URL and the stream of events sent to it, which unregisters the
+    /// path when dropped.
+    pub(crate) async fn register(
+        &self,
+        url: &Uri,
+        recording: Option<(Recorder, Uri)>,
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let path = format!("/event/{id}");
+
+        let callback = match &self.inner.callback_url {
+            Some(callback_url) => format!("{callback_url}{path}"),
+            None => {
+                let local_addr = self.inner.local_addr;
+                let ip = if local_addr.ip().is_unspecified() {
+                    let ip = utils::get_local_addr_for(url).await?;
+                    if ip.is_ipv6() && local_addr.is_ipv4() {
+                        return Err(Error::NoLocalInterfaceOpen);
+                    }
+                    ip
+                } else {
+                    local_addr.ip()
+                };
+                // without the scope id of link-local addresses, which only means something
+                // on this host
+                let addr = SocketAddr::new(ip, local_addr.port());
+                format!("http://{addr}{path}")
+            }
+        };
+
+        let (events, rx) = mpsc::channel(EVENT_BUFFER);
+        let route = Route { events, recording };
+        self.inner
+            .routes
+            .lock()
+            .unwrap()
+            .insert(path.clone(), route);
+
+        let registration = Registration {
+            server: self.clone(),
+            path,
+        };
+        Ok((callback, Gen::new(move |co| forward(registration, rx, co))))
+    }
+}
+
+impl fmt::Debug for EventServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventServer")
+            .field("local_addr", &self.inner.local_addr)
+            .field("subscriptions", &self.inner.routes.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Removes the callback path from the server when the stream of events is dropped.
+struct Registration {
+    server: EventServer,
+    path: String,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.server.inner.routes.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Yields the received events in the order they arrived, marking gaps in their sequence.
+async fn forward(
+    _registration: Registration,
+    mut rx: mpsc::Receiver<Result<Event>>,
+    co: Co<Result<Event>>,
+) {
+    let mut tracker = SequenceTracker::default();
+    while let Some(mut event) = rx.recv().await {
+        if let Ok(event) = &mut event {
+            tracker.track(event);
+        }
+        co.yield_(event).await;
+    }
+}
+
+async fn accept_loop(listener: TcpListener, routes: Routes) {
+    let mut failing = false;
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                // report it once, not on every retry while it persists
+                if !failing {
+                    broadcast(&routes, err);
+                }
+                failing = true;
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        failing = false;
+
+        let routes = Arc::clone(&routes);
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, Arc::clone(&routes)));
+            // errors of a single connection are the publisher's problem
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+/// Reports an error of the listening socket to every subscription.
+fn broadcast(routes: &Routes, err: io::Error) {
+    let routes = routes.lock().unwrap();
+    for route in routes.values() {
+        let err = Error::IO(io::Error::new(err.kind(), err.to_string()));
+        let _ = route.events.try_send(Err(err));
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    routes: Routes,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let route = routes
+        .lock()
+        .unwrap()
+        .get(request.uri().path())
+        .map(|route| (route.events.clone(), route.recording.clone()));
+    // the subscription is unknown or was dropped
+    let Some((tx, recording)) = route else {
+        return Ok(respond(StatusCode::PRECONDITION_FAILED));
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match read_body(body).await {
+        Ok(body) => body,
+        Err(status) => return Ok(respond(status)),
+    };
+    if let Some((recorder, url)) = recording {
+        recorder.record_notification(&url, &parts.method, &parts.headers, &body);
+    }
+
+    let status = match parse_notify(&parts.method, &parts.headers, &body) {
+        // answered right away, devices drop subscriptions whose callbacks are slow
+        Ok(event) => match tx.try_send(Ok(event)) {
+            Ok(()) => StatusCode::OK,
+            // the device isn't at fault, the consumer sees the lost event as a gap in the
+            // sequence of the next one
+            Err(TrySendError::Full(_)) => StatusCode::OK,
+            // nobody is listening anymore, so the subscription is as good as cancelled
+            Err(TrySendError::Closed(_)) => StatusCode::PRECONDITION_FAILED,
+        },
+        Err(status) => status,
+    };
+    Ok(respond(status))
+}
+
+fn respond(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::default())
+        .expect("infallible")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn callback_urls() {
+        let device = Uri::from_static("http://127.0.0.1:1400/description.xml");
+
+        let server = EventServer::new().await.unwrap();
+        let (callback, _events) = server.register(&device, None).await.unwrap();
+        let port = server.local_addr().port();
+        assert_eq!(callback, format!("http://127.0.0.1:{port}/event/0"));
+
+        let taken = server.local_addr().port();
+        let server = EventServer::builder()
+            .bind_addr(Ipv4Addr::LOCALHOST.into())
+            .port_range(taken..=taken.saturating_add(10))
+            .callback_url(Uri::from_static("http://203.0.113.5:8080/upnp/"))
+            .build()
+            .await
+            .unwrap();
+        assert_ne!(server.local_addr().port(), taken);
+        let (callback, _events) = server.register(&device, None).await.unwrap();
+        assert_eq!(callback, "http://203.0.113.5:8080/upnp/event/0");
+    }
+
+    #[tokio::test]
+    async fn slow_consumer() {
+        let server = EventServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let device = Uri::from_static("http://127.0.0.1:1400/");
+        let (url, events) = server.register(&device, None).await.unwrap();
+        let mut events = std::pin::pin!(events);
+
+        let client = crate::Client::new();
+        let notify = |seq: u32| {
+            let request = Request::builder()
+                .method("NOTIFY")
+                .uri(&url)
+                .header("NT", "upnp:event")
+                .header("NTS", "upnp:propchange")
+                .header("SID", "uuid:1")
+                .header("SEQ", seq.to_string())
+                .body(Full::from(
+                    "<e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\"/>",
+                ))
+                .unwrap();
+            client.request(request)
+        };
+
+        // nobody reads the events, but the device doesn't have to wait
+        for seq in 0..20 {
+            let response = tokio::time::timeout(Duration::from_secs(1), notify(seq))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        for seq in 0..EVENT_BUFFER as u32 {
+            let event = futures_util::StreamExt::next(&mut events).await.unwrap();
+            assert_eq!(event.unwrap().seq(), seq);
+        }
+
+        notify(20).await.unwrap();
+        let event = futures_util::StreamExt::next(&mut events).await.unwrap();
+        assert_eq!(
+            event.unwrap().gap(),
+            Some(crate::SequenceGap::Skipped {
+                expected: EVENT_BUFFER as u32,
+                received: 20
+            })
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use super::Event;
+use crate::{
+    scpd::{DataType, Value, SCPD},
+    Error, FromArgValue, Result,
+};
+use futures_core::stream::Stream;
+use futures_util::stream::StreamExt;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+
+/// The current values of the evented state variables of a subscribed service.
+///
+/// It knows the state variables which [send events](crate::scpd::StateVariable::sends_events)
+/// from the service's SCPD and updates them with every event passed to
+/// [`apply`](ServiceState::apply) or [`track`](ServiceState::track).
+/// Variables which aren't in the SCPD are kept as well, but can't be read as a [`Value`].
+///
+/// Cloning a `ServiceState` is cheap and shares the values, so it can be updated in one task
+/// and read in others.
+///
+/// Services like `AVTransport` event all their state through `LastChange`,
+/// see [`LastChange`](crate::LastChange).
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # use futures::prelude::*;
+/// # async fn service_state(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+/// use rupnp::ServiceState;
+///
+/// let scpd = service.scpd().await?;
+/// let state = ServiceState::new(&scpd);
+///
+/// let mut volume = state.watch("Volume").expect("Volume is evented");
+/// tokio::spawn(async move {
+///     while volume.changed().await.is_ok() {
+///         println!("volume changed to {:?}", *volume.borrow());
+///     }
+/// });
+///
+/// let subscription = service.subscription(300).await?;
+/// let mut events = std::pin::pin!(state.track(subscription));
+/// while let Some(_event) = events.try_next().await? {
+///     let mute: Option<bool> = state.get_as("Mute").transpose()?;
+///     println!("mute: {mute:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ServiceState {
+    variables: Arc<Mutex<HashMap<String, Variable>>>,
+}
+
+struct Variable {
+    datatype: Option<DataType>,
+    value: watch::Sender<Option<String>>,
+}
+
+impl Variable {
+    fn new(datatype: Option<DataType>) -> Self {
+        let (value, _) = watch::channel(None);
+        Variable { datatype, value }
+    }
+}
+
+impl ServiceState {
+    /// Creates an empty state for the evented state variables of `scpd`.
+    pub fn new(scpd: &SCPD) -> Self {
+        let variables = scpd
+            .state_variables()
+            .iter()
+            .filter(|variable| variable.sends_events())
+            .map(|variable| {
+                let name = variable.name().to_string();
+                (name, Variable::new(Some(variable.datatype())))
+            })
+            .collect();
+
+        ServiceState {
+            variables: Arc::new(Mutex::new(variables)),
+        }
+    }
+
+    /// Updates the state with the values of an event.
+    /// Watchers are only notified of variables whose value changed.
+    pub fn apply(&self, event: &Event) {
+        let mut variables = self.variables.lock().unwrap();
+        for (name, value) in event.iter() {
+            let variable = variables
+                .entry(name.to_string())
+                .or_insert_with(|| Variable::new(None));
+            variable.value.send_if_modified(|current| {
+                if current.as_deref() == Some(value) {
+                    return false;
+                }
+                *current = Some(value.to_string());
+                true
+            });
+        }
+    }
+
+    /// Applies every event of `events` to the state and passes them on.
+    pub fn track<S>(&self, events: S) -> impl Stream<Item = Result<Event>>
+    where
+        S: Stream<Item = Result<Event>>,
+    {
+        let state = self.clone();
+        events.inspect(move |event| {
+            if let Ok(event) = event {
+                state.apply(event);
+            }
+        })
+    }
+
+    /// Returns the value of the state variable called `name`, `None` if no event contained it
+    /// yet.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let variables = self.variables.lock().unwrap();
+        let value = variables.get(name)?.value.borrow().clone();
+        value
+    }
+
+    /// Returns the value of the state variable called `name` parsed according to its datatype
+    /// in the SCPD. `None` if the variable isn't in the SCPD or has no value yet.
+    pub fn value(&self, name: &str) -> Option<Result<Value>> {
+        let variables = self.variables.lock().unwrap();
+        let variable = variables.get(name)?;
+        let datatype = variable.datatype?;
+        let value = variable.value.borrow();
+
+        let parsed = Value::parse(datatype, value.as_deref()?).map_err(Error::invalid_response);
+        Some(parsed)
+    }
+
+    /// Returns the value of the state variable called `name` parsed as `T`, `None` if it has
+    /// no value yet.
+    pub fn get_as<T: FromArgValue>(&self, name: &str) -> Option<Result<T>> {
+        self.get(name).map(|value| T::from_arg_value(&value))
+    }
+
+    /// Returns a receiver which is notified whenever the value of the state variable called
+    /// `name` changes. `None` if the variable isn't evented according to the SCPD and no event
+    /// contained it yet.
+    pub fn watch(&self, name: &str) -> Option<watch::Receiver<Option<String>>> {
+        let variables = self.variables.lock().unwrap();
+        Some(variables.get(name)?.value.subscribe())
+    }
+
+    /// The names and values of all state variables which have a value.
+    pub fn snapshot(&self) -> HashMap<String, String> {
+        let variables = self.variables.lock().unwrap();
+        variables
+            .iter()
+            .filter_map(|(name, variable)| Some((name.clone(), variable.value.borrow().clone()?)))
+            .collect()
+    }
+}
+
+impl fmt::Debug for ServiceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.snapshot()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssdp::URN;
+
+    #[test]
+    fn apply_events() {
+        let scpd = r#"<?xml version="1.0"?>
+            <scpd xmlns="urn:schemas-upnp-org:service-1-0">
+              <specVersion><major>1</major><minor>0</minor></specVersion>
+              <actionList/>
+              <serviceStateTable>
+                <stateVariable sendEvents="yes"><name>Volume</name><dataType>ui2</dataType></stateVariable>
+                <stateVariable sendEvents="yes"><name>Mute</name><dataType>boolean</dataType></stateVariable>
+                <stateVariable sendEvents="no"><name>A_ARG_TYPE_Channel</name><dataType>string</dataType></stateVariable>
+              </serviceStateTable>
+            </scpd>"#;
+        let scpd = SCPD::from_xml_str(URN::service("schemas-upnp-org", "Test", 1), scpd).unwrap();
+
+        let state = ServiceState::new(&scpd);
+        let mut mute = state.watch("Mute").unwrap();
+        assert!(state.watch("A_ARG_TYPE_Channel").is_none());
+
+        let event = |seq, properties: &[(&str, &str)]| {
+            let properties = properties
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            Event::new("uuid:1".to_string(), seq, properties)
+        };
+
+        state.apply(&event(
+            0,
+            &[("Volume", "25"), ("Mute", "0"), ("Custom", "x")],
+        ));
+        assert_eq!(state.get("Volume").as_deref(), Some("25"));
+        assert_eq!(state.value("Volume").unwrap().unwrap(), Value::Ui2(25));
+        assert!(!state.get_as::<bool>("Mute").unwrap().unwrap());
+        assert_eq!(state.get("Custom").as_deref(), Some("x"));
+        assert!(state.value("Custom").is_none());
+        assert!(mute.has_changed().unwrap());
+        mute.borrow_and_update();
+
+        state.apply(&event(1, &[("Volume", "30"), ("Mute", "0")]));
+        assert!(!mute.has_changed().unwrap());
+        assert_eq!(state.get_as::<u16>("Volume").unwrap().unwrap(), 30);
+        assert_eq!(state.snapshot().len(), 3);
+    }
+}
@@ -0,0 +1,338 @@
+use super::{event::filter_variables, Event};
+use crate::{utils, Client, Error, Result, Service};
+use futures_core::stream::Stream;
+use http::{HeaderMap, StatusCode};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle, time::Instant};
+
+/// How long to wait at most before trying again after a renewal failed.
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+/// The shortest time between renewals, even if the device grants very short subscriptions.
+const MIN_RENEWAL_DELAY: Duration = Duration::from_secs(1);
+
+/// A subscription to the events of a service which is kept alive in the background.
+///
+/// The subscription is renewed when half of the duration granted by the device elapsed.
+/// If the device doesn't know the subscription anymore (`412 Precondition Failed`), e.g.
+/// because it rebooted, a new one is made. Errors while renewing are yielded by the stream
+/// without ending it.
+///
+/// Dropping the subscription sends an `UNSUBSCRIBE` request in the background, use
+/// [`unsubscribe`](Subscription::unsubscribe) to wait for it.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # use futures::prelude::*;
+/// # async fn subscription_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+/// let mut subscription = service.subscription(300).await?;
+///
+/// while let Some(event) = subscription.try_next().await? {
+///     for (name, value) in event.iter() {
+///         println!("{name} => {value}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Subscription {
+    sid: Arc<Mutex<String>>,
+    timeout: Arc<Mutex<Option<Duration>>>,
+    events: Pin<Box<dyn Stream<Item = Result<Event>> + Send>>,
+    errors: mpsc::UnboundedReceiver<Error>,
+    renewal: JoinHandle<()>,
+    client: Client,
+    service: Service,
+    unsubscribed: bool,
+}
+
+impl Subscription {
+    pub(crate) async fn new(
+        client: Client,
+        service: Service,
+        timeout_secs: u32,
+        state_variables: Vec<String>,
+    ) -> Result<Subscription> {
+        let (callback, events) = client.event_listener(&service).await?;
+
+        let request = service.subscribe_request(&callback, timeout_secs, &state_variables)?;
+        let (sid, timeout) = client
+            .send_subscription_request(request, timeout_secs)
+            .await?;
+
+        let sid = Arc::new(Mutex::new(sid));
+        let timeout = Arc::new(Mutex::new(timeout));
+        let (errors_tx, errors) = mpsc::unbounded_channel();
+
+        let renewal = tokio::spawn(
+            Renewal {
+                client: client.clone(),
+                service: service.clone(),
+                callback,
+                timeout_secs,
+                state_variables: state_variables.clone(),
+                sid: Arc::clone(&sid),
+                timeout: Arc::clone(&timeout),
+                errors: errors_tx,
+            }
+            .run(),
+        );
+
+        Ok(Subscription {
+            sid,
+            timeout,
+            events: Box::pin(filter_variables(events, state_variables)),
+            errors,
+            renewal,
+            client,
+            service,
+            unsubscribed: false,
+        })
+    }
+
+    /// The current subscription identifier. It changes when the subscription had to be made
+    /// again.
+    pub fn sid(&self) -> String {
+        self.sid.lock().unwrap().clone()
+    }
+
+    /// The duration the device granted for the subscription, `None` if it doesn't expire.
+    pub fn timeout(&self) -> Option<Duration> {
+        *self.timeout.lock().unwrap()
+    }
+
+    /// Stops renewing and cancels the subscription.
+    pub async fn unsubscribe(mut self) -> Result<()> {
+        self.renewal.abort();
+        self.unsubscribed = true;
+
+        let sid = self.sid();
+        self.client.unsubscribe(&self.service, &sid).await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Poll::Ready(Some(err)) = self.errors.poll_recv(cx) {
+            return Poll::Ready(Some(Err(err)));
+        }
+        self.events.as_mut().poll_next(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.renewal.abort();
+        if self.unsubscribed {
+            return;
+        }
+
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let service = self.service.clone();
+            let sid = self.sid();
+            runtime.spawn(async move {
+                let _ = client.unsubscribe(&service, &sid).await;
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("sid", &self.sid())
+            .field("timeout", &self.timeout())
+            .field("service", &self.service.service_type())
+            .field("url", self.service.event_sub_url())
+            .finish()
+    }
+}
+
+struct Renewal {
+    client: Client,
+    service: Service,
+    callback: String,
+    timeout_secs: u32,
+    state_variables: Vec<String>,
+    sid: Arc<Mutex<String>>,
+    timeout: Arc<Mutex<Option<Duration>>>,
+    errors: mpsc::UnboundedSender<Error>,
+}
+
+impl Renewal {
+    async fn run(self) {
+        let timeout = *self.timeout.lock().unwrap();
+        let mut expires_at = timeout.map(|timeout| Instant::now() + timeout);
+        let mut delay = timeout.map(renewal_delay);
+
+        while let Some(wait) = delay {
+            tokio::time::sleep(wait).await;
+
+            match self.renew().await {
+                Ok((sid, timeout)) => {
+                    *self.sid.lock().unwrap() = sid;
+                    *self.timeout.lock().unwrap() = timeout;
+                    expires_at = timeout.map(|timeout| Instant::now() + timeout);
+                    delay = timeout.map(renewal_delay);
+                }
+                Err(err) => {
+                    if self.errors.send(err).is_err() {
+                        return;
+                    }
+                    let remaining = expires_at.map_or(Duration::ZERO, |expires_at| {
+                        expires_at.saturating_duration_since(Instant::now())
+                    });
+                    delay = Some(retry_delay(remaining));
+                }
+            }
+        }
+    }
+
+    async fn renew(&self) -> Result<(String, Option<Duration>)> {
+        let sid = self.sid.lock().unwrap().clone();
+        let request = self
+            .service
+            .renew_subscription_request(&sid, self.timeout_secs);
+
+        match self
+            .client
+            .send_subscription_request(request, self.timeout_secs)
+            .await
+        {
+            // the device forgot about the subscription, e.g. because it rebooted
+            Err(Error::HttpErrorCode(StatusCode::PRECONDITION_FAILED)) => {
+                let request = self.service.subscribe_request(
+                    &self.callback,
+                    self.timeout_secs,
+                    &self.state_variables,
+                )?;
+                self.client
+                    .send_subscription_request(request, self.timeout_secs)
+                    .await
+            }
+            renewed => renewed,
+        }
+    }
+}
+
+/// Renews when half of the granted `timeout` elapsed, but not in a busy loop for timeouts like
+/// `Second-0`.
+fn renewal_delay(timeout: Duration) -> Duration {
+    (timeout / 2).max(MIN_RENEWAL_DELAY)
+}
+
+/// Retries a failed renewal after at most [`RETRY_INTERVAL`], but within half of the time the
+/// subscription is still valid, so short subscriptions are retried before they expire.
+fn retry_delay(remaining: Duration) -> Duration {
+    RETRY_INTERVAL.min(remaining / 2).max(MIN_RENEWAL_DELAY)
+}
+
+/// Checks the names of the state variables to subscribe to, which are sent in the `STATEVAR`
+/// header.
+pub(crate) fn state_variable_names(names: &[&str]) -> Result<Vec<String>> {
+    names
+        .iter()
+        .map(|name| match utils::is_ncname(name) {
+            true => Ok(name.to_string()),
+            false => Err(Error::ParseError("invalid state variable name")),
+        })
+        .collect()
+}
+
+/// Reads the `SID` and `TIMEOUT` headers of a response to a `SUBSCRIBE` request.
+/// The timeout is `None` for `Second-infinite`.
+pub(crate) fn parse_subscription_headers(
+    headers: &HeaderMap,
+    requested_secs: u32,
+) -> Result<(String, Option<Duration>)> {
+    let sid = headers
+        .get("SID")
+        .ok_or(Error::MissingHeader("SID"))?
+        .to_str()
+        .map_err(|_| Error::ParseError("SID header contained non-visible ASCII bytes"))?
+        .to_string();
+
+    let timeout = match headers.get("TIMEOUT").and_then(|v| v.to_str().ok()) {
+        Some(timeout) => parse_timeout(timeout)?,
+        None => Some(Duration::from_secs(requested_secs.into())),
+    };
+
+    Ok((sid, timeout))
+}
+
+/// `Second-1800` -> 30 minutes, `Second-infinite` -> `None`.
+fn parse_timeout(value: &str) -> Result<Option<Duration>> {
+    let value = value.trim();
+    let seconds = value
+        .get(.."Second-".len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case("Second-"))
+        .map(|_| &value["Second-".len()..])
+        .ok_or(Error::ParseError("`TIMEOUT` header is not `Second-N`"))?;
+
+    if seconds.eq_ignore_ascii_case("infinite") {
+        return Ok(None);
+    }
+    seconds
+        .parse()
+        .map(|seconds| Some(Duration::from_secs(seconds)))
+        .map_err(Error::invalid_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_state_variable_names() {
+        assert_eq!(
+            state_variable_names(&["Volume", "Mute"]).unwrap(),
+            ["Volume", "Mute"]
+        );
+        for names in [&["a\nb"][..], &["Volume", ""], &["A,B"]] {
+            assert!(matches!(
+                state_variable_names(names),
+                Err(Error::ParseError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn timeout_header() {
+        assert_eq!(
+            parse_timeout("Second-1800").unwrap(),
+            Some(Duration::from_secs(1800))
+        );
+        assert_eq!(
+            parse_timeout("second-300").unwrap(),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(parse_timeout("Second-infinite").unwrap(), None);
+        assert!(parse_timeout("1800").is_err());
+        assert!(parse_timeout("Second-").is_err());
+    }
+
+    #[test]
+    fn renewal_delays() {
+        assert_eq!(
+            renewal_delay(Duration::from_secs(1800)),
+            Duration::from_secs(900)
+        );
+        assert_eq!(renewal_delay(Duration::from_secs(1)), MIN_RENEWAL_DELAY);
+        assert_eq!(renewal_delay(Duration::ZERO), MIN_RENEWAL_DELAY);
+    }
+
+    #[test]
+    fn retry_delays() {
+        assert_eq!(retry_delay(Duration::from_secs(900)), RETRY_INTERVAL);
+        // a subscription granted for 8 seconds has 4 seconds left when the renewal fails
+        assert_eq!(retry_delay(Duration::from_secs(4)), Duration::from_secs(2));
+        assert_eq!(retry_delay(Duration::ZERO), MIN_RENEWAL_DELAY);
+    }
+}
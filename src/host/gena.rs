@@ -0,0 +1,687 @@
+//! The publisher side of GENA: `SUBSCRIBE`, renewal and `UNSUBSCRIBE` requests of control
+//! points, and the `NOTIFY` requests sent to them.
+
+use super::{server_header, status, ResponseBody};
+use crate::{scpd::SCPD, utils, ArgValue, Client};
+use bytes::Bytes;
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use http_body_util::Full;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{oneshot, Notify},
+    time::Instant,
+};
+
+/// Granted when a control point doesn't ask for a duration.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1800);
+/// The longest subscription granted, also for `Second-infinite`.
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often expired subscriptions are removed when no event is sent.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends the changes of the evented state variables of a hosted service to its subscribers.
+///
+/// It is available to action handlers through [`ActionRequest::events`](super::ActionRequest::events)
+/// and to the rest of the program through [`DeviceHost::events`](super::DeviceHost::events).
+/// Cloning an `EventPublisher` is cheap and shares the state.
+///
+/// Every new subscriber receives the current values of all evented state variables first, those
+/// which weren't [`set`](EventPublisher::set) yet with the default value from the SCPD.
+/// Variables with a [maximum rate](super::DeviceHostBuilder::maximum_rate) or
+/// [minimum delta](super::DeviceHostBuilder::minimum_delta) are moderated accordingly.
+#[derive(Clone)]
+pub struct EventPublisher {
+    inner: Arc<Mutex<State>>,
+    client: Client,
+}
+
+struct State {
+    variables: Vec<Variable>,
+    subscribers: HashMap<String, Subscriber>,
+}
+
+struct Variable {
+    name: String,
+    value: String,
+    maximum_rate: Option<Duration>,
+    minimum_delta: Option<f64>,
+    last_evented: Option<(Instant, String)>,
+    flush_scheduled: bool,
+}
+
+struct Subscriber {
+    expires: Instant,
+    variables: Vec<String>,
+    events: Arc<Queue>,
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        self.events.close();
+    }
+}
+
+/// The properties waiting to be sent to one subscriber.
+///
+/// A newer value of a variable replaces a pending one, so the queue of a slow or unreachable
+/// subscriber never holds more than the evented variables.
+#[derive(Default)]
+struct Queue {
+    pending: Mutex<Pending>,
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct Pending {
+    properties: Vec<(String, String)>,
+    closed: bool,
+}
+
+impl Queue {
+    fn push(&self, properties: Vec<(String, String)>) {
+        let mut pending = self.pending.lock().unwrap();
+        for (name, value) in properties {
+            match pending.properties.iter_mut().find(|(n, _)| *n == name) {
+                Some(property) => property.1 = value,
+                None => pending.properties.push((name, value)),
+            }
+        }
+        drop(pending);
+        self.changed.notify_one();
+    }
+
+    fn close(&self) {
+        self.pending.lock().unwrap().closed = true;
+        self.changed.notify_one();
+    }
+
+    /// Waits for the next event, `None` once the subscription ended.
+    async fn pop(&self) -> Option<Vec<(String, String)>> {
+        loop {
+            {
+                let mut pending = self.pending.lock().unwrap();
+                if !pending.properties.is_empty() {
+                    return Some(std::mem::take(&mut pending.properties));
+                }
+                if pending.closed {
+                    return None;
+                }
+            }
+            self.changed.notified().await;
+        }
+    }
+}
+
+/// What to do with a changed value of a moderated variable.
+enum Moderation {
+    Send,
+    Suppress,
+    Delay(Instant),
+}
+
+impl Variable {
+    fn moderate(&self, now: Instant) -> Moderation {
+        let Some((evented_at, evented)) = &self.last_evented else {
+            return Moderation::Send;
+        };
+
+        if let Some(minimum_delta) = self.minimum_delta {
+            if let (Ok(evented), Ok(value)) = (evented.parse::<f64>(), self.value.parse::<f64>()) {
+                if (value - evented).abs() < minimum_delta {
+                    return Moderation::Suppress;
+                }
+            }
+        }
+        match self.maximum_rate {
+            Some(rate) if *evented_at + rate > now => Moderation::Delay(*evented_at + rate),
+            _ => Moderation::Send,
+        }
+    }
+}
+
+impl State {
+    /// Removes the subscribers which didn't renew in time, which ends their delivery.
+    fn expire(&mut self, now: Instant) {
+        self.subscribers
+            .retain(|_, subscriber| subscriber.expires > now);
+    }
+
+    /// Queues an event for every subscriber interested in one of the `properties`.
+    fn notify(&mut self, properties: &[(String, String)], now: Instant) {
+        self.expire(now);
+
+        for subscriber in self.subscribers.values() {
+            let properties: Vec<_> = properties
+                .iter()
+                .filter(|(name, _)| {
+                    subscriber.variables.is_empty() || subscriber.variables.contains(name)
+                })
+                .cloned()
+                .collect();
+            if !properties.is_empty() {
+                subscriber.events.push(properties);
+            }
+        }
+    }
+}
+
+impl EventPublisher {
+    pub(super) fn new(scpd: &SCPD, client: Client) -> Self {
+        let variables = scpd
+            .state_variables()
+            .iter()
+            .filter(|variable| variable.sends_events())
+            .map(|variable| Variable {
+                name: variable.name().to_string(),
+                value: variable.default().unwrap_or_default().to_string(),
+                maximum_rate: None,
+                minimum_delta: None,
+                last_evented: None,
+                flush_scheduled: false,
+            })
+            .collect();
+
+        EventPublisher {
+            inner: Arc::new(Mutex::new(State {
+                variables,
+                subscribers: HashMap::new(),
+            })),
+            client,
+        }
+    }
+
+    /// Whether the service has an evented state variable called `name`.
+    pub(crate) fn is_evented(&self, name: &str) -> bool {
+        let state = self.inner.lock().unwrap();
+        state.variables.iter().any(|variable| variable.name == name)
+    }
+
+    pub(super) fn set_maximum_rate(&self, name: &str, rate: Duration) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(variable) = state.variables.iter_mut().find(|v| v.name == name) {
+            variable.maximum_rate = Some(rate);
+        }
+    }
+
+    pub(super) fn set_minimum_delta(&self, name: &str, delta: f64) {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(variable) = state.variables.iter_mut().find(|v| v.name == name) {
+            variable.minimum_delta = Some(delta);
+        }
+    }
+
+    /// Changes the value of the evented state variable called `name` and notifies the
+    /// subscribers, unless the value is the same as before.
+    /// Variables which don't send events according to the SCPD are ignored.
+    ///
+    /// Delaying the events of variables with a maximum rate requires a tokio runtime.
+    pub fn set(&self, name: &str, value: impl Into<ArgValue>) {
+        let value = value.into().as_str().to_string();
+        let now = Instant::now();
+
+        let mut state = self.inner.lock().unwrap();
+        let Some(variable) = state.variables.iter_mut().find(|v| v.name == name) else {
+            return;
+        };
+        if variable.value == value {
+            return;
+        }
+        variable.value = value;
+
+        match variable.moderate(now) {
+            Moderation::Send => {
+                variable.last_evented = Some((now, variable.value.clone()));
+                let properties = [(variable.name.clone(), variable.value.clone())];
+                state.notify(&properties, now);
+            }
+            Moderation::Suppress => {}
+            Moderation::Delay(until) => {
+                if variable.flush_scheduled {
+                    return;
+                }
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    variable.flush_scheduled = true;
+                    runtime.spawn(self.clone().flush(name.to_string(), until));
+                }
+            }
+        }
+    }
+
+    /// Sends the value of a variable whose event was delayed by its maximum rate.
+    async fn flush(self, name: String, until: Instant) {
+        tokio::time::sleep_until(until).await;
+
+        let now = Instant::now();
+        let mut state = self.inner.lock().unwrap();
+        let Some(variable) = state.variables.iter_mut().find(|v| v.name == name) else {
+            return;
+        };
+        variable.flush_scheduled = false;
+        if variable.last_evented.as_ref().map(|(_, value)| value) == Some(&variable.value) {
+            return;
+        }
+
+        variable.last_evented = Some((now, variable.value.clone()));
+        let properties = [(variable.name.clone(), variable.value.clone())];
+        state.notify(&properties, now);
+    }
+
+    /// Returns the current value of the evented state variable called `name`.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let state = self.inner.lock().unwrap();
+        state
+            .variables
+            .iter()
+            .find(|variable| variable.name == name)
+            .map(|variable| variable.value.clone())
+    }
+
+    /// Forgets all subscriptions, like a device which rebooted.
+    #[cfg(feature = "testing")]
+    pub(crate) fn clear_subscribers(&self) {
+        self.inner.lock().unwrap().subscribers.clear();
+    }
+
+    /// The number of active subscriptions.
+    pub fn subscribers(&self) -> usize {
+        let mut state = self.inner.lock().unwrap();
+        state.expire(Instant::now());
+        state.subscribers.len()
+    }
+
+    /// Removes expired subscriptions of the `publishers` periodically.
+    pub(super) async fn expire_subscribers(publishers: Vec<EventPublisher>) {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            let now = interval.tick().await;
+            for publisher in &publishers {
+                publisher.inner.lock().unwrap().expire(now);
+            }
+        }
+    }
+
+    /// Answers a `SUBSCRIBE` or `UNSUBSCRIBE` request to the event URL of the service.
+    pub(super) fn handle<B>(&self, request: &Request<B>) -> Response<ResponseBody> {
+        let headers = request.headers();
+        let has_sid = headers.contains_key("SID");
+        let is_new = headers.contains_key("CALLBACK") || headers.contains_key("NT");
+
+        let response = match request.method().as_str() {
+            _ if has_sid && is_new => status(StatusCode::BAD_REQUEST),
+            "SUBSCRIBE" if has_sid => self.renew(headers),
+            "SUBSCRIBE" => return self.subscribe(headers),
+            "UNSUBSCRIBE" => self.unsubscribe(headers),
+            _ => status(StatusCode::METHOD_NOT_ALLOWED),
+        };
+        response.map(ResponseBody::from)
+    }
+
+    fn subscribe(&self, headers: &HeaderMap) -> Response<ResponseBody> {
+        let callbacks = header(headers, "CALLBACK")
+            .map(parse_callbacks)
+            .unwrap_or_default();
+        if header(headers, "NT") != Some("upnp:event") || callbacks.is_empty() {
+            return status(StatusCode::PRECONDITION_FAILED).map(ResponseBody::from);
+        }
+
+        let requested: Vec<String> = header(headers, "STATEVAR")
+            .map(|variables| {
+                variables
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !requested.iter().all(|name| self.is_evented(name)) {
+            return status(StatusCode::PRECONDITION_FAILED).map(ResponseBody::from);
+        }
+
+        let sid = new_sid();
+        let timeout = granted_timeout(header(headers, "TIMEOUT"));
+        let events = Arc::new(Queue::default());
+
+        let mut state = self.inner.lock().unwrap();
+        // the initial event contains the current values of all variables of interest
+        let initial: Vec<_> = state
+            .variables
+            .iter()
+            .filter(|variable| requested.is_empty() || requested.contains(&variable.name))
+            .map(|variable| (variable.name.clone(), variable.value.clone()))
+            .collect();
+        events.push(initial);
+        state.subscribers.insert(
+            sid.clone(),
+            Subscriber {
+                expires: Instant::now() + timeout,
+                variables: requested.clone(),
+                events: Arc::clone(&events),
+            },
+        );
+        drop(state);
+
+        let (sent, subscribed) = oneshot::channel();
+        let client = self.client.clone();
+        tokio::spawn(deliver(client, sid.clone(), callbacks, events, subscribed));
+
+        let mut response = subscription_response(&sid, timeout);
+        if !requested.is_empty() {
+            let accepted = requested.join(",").parse().expect("valid header value");
+            response.headers_mut().insert("ACCEPTED-STATEVAR", accepted);
+        }
+        response.map(|body| ResponseBody::signal_sent(body, sent))
+    }
+
+    fn renew(&self, headers: &HeaderMap) -> Response<Full<Bytes>> {
+        let sid = header(headers, "SID").unwrap_or_default();
+        let timeout = granted_timeout(header(headers, "TIMEOUT"));
+        let now = Instant::now();
+
+        let mut state = self.inner.lock().unwrap();
+        state.expire(now);
+        match state.subscribers.get_mut(sid) {
+            Some(subscriber) => {
+                subscriber.expires = now + timeout;
+                subscription_response(sid, timeout)
+            }
+            _ => status(StatusCode::PRECONDITION_FAILED),
+        }
+    }
+
+    fn unsubscribe(&self, headers: &HeaderMap) -> Response<Full<Bytes>> {
+        let sid = header(headers, "SID").unwrap_or_default();
+
+        let mut state = self.inner.lock().unwrap();
+        match state.subscribers.remove(sid) {
+            Some(_) => status(StatusCode::OK),
+            None => status(StatusCode::PRECONDITION_FAILED),
+        }
+    }
+}
+
+impl fmt::Debug for EventPublisher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.lock().unwrap();
+        f.debug_struct("EventPublisher")
+            .field(
+                "variables",
+                &state
+                    .variables
+                    .iter()
+                    .map(|variable| (&variable.name, &variable.value))
+                    .collect::<Vec<_>>(),
+            )
+            .field("subscribers", &state.subscribers.len())
+            .finish()
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn subscription_response(sid: &str, timeout: Duration) -> Response<Full<Bytes>> {
+    Response::builder()
+        .header("SID", sid)
+        .header("TIMEOUT", format!("Second-{}", timeout.as_secs()))
+        .header(http::header::SERVER, server_header())
+        .body(Full::default())
+        .expect("infallible")
+}
+
+/// `<http://192.168.1.2:7000/event/0><http://[fe80::1]:7000/event/0>` -> both URLs.
+fn parse_callbacks(header: &str) -> Vec<Uri> {
+    header
+        .split(['<', '>'])
+        .map(str::trim)
+        .filter_map(|url| url.parse::<Uri>().ok())
+        .filter(|url| url.scheme_str() == Some("http") && url.host().is_some())
+        .collect()
+}
+
+/// The duration granted for a `TIMEOUT` header like `Second-300`.
+fn granted_timeout(header: Option<&str>) -> Duration {
+    let Some(seconds) = header.and_then(|header| {
+        let header = header.trim();
+        let prefix = header.get(.."Second-".len())?;
+        prefix
+            .eq_ignore_ascii_case("Second-")
+            .then(|| &header["Second-".len()..])
+    }) else {
+        return DEFAULT_TIMEOUT;
+    };
+
+    match seconds.parse() {
+        Ok(seconds) => Duration::from_secs(seconds).min(MAX_TIMEOUT),
+        Err(_) if seconds.eq_ignore_ascii_case("infinite") => MAX_TIMEOUT,
+        Err(_) => DEFAULT_TIMEOUT,
+    }
+}
+
+/// A new subscription identifier, `uuid:` followed by a random UUID.
+fn new_sid() -> String {
+    format!("uuid:{}", random_uuid())
+}
+
+/// A random (version 4) UUID.
+pub(crate) fn random_uuid() -> String {
+    // every `RandomState` is seeded differently, which is random enough for identifiers
+    let random = || {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(0);
+        hasher.finish()
+    };
+    let (high, low) = (random(), random());
+
+    format!(
+        "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xfff,
+        (low >> 48) & 0x3fff | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// The sequence number after `seq`. It wraps around to 1, since 0 is only used for the initial
+/// event.
+fn next_seq(seq: u32) -> u32 {
+    seq.checked_add(1).unwrap_or(1)
+}
+
+/// Sends the events of one subscription in order, to the first callback URL accepting them.
+/// It starts once the response to the `SUBSCRIBE` request was sent, because the control point
+/// has to know the SID before the initial event arrives.
+async fn deliver(
+    client: Client,
+    sid: String,
+    callbacks: Vec<Uri>,
+    events: Arc<Queue>,
+    subscribed: oneshot::Receiver<()>,
+) {
+    let _ = subscribed.await;
+    let mut seq = 0;
+    while let Some(properties) = events.pop().await {
+        let body = propertyset(&properties);
+        for callback in &callbacks {
+            let request = Request::builder()
+                .method(Method::from_bytes(b"NOTIFY").expect("valid method"))
+                .uri(callback)
+                .header("CONTENT-TYPE", "text/xml; charset=\"utf-8\"")
+                .header("NT", "upnp:event")
+                .header("NTS", "upnp:propchange")
+                .header("SID", &sid)
+                .header("SEQ", seq.to_string())
+                .body(Full::from(body.clone()))
+                .expect("valid request");
+
+            // an unreachable subscriber misses the event, but keeps its subscription
+            match client.request(request).await {
+                Ok(response) if response.status().is_success() => break,
+                _ => continue,
+            }
+        }
+        seq = next_seq(seq);
+    }
+}
+
+fn propertyset(properties: &[(String, String)]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0"?><e:propertyset xmlns:e="urn:schemas-upnp-org:event-1-0">"#,
+    );
+    for (name, value) in properties {
+        xml.push_str(&format!(
+            "<e:property><{name}>{}</{name}></e:property>",
+            utils::escape_xml(value)
+        ));
+    }
+    xml.push_str("</e:propertyset>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssdp::URN;
+
+    #[tokio::test]
+    async fn moderated_variables() {
+        let scpd = r#"<?xml version="1.0"?>
+            <scpd xmlns="urn:schemas-upnp-org:service-1-0">
+              <specVersion><major>1</major><minor>0</minor></specVersion>
+              <actionList/>
+              <serviceStateTable>
+                <stateVariable sendEvents="yes"><name>Volume</name><dataType>ui2</dataType><defaultValue>10</defaultValue></stateVariable>
+                <stateVariable sendEvents="yes"><name>Position</name><dataType>string</dataType></stateVariable>
+                <stateVariable sendEvents="no"><name>A_ARG_TYPE_InstanceID</name><dataType>ui4</dataType></stateVariable>
+              </serviceStateTable>
+            </scpd>"#;
+        let scpd = SCPD::from_xml_str(URN::service("schemas-upnp-org", "Test", 1), scpd).unwrap();
+        let publisher = EventPublisher::new(&scpd, Client::new());
+        publisher.set_minimum_delta("Volume", 5.0);
+        publisher.set_maximum_rate("Position", Duration::from_millis(100));
+
+        let events = Arc::new(Queue::default());
+        publisher.inner.lock().unwrap().subscribers.insert(
+            new_sid(),
+            Subscriber {
+                expires: Instant::now() + DEFAULT_TIMEOUT,
+                variables: Vec::new(),
+                events: Arc::clone(&events),
+            },
+        );
+        let received = || std::mem::take(&mut events.pending.lock().unwrap().properties);
+        let property = |name: &str, value: &str| (name.to_string(), value.to_string());
+
+        assert_eq!(publisher.get("Volume").as_deref(), Some("10"));
+        publisher.set("Volume", 12);
+        assert_eq!(received(), [property("Volume", "12")]);
+        publisher.set("Volume", 14);
+        publisher.set("Volume", 20);
+        publisher.set("A_ARG_TYPE_InstanceID", 1);
+        assert_eq!(received(), [property("Volume", "20")]);
+        assert_eq!(publisher.get("Volume").as_deref(), Some("20"));
+
+        publisher.set("Position", "0:00:01");
+        publisher.set("Position", "0:00:02");
+        publisher.set("Position", "0:00:03");
+        assert_eq!(received(), [property("Position", "0:00:01")]);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(received(), [property("Position", "0:00:03")]);
+
+        // values nobody picked up yet are replaced instead of queueing up
+        publisher.set("Volume", 30);
+        publisher.set("Volume", 40);
+        assert_eq!(received(), [property("Volume", "40")]);
+    }
+
+    #[tokio::test]
+    async fn initial_event_after_response() {
+        let scpd = r#"<?xml version="1.0"?>
+            <scpd xmlns="urn:schemas-upnp-org:service-1-0">
+              <specVersion><major>1</major><minor>0</minor></specVersion>
+              <actionList/>
+              <serviceStateTable>
+                <stateVariable sendEvents="yes"><name>Volume</name><dataType>ui2</dataType></stateVariable>
+              </serviceStateTable>
+            </scpd>"#;
+        let scpd = SCPD::from_xml_str(URN::service("schemas-upnp-org", "Test", 1), scpd).unwrap();
+        let publisher = EventPublisher::new(&scpd, Client::new());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let callback = format!("<http://{}/>", listener.local_addr().unwrap());
+        let request = Request::builder()
+            .method("SUBSCRIBE")
+            .header("CALLBACK", callback)
+            .header("NT", "upnp:event")
+            .body(())
+            .unwrap();
+        let response = publisher.handle(&request);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let accept = tokio::time::timeout(Duration::from_millis(200), listener.accept());
+        assert!(accept.await.is_err(), "notified before the response");
+        drop(response);
+        let accept = tokio::time::timeout(Duration::from_secs(5), listener.accept());
+        assert!(accept.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn expired_subscribers_are_removed() {
+        let scpd = r#"<?xml version="1.0"?>
+            <scpd xmlns="urn:schemas-upnp-org:service-1-0">
+              <specVersion><major>1</major><minor>0</minor></specVersion>
+              <actionList/>
+              <serviceStateTable>
+                <stateVariable sendEvents="yes"><name>Volume</name><dataType>ui2</dataType></stateVariable>
+              </serviceStateTable>
+            </scpd>"#;
+        let scpd = SCPD::from_xml_str(URN::service("schemas-upnp-org", "Test", 1), scpd).unwrap();
+        let publisher = EventPublisher::new(&scpd, Client::new());
+        let subscribe = |expires_in| {
+            let events = Arc::new(Queue::default());
+            publisher.inner.lock().unwrap().subscribers.insert(
+                new_sid(),
+                Subscriber {
+                    expires: Instant::now() + expires_in,
+                    variables: Vec::new(),
+                    events: Arc::clone(&events),
+                },
+            );
+            events
+        };
+
+        let events = subscribe(Duration::ZERO);
+        assert_eq!(publisher.subscribers(), 0);
+        assert!(events.pop().await.is_none());
+
+        // without any event being sent, the periodic check ends the delivery
+        let events = subscribe(Duration::from_millis(100));
+        let expiry = tokio::spawn(EventPublisher::expire_subscribers(vec![publisher.clone()]));
+        let popped = tokio::time::timeout(Duration::from_secs(5), events.pop()).await;
+        assert!(matches!(popped, Ok(None)));
+        expiry.abort();
+    }
+
+    #[test]
+    fn subscription_headers() {
+        assert_eq!(
+            granted_timeout(Some("Second-300")),
+            Duration::from_secs(300)
+        );
+        assert_eq!(granted_timeout(Some("Second-infinite")), MAX_TIMEOUT);
+        assert_eq!(granted_timeout(None), DEFAULT_TIMEOUT);
+        assert_eq!(next_seq(u32::MAX), 1);
+
+        let callbacks = parse_callbacks("<http://10.0.0.2:7000/event/0> <ftp://x/><http://[::1]/>");
+        assert_eq!(callbacks.len(), 2);
+        assert_ne!(new_sid(), new_sid());
+    }
+}
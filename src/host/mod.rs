@@ -0,0 +1,728 @@
+//! Hosting UPnP devices: serving their descriptions, answering control points' actions and
+//! announcing them on the network.
+
+mod gena;
+mod soap;
+mod ssdp;
+
+#[cfg(feature = "testing")]
+pub(crate) use gena::random_uuid;
+pub use gena::EventPublisher;
+
+use crate::{
+    error::UPnPError, httpu::SSDP_MULTICAST_V4, httpu::SSDP_PORT, scpd::SCPD, utils, ActionArgs,
+    Client, Device, Error, FromArgValue, Result, Service,
+};
+use bytes::Bytes;
+use http::{header, Method, Request, Response, StatusCode, Uri};
+use http_body_util::Full;
+use hyper::{
+    body::{Frame, Incoming, SizeHint},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use ssdp_client::URN;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    net::{Ipv4Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+const DEFAULT_DESCRIPTION_PATH: &str = "/description.xml";
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(1800);
+/// How long to wait before accepting connections again after it failed, e.g. because the
+/// process ran out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+type ActionFuture = Pin<Box<dyn Future<Output = Result<ActionArgs, UPnPError>> + Send>>;
+type Handler = Arc<dyn Fn(ActionRequest) -> ActionFuture + Send + Sync>;
+
+/// The `SERVER` header of all responses and announcements.
+fn server_header() -> String {
+    format!(
+        "{} UPnP/2.0 rupnp/{}",
+        std::env::consts::OS,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// An action invoked by a control point on a hosted service, see [`DeviceHostBuilder::service`].
+///
+/// The input arguments were already checked against the service's [`SCPD`].
+#[derive(Debug, Clone)]
+pub struct ActionRequest {
+    service_type: URN,
+    service_id: String,
+    action: String,
+    args: ActionArgs,
+    events: EventPublisher,
+}
+
+impl ActionRequest {
+    /// The type of the service the action was invoked on.
+    pub fn service_type(&self) -> &URN {
+        &self.service_type
+    }
+
+    /// The `serviceId` of the service the action was invoked on.
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// The name of the action, e.g. `GetVolume`.
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// The input arguments, in the order the control point sent them.
+    pub fn args(&self) -> &ActionArgs {
+        &self.args
+    }
+
+    /// The publisher of the service's events, to announce the state variables changed by the
+    /// action.
+    pub fn events(&self) -> &EventPublisher {
+        &self.events
+    }
+
+    /// Returns the value of the input argument called `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.args.get(name).map(|value| value.as_str())
+    }
+
+    /// Returns the input argument called `name`, parsed as `T`.
+    /// Fails with `402 Invalid Args` if it is missing or can't be parsed, so it can be returned
+    /// from the handler with `?`.
+    pub fn get_as<T: FromArgValue>(&self, name: &str) -> Result<T, UPnPError> {
+        self.get(name)
+            .ok_or_else(|| UPnPError::new(402, format!("missing argument `{name}`")))
+            .and_then(|value| {
+                T::from_arg_value(value).map_err(|err| UPnPError::new(402, err.to_string()))
+            })
+    }
+}
+
+struct ServiceDefinition {
+    service_id: String,
+    scpd: String,
+    handler: Handler,
+}
+
+enum Moderation {
+    MaximumRate(Duration),
+    MinimumDelta(f64),
+}
+
+/// Builder for a [`DeviceHost`], see [`DeviceHost::builder`].
+pub struct DeviceHostBuilder {
+    description: String,
+    services: Vec<ServiceDefinition>,
+    bind_addr: SocketAddr,
+    description_path: String,
+    max_age: Duration,
+    advertise: bool,
+    moderation: Vec<(String, String, Moderation)>,
+}
+
+impl DeviceHostBuilder {
+    /// Implements the service with the `serviceId` `service_id` of the device description.
+    ///
+    /// `scpd` is served to control points as it is, and every action invoked on the service is
+    /// checked against it before it is passed to `handler`. Invalid arguments are answered with
+    /// the matching UPnP error (`401`, `402`, `600` or `601`) without calling the handler.
+    pub fn service<F, Fut>(
+        mut self,
+        service_id: impl Into<String>,
+        scpd: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(ActionRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ActionArgs, UPnPError>> + Send + 'static,
+    {
+        let handler: Handler = Arc::new(move |request| Box::pin(handler(request)));
+        self.services.push(ServiceDefinition {
+            service_id: service_id.into(),
+            scpd: scpd.into(),
+            handler,
+        });
+        self
+    }
+
+    /// The address the HTTP server listens on. Defaults to a port chosen by the operating
+    /// system on all IPv4 interfaces.
+    pub fn bind_addr(mut self, bind_addr: SocketAddr) -> Self {
+        self.bind_addr = bind_addr;
+        self
+    }
+
+    /// The path the device description is served at. Defaults to `/description.xml`.
+    /// The paths of the services are taken from the `SCPDURL` and `controlURL` elements of the
+    /// description, relative to this path.
+    pub fn description_path(mut self, path: impl Into<String>) -> Self {
+        self.description_path = path.into();
+        self
+    }
+
+    /// How long control points may consider the device available after an announcement.
+    /// The device is announced again after half of it elapsed. Defaults to 30 minutes.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Whether to announce the device with SSDP and answer `M-SEARCH` requests. Defaults to
+    /// `true`; without it, control points need to know the [`location`](DeviceHost::location).
+    pub fn advertise(mut self, advertise: bool) -> Self {
+        self.advertise = advertise;
+        self
+    }
+
+    /// Events the state variable `variable` of the service `service_id` at most once per
+    /// `rate`, like the `maximumRate` of standardized services. Changes in between are sent
+    /// together once the time elapsed.
+    pub fn maximum_rate(
+        mut self,
+        service_id: impl Into<String>,
+        variable: impl Into<String>,
+        rate: Duration,
+    ) -> Self {
+        let moderation = Moderation::MaximumRate(rate);
+        self.moderation
+            .push((service_id.into(), variable.into(), moderation));
+        self
+    }
+
+    /// Events the numeric state variable `variable` of the service `service_id` only when it
+    /// changed by at least `delta` since its last event, like the `minimumDelta` of
+    /// standardized services.
+    pub fn minimum_delta(
+        mut self,
+        service_id: impl Into<String>,
+        variable: impl Into<String>,
+        delta: f64,
+    ) -> Self {
+        let moderation = Moderation::MinimumDelta(delta);
+        self.moderation
+            .push((service_id.into(), variable.into(), moderation));
+        self
+    }
+
+    /// Starts serving the device and announces it.
+    pub async fn start(self) -> Result<DeviceHost> {
+        let listener = TcpListener::bind(self.bind_addr).await?;
+        let local_addr = listener.local_addr()?;
+
+        // the address control points reach the device at, unless they are in another network
+        let ip = if local_addr.ip().is_unspecified() {
+            utils::local_ip_for((SSDP_MULTICAST_V4, SSDP_PORT).into())?
+        } else {
+            local_addr.ip()
+        };
+        let location: Uri = format!(
+            "http://{}{}",
+            SocketAddr::new(ip, local_addr.port()),
+            self.description_path
+        )
+        .parse()?;
+
+        let device = Device::from_xml(location.clone(), self.description.as_bytes(), &[])?;
+        let advertisements = ssdp::advertisements(&self.description)?;
+
+        let client = Client::new();
+        let mut services = Vec::new();
+        let mut routes = HashMap::new();
+        routes.insert(location.path().to_string(), Route::Description);
+        for definition in self.services {
+            let service = device
+                .services_iter()
+                .find(|service| service.service_id() == definition.service_id)
+                .ok_or_else(|| {
+                    Error::XmlMissingElement(
+                        "Device Description".to_string(),
+                        format!("service `{}`", definition.service_id),
+                    )
+                })?;
+            let scpd = SCPD::from_xml_str(service.service_type().clone(), &definition.scpd)?;
+
+            let index = services.len();
+            let path = |url: &Uri| url.path().to_string();
+            routes.insert(path(service.scpd_url()), Route::Scpd(index));
+            routes.insert(path(service.control_url()), Route::Control(index));
+            routes.insert(path(service.event_sub_url()), Route::Events(index));
+
+            let events = EventPublisher::new(&scpd, client.clone());
+            services.push(HostedService {
+                service: service.clone(),
+                scpd,
+                scpd_xml: definition.scpd,
+                handler: definition.handler,
+                events,
+            });
+        }
+
+        for (service_id, variable, moderation) in self.moderation {
+            let events = services
+                .iter()
+                .find(|hosted| hosted.service.service_id() == service_id)
+                .map(|hosted| &hosted.events)
+                .filter(|events| events.is_evented(&variable))
+                .ok_or_else(|| {
+                    Error::XmlMissingElement(
+                        format!("serviceStateTable of `{service_id}`"),
+                        format!("evented state variable `{variable}`"),
+                    )
+                })?;
+            match moderation {
+                Moderation::MaximumRate(rate) => events.set_maximum_rate(&variable, rate),
+                Moderation::MinimumDelta(delta) => events.set_minimum_delta(&variable, delta),
+            }
+        }
+
+        let shared = Arc::new(Shared {
+            description: self.description,
+            services,
+            routes,
+        });
+        let publishers = shared.services.iter().map(|s| s.events.clone()).collect();
+        let mut tasks = vec![
+            tokio::spawn(accept_loop(listener, Arc::clone(&shared))),
+            tokio::spawn(EventPublisher::expire_subscribers(publishers)),
+        ];
+
+        let advertiser =
+            ssdp::Advertiser::new(advertisements, location.clone(), local_addr, self.max_age);
+        let advertiser = Arc::new(advertiser);
+        if self.advertise {
+            tasks.push(tokio::spawn(ssdp::respond(Arc::clone(&advertiser))?));
+            tasks.push(tokio::spawn(ssdp::announce(Arc::clone(&advertiser))));
+        }
+
+        Ok(DeviceHost {
+            local_addr,
+            location,
+            advertiser: self.advertise.then_some(advertiser),
+            shared,
+            tasks,
+        })
+    }
+}
+
+impl fmt::Debug for DeviceHostBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let services: Vec<_> = self.services.iter().map(|s| &s.service_id).collect();
+        f.debug_struct("DeviceHostBuilder")
+            .field("services", &services)
+            .field("bind_addr", &self.bind_addr)
+            .field("description_path", &self.description_path)
+            .field("max_age", &self.max_age)
+            .field("advertise", &self.advertise)
+            .finish()
+    }
+}
+
+/// A UPnP device hosted by this process.
+///
+/// It serves the device description and the SCPDs of its services over HTTP, passes every
+/// action invoked by a control point to the handler of the service and responds with its
+/// output arguments, or with a SOAP fault if it returned a [`UPnPError`].
+/// Control points can subscribe to the evented state variables of the services, whose changes
+/// are published through their [`EventPublisher`].
+/// The device is announced with `ssdp:alive` messages until it is
+/// [shut down](DeviceHost::shutdown), which announces `ssdp:byebye`.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn host(description: &str, scpd: &str) -> Result<(), rupnp::Error> {
+/// use rupnp::{ActionArgs, DeviceHost, UPnPError};
+/// use std::sync::{atomic::{AtomicU16, Ordering}, Arc};
+///
+/// let volume = Arc::new(AtomicU16::new(25));
+/// let host = DeviceHost::builder(description)
+///     .service("urn:upnp-org:serviceId:RenderingControl", scpd, move |request| {
+///         let volume = Arc::clone(&volume);
+///         async move {
+///             match request.action() {
+///                 "GetVolume" => {
+///                     let volume = volume.load(Ordering::Relaxed);
+///                     Ok(ActionArgs::new().arg("CurrentVolume", volume))
+///                 }
+///                 "SetVolume" => {
+///                     volume.store(request.get_as("DesiredVolume")?, Ordering::Relaxed);
+///                     Ok(ActionArgs::new())
+///                 }
+///                 _ => Err(UPnPError::new(602, "Optional Action Not Implemented")),
+///             }
+///         }
+///     })
+///     .start()
+///     .await?;
+///
+/// println!("serving {}", host.location());
+/// tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+/// host.shutdown().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DeviceHost {
+    local_addr: SocketAddr,
+    location: Uri,
+    advertiser: Option<Arc<ssdp::Advertiser>>,
+    shared: Arc<Shared>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl DeviceHost {
+    /// Creates a builder for the device described by `description`, the XML document served
+    /// to control points as the device description.
+    pub fn builder(description: impl Into<String>) -> DeviceHostBuilder {
+        DeviceHostBuilder {
+            description: description.into(),
+            services: Vec::new(),
+            bind_addr: (Ipv4Addr::UNSPECIFIED, 0).into(),
+            description_path: DEFAULT_DESCRIPTION_PATH.to_string(),
+            max_age: DEFAULT_MAX_AGE,
+            advertise: true,
+            moderation: Vec::new(),
+        }
+    }
+
+    /// The address the HTTP server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The URL of the device description, as announced to control points.
+    pub fn location(&self) -> &Uri {
+        &self.location
+    }
+
+    /// The publisher of the events of the service with the `serviceId` `service_id`, `None`
+    /// if it isn't implemented by this host.
+    pub fn events(&self, service_id: &str) -> Option<&EventPublisher> {
+        self.shared
+            .services
+            .iter()
+            .find(|hosted| hosted.service.service_id() == service_id)
+            .map(|hosted| &hosted.events)
+    }
+
+    /// Stops serving the device and announces that it left the network.
+    pub async fn shutdown(mut self) {
+        self.stop();
+        if let Some(advertiser) = self.advertiser.take() {
+            let _ = advertiser.bye_bye().await;
+        }
+    }
+
+    fn stop(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for DeviceHost {
+    fn drop(&mut self) {
+        self.stop();
+        if let (Some(advertiser), Ok(runtime)) = (
+            self.advertiser.take(),
+            tokio::runtime::Handle::try_current(),
+        ) {
+            runtime.spawn(async move {
+                let _ = advertiser.bye_bye().await;
+            });
+        }
+    }
+}
+
+impl fmt::Debug for DeviceHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceHost")
+            .field("local_addr", &self.local_addr)
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+struct HostedService {
+    service: Service,
+    scpd: SCPD,
+    scpd_xml: String,
+    handler: Handler,
+    events: EventPublisher,
+}
+
+enum Route {
+    Description,
+    Scpd(usize),
+    Control(usize),
+    Events(usize),
+}
+
+struct Shared {
+    description: String,
+    services: Vec<HostedService>,
+    routes: HashMap<String, Route>,
+}
+
+async fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    loop {
+        // there is nobody to report errors to, control points retry or time out on their
+        // own, but don't keep failing in a hot loop until the cause went away
+        let Ok((stream, _)) = listener.accept().await else {
+            tokio::time::sleep(ACCEPT_BACKOFF).await;
+            continue;
+        };
+
+        let shared = Arc::clone(&shared);
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, Arc::clone(&shared)));
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    shared: Arc<Shared>,
+) -> Result<Response<ResponseBody>, Infallible> {
+    let response = match (request.method(), shared.routes.get(request.uri().path())) {
+        (_, None) => status(StatusCode::NOT_FOUND),
+        (&Method::GET, Some(Route::Description)) => xml(shared.description.clone()),
+        (&Method::GET, Some(&Route::Scpd(index))) => xml(shared.services[index].scpd_xml.clone()),
+        (&Method::POST, Some(&Route::Control(index))) => {
+            soap::dispatch(&shared.services[index], request).await
+        }
+        (_, Some(&Route::Events(index))) => {
+            return Ok(shared.services[index].events.handle(&request));
+        }
+        (_, Some(_)) => status(StatusCode::METHOD_NOT_ALLOWED),
+    };
+    Ok(response.map(ResponseBody::from))
+}
+
+fn status(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::default())
+        .expect("infallible")
+}
+
+fn xml(body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/xml; charset=\"utf-8\"")
+        .header(header::SERVER, server_header())
+        .body(Full::from(body))
+        .expect("infallible")
+}
+
+/// The body of a response, which can tell when the response was handed to the connection.
+struct ResponseBody {
+    body: Full<Bytes>,
+    /// Its receiver completes once the body is dropped.
+    _sent: Option<oneshot::Sender<()>>,
+}
+
+impl ResponseBody {
+    fn signal_sent(body: Full<Bytes>, sent: oneshot::Sender<()>) -> Self {
+        ResponseBody {
+            body,
+            _sent: Some(sent),
+        }
+    }
+}
+
+impl From<Full<Bytes>> for ResponseBody {
+    fn from(body: Full<Bytes>) -> Self {
+        ResponseBody { body, _sent: None }
+    }
+}
+
+impl hyper::body::Body for ResponseBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, Infallible>>> {
+        Pin::new(&mut self.get_mut().body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+        <root xmlns="urn:schemas-upnp-org:device-1-0">
+          <specVersion><major>1</major><minor>0</minor></specVersion>
+          <device>
+            <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+            <friendlyName>Test Renderer</friendlyName>
+            <manufacturer>rupnp</manufacturer>
+            <modelName>Test</modelName>
+            <UDN>uuid:5c863963-f2a2-491e-8b60-079cdadad147</UDN>
+            <serviceList>
+              <service>
+                <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+                <serviceId>urn:upnp-org:serviceId:RenderingControl</serviceId>
+                <SCPDURL>/RenderingControl/scpd.xml</SCPDURL>
+                <controlURL>/RenderingControl/control</controlURL>
+                <eventSubURL>/RenderingControl/event</eventSubURL>
+              </service>
+            </serviceList>
+          </device>
+        </root>"#;
+
+    const SCPD: &str = r#"<?xml version="1.0"?>
+        <scpd xmlns="urn:schemas-upnp-org:service-1-0">
+          <specVersion><major>1</major><minor>0</minor></specVersion>
+          <actionList>
+            <action>
+              <name>GetVolume</name>
+              <argumentList>
+                <argument><name>InstanceID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable></argument>
+                <argument><name>CurrentVolume</name><direction>out</direction><relatedStateVariable>Volume</relatedStateVariable></argument>
+              </argumentList>
+            </action>
+            <action>
+              <name>SetVolume</name>
+              <argumentList>
+                <argument><name>InstanceID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable></argument>
+                <argument><name>DesiredVolume</name><direction>in</direction><relatedStateVariable>Volume</relatedStateVariable></argument>
+              </argumentList>
+            </action>
+          </actionList>
+          <serviceStateTable>
+            <stateVariable sendEvents="no"><name>A_ARG_TYPE_InstanceID</name><dataType>ui4</dataType></stateVariable>
+            <stateVariable sendEvents="yes">
+              <name>Volume</name><dataType>ui2</dataType><defaultValue>25</defaultValue>
+              <allowedValueRange><minimum>0</minimum><maximum>100</maximum></allowedValueRange>
+            </stateVariable>
+          </serviceStateTable>
+        </scpd>"#;
+
+    #[tokio::test]
+    async fn serve_and_dispatch_actions() {
+        let host = DeviceHost::builder(DESCRIPTION)
+            .service(
+                "urn:upnp-org:serviceId:RenderingControl",
+                SCPD,
+                |request| async move {
+                    match request.action() {
+                        "GetVolume" if request.get_as::<u32>("InstanceID")? == 0 => {
+                            Ok(ActionArgs::new().arg("CurrentVolume", 25))
+                        }
+                        "GetVolume" if request.get_as::<u32>("InstanceID")? == 2 => {
+                            Ok(ActionArgs::new().arg("Current Volume", 25))
+                        }
+                        "GetVolume" => Err(UPnPError::new(702, "Invalid InstanceID")),
+                        _ => Err(UPnPError::new(602, "Optional Action Not Implemented")),
+                    }
+                },
+            )
+            .bind_addr((Ipv4Addr::LOCALHOST, 0).into())
+            .advertise(false)
+            .start()
+            .await
+            .unwrap();
+
+        let device = Device::from_url(host.location().clone()).await.unwrap();
+        assert_eq!(device.friendly_name(), "Test Renderer");
+        let service = device
+            .find_service(&URN::service("schemas-upnp-org", "RenderingControl", 1))
+            .unwrap();
+        let scpd = service.scpd().await.unwrap();
+        assert!(scpd.action("SetVolume").is_some());
+
+        let call = |action: &'static str, args: ActionArgs| {
+            let service = service.clone();
+            async move { service.call(action, &args).await }
+        };
+        let err_code = |result: Result<_>| match result {
+            Err(Error::UPnPError(err)) => err.err_code(),
+            other => panic!("expected a UPnP error, got {:?}", other),
+        };
+        let volume = |instance: u32| ActionArgs::new().arg("InstanceID", instance);
+        let set_volume = |desired: &str| volume(0).arg("DesiredVolume", desired);
+
+        let response = call("GetVolume", volume(0)).await.unwrap();
+        assert_eq!(response.get_as::<u16>("CurrentVolume").unwrap(), 25);
+        assert_eq!(err_code(call("GetVolume", volume(1)).await), 702);
+        // an output argument which can't be sent
+        assert_eq!(err_code(call("GetVolume", volume(2)).await), 501);
+
+        assert_eq!(err_code(call("SetVolume", set_volume("101")).await), 601);
+        assert_eq!(err_code(call("SetVolume", set_volume("loud")).await), 600);
+        assert_eq!(err_code(call("SetVolume", set_volume("50")).await), 602);
+        assert_eq!(err_code(call("Stop", ActionArgs::new()).await), 401);
+
+        host.shutdown().await;
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn publish_events() {
+        use futures_util::TryStreamExt;
+
+        let host = DeviceHost::builder(DESCRIPTION)
+            .service(
+                "urn:upnp-org:serviceId:RenderingControl",
+                SCPD,
+                |request| async move {
+                    let volume: u16 = request.get_as("DesiredVolume")?;
+                    request.events().set("Volume", volume);
+                    Ok(ActionArgs::new())
+                },
+            )
+            .bind_addr((Ipv4Addr::LOCALHOST, 0).into())
+            .advertise(false)
+            .start()
+            .await
+            .unwrap();
+        let events = host
+            .events("urn:upnp-org:serviceId:RenderingControl")
+            .unwrap();
+
+        let device = Device::from_url(host.location().clone()).await.unwrap();
+        let service = device.services()[0].clone();
+        let mut subscription = service.subscription(300).await.unwrap();
+        assert_eq!(subscription.timeout(), Some(Duration::from_secs(300)));
+        assert_eq!(events.subscribers(), 1);
+
+        let initial = subscription.try_next().await.unwrap().unwrap();
+        assert_eq!((initial.seq(), initial.get("Volume")), (0, Some("25")));
+
+        let args = ActionArgs::new()
+            .arg("InstanceID", 0)
+            .arg("DesiredVolume", 30);
+        service.call("SetVolume", &args).await.unwrap();
+        let event = subscription.try_next().await.unwrap().unwrap();
+        assert_eq!((event.seq(), event.get("Volume")), (1, Some("30")));
+        assert_eq!(events.get("Volume").as_deref(), Some("30"));
+
+        subscription.unsubscribe().await.unwrap();
+        assert_eq!(events.subscribers(), 0);
+        host.shutdown().await;
+    }
+}
@@ -0,0 +1,120 @@
+use super::{server_header, status, ActionRequest, HostedService};
+use crate::{error::UPnPError, utils, ActionArgs};
+use bytes::Bytes;
+use http::{header, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use roxmltree::{Document, Node};
+
+/// The largest SOAP request accepted, far more than any action needs.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// Passes an action request to the handler of the service and turns its result into a SOAP
+/// response or fault.
+pub(super) async fn dispatch(
+    service: &HostedService,
+    request: Request<Incoming>,
+) -> Response<Full<Bytes>> {
+    let body = match Limited::new(request.into_body(), MAX_REQUEST_SIZE)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return status(StatusCode::BAD_REQUEST),
+    };
+    let (action, args) = match std::str::from_utf8(&body)
+        .ok()
+        .and_then(|body| parse_action_request(body).ok())
+    {
+        Some(parsed) => parsed,
+        None => return status(StatusCode::BAD_REQUEST),
+    };
+
+    if let Err(err) = service.scpd.validate(&action, &args) {
+        return fault(&UPnPError::new(err.err_code(), err.to_string()));
+    }
+
+    let request = ActionRequest {
+        service_type: service.service.service_type().clone(),
+        service_id: service.service.service_id().to_string(),
+        action,
+        args,
+        events: service.events.clone(),
+    };
+    let action = request.action.clone();
+    let output = (service.handler)(request).await.and_then(|output| {
+        // an invalid output argument name is the handler's fault: 501 Action Failed
+        output
+            .to_xml()
+            .map_err(|err| UPnPError::new(501, err.to_string()))
+    });
+    match output {
+        Ok(output) => respond(
+            &service.service.service_type().to_string(),
+            &action,
+            &output,
+        ),
+        Err(err) => fault(&err),
+    }
+}
+
+/// Reads the name and the input arguments of the action in the body of a SOAP request.
+fn parse_action_request(body: &str) -> crate::Result<(String, ActionArgs)> {
+    let document = Document::parse(body)?;
+    let action = utils::find_root(&document, "Body", "SOAP Request")?
+        .first_element_child()
+        .ok_or_else(|| crate::Error::XmlMissingElement("Body".to_string(), "action".to_string()))?;
+
+    let args = action
+        .children()
+        .filter(Node::is_element)
+        .map(|node| {
+            let value = node.text().unwrap_or_default();
+            (node.tag_name().name(), value)
+        })
+        .collect();
+
+    Ok((action.tag_name().name().to_string(), args))
+}
+
+fn respond(service_type: &str, action: &str, output: &str) -> Response<Full<Bytes>> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
+<s:Body><u:{action}Response xmlns:u="{service_type}">{output}</u:{action}Response></s:Body>
+</s:Envelope>"#,
+    );
+    soap_response(StatusCode::OK, body)
+}
+
+fn fault(err: &UPnPError) -> Response<Full<Bytes>> {
+    let description = err
+        .description()
+        .unwrap_or_else(|| err.err_code_description());
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">
+<s:Body><s:Fault>
+<faultcode>s:Client</faultcode>
+<faultstring>UPnPError</faultstring>
+<detail><UPnPError xmlns="urn:schemas-upnp-org:control-1-0">
+<errorCode>{}</errorCode>
+<errorDescription>{}</errorDescription>
+</UPnPError></detail>
+</s:Fault></s:Body>
+</s:Envelope>"#,
+        err.err_code(),
+        utils::escape_xml(description)
+    );
+    soap_response(StatusCode::INTERNAL_SERVER_ERROR, body)
+}
+
+fn soap_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/xml; charset=\"utf-8\"")
+        .header("EXT", "")
+        .header(header::SERVER, server_header())
+        .body(Full::from(body))
+        .expect("infallible")
+}
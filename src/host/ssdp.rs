@@ -0,0 +1,386 @@
+use super::server_header;
+use crate::{
+    httpu::{self, HttpuMessage, SSDP_MULTICAST_V4, SSDP_PORT},
+    utils, Error, Result,
+};
+use http::Uri;
+use roxmltree::{Document, Node};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    future::Future,
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::net::UdpSocket;
+
+/// The longest a search response is delayed, even if the control point allows more.
+const MAX_RESPONSE_DELAY: Duration = Duration::from_secs(5);
+const MULTICAST_TTL: u32 = 2;
+
+/// A notification type the device is announced with, and the unique service name belonging to
+/// it, e.g. `upnp:rootdevice` and `uuid:<udn>::upnp:rootdevice`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Advertisement {
+    nt: String,
+    usn: String,
+}
+
+/// Collects the announcements required for a device description: the root device, and the
+/// UDN, type and service types of every (embedded) device.
+pub(super) fn advertisements(description: &str) -> Result<Vec<Advertisement>> {
+    let document = Document::parse(description)?;
+    let root = utils::find_root(&document, "device", "Device Description")?;
+
+    let mut advertisements = vec![Advertisement {
+        nt: "upnp:rootdevice".to_string(),
+        usn: format!("{}::upnp:rootdevice", udn(root)?),
+    }];
+    for device in root
+        .descendants()
+        .filter(|node| node.tag_name().name() == "device")
+    {
+        let udn = udn(device)?;
+        let types = device
+            .children()
+            .filter(|node| node.tag_name().name() == "deviceType")
+            .chain(
+                device
+                    .children()
+                    .filter(|node| node.tag_name().name() == "serviceList")
+                    .flat_map(|list| list.children())
+                    .flat_map(|service| service.children())
+                    .filter(|node| node.tag_name().name() == "serviceType"),
+            )
+            .filter_map(|node| node.text())
+            .map(str::trim);
+
+        advertisements.push(Advertisement {
+            nt: udn.to_string(),
+            usn: udn.to_string(),
+        });
+        for nt in types {
+            let advertisement = Advertisement {
+                nt: nt.to_string(),
+                usn: format!("{udn}::{nt}"),
+            };
+            if !advertisements.contains(&advertisement) {
+                advertisements.push(advertisement);
+            }
+        }
+    }
+
+    Ok(advertisements)
+}
+
+fn udn<'a>(device: Node<'a, '_>) -> Result<&'a str> {
+    device
+        .children()
+        .find(|node| node.tag_name().name() == "UDN")
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .ok_or_else(|| Error::XmlMissingElement("device".to_string(), "UDN".to_string()))
+}
+
+/// Returns the search target and USN of every search response to send for the `ST` header
+/// of an `M-SEARCH` request.
+fn search_responses<'a>(
+    advertisements: &'a [Advertisement],
+    search_target: &'a str,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    advertisements.iter().filter_map(move |advertisement| {
+        if search_target == "ssdp:all" {
+            return Some((advertisement.nt.as_str(), advertisement.usn.as_str()));
+        }
+        if advertisement.nt == search_target || supports_version(&advertisement.nt, search_target) {
+            return Some((search_target, advertisement.usn.as_str()));
+        }
+        None
+    })
+}
+
+/// Newer versions of device and service types are backwards compatible, so a search for
+/// `...:RenderingControl:1` is answered by a `...:RenderingControl:2`.
+fn supports_version(urn: &str, search_target: &str) -> bool {
+    fn split(urn: &str) -> Option<(&str, u32)> {
+        let (prefix, version) = urn.rsplit_once(':')?;
+        Some((prefix, version.parse().ok()?))
+    }
+    match (split(urn), split(search_target)) {
+        (Some((prefix, version)), Some((searched, searched_version))) => {
+            urn.starts_with("urn:") && prefix == searched && searched_version <= version
+        }
+        _ => false,
+    }
+}
+
+/// Sends the `NOTIFY` announcements and search responses of a hosted device.
+pub(super) struct Advertiser {
+    advertisements: Vec<Advertisement>,
+    location: Uri,
+    local_addr: SocketAddr,
+    max_age: Duration,
+    boot_id: u32,
+}
+
+impl Advertiser {
+    pub(super) fn new(
+        advertisements: Vec<Advertisement>,
+        location: Uri,
+        local_addr: SocketAddr,
+        max_age: Duration,
+    ) -> Self {
+        // a new one is required after each restart, the time is as good as any
+        let boot_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs() as u32 & 0x7fff_ffff)
+            .unwrap_or_default();
+
+        Advertiser {
+            advertisements,
+            location,
+            local_addr,
+            max_age,
+            boot_id,
+        }
+    }
+
+    /// A socket sending multicast messages from the interface the device is hosted on, or the
+    /// default one if it listens on all interfaces.
+    fn multicast_socket(&self) -> io::Result<UdpSocket> {
+        let interface = match self.local_addr {
+            SocketAddr::V4(addr) => *addr.ip(),
+            SocketAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+        };
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_multicast_ttl_v4(MULTICAST_TTL)?;
+        if !interface.is_unspecified() {
+            socket.set_multicast_if_v4(&interface)?;
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((interface, 0)).into())?;
+        UdpSocket::from_std(socket.into())
+    }
+
+    async fn notify(&self, nts: &str) -> io::Result<()> {
+        let socket = self.multicast_socket()?;
+
+        for Advertisement { nt, usn } in &self.advertisements {
+            let alive = match nts {
+                "ssdp:alive" => format!(
+                    "CACHE-CONTROL: max-age={}\r\n\
+                     LOCATION: {}\r\n\
+                     SERVER: {}\r\n",
+                    self.max_age.as_secs(),
+                    self.location,
+                    server_header()
+                ),
+                _ => String::new(),
+            };
+            let message = format!(
+                "NOTIFY * HTTP/1.1\r\n\
+                 HOST: {SSDP_MULTICAST_V4}:{SSDP_PORT}\r\n\
+                 {alive}\
+                 NT: {nt}\r\n\
+                 NTS: {nts}\r\n\
+                 USN: {usn}\r\n\
+                 BOOTID.UPNP.ORG: {}\r\n\
+                 CONFIGID.UPNP.ORG: 1\r\n\r\n",
+                self.boot_id
+            );
+            socket
+                .send_to(message.as_bytes(), (SSDP_MULTICAST_V4, SSDP_PORT))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Announces that the device left the network.
+    pub(super) async fn bye_bye(&self) -> io::Result<()> {
+        self.notify("ssdp:byebye").await
+    }
+
+    /// The response to a search for `search_target`, with the location of the device as seen
+    /// from the control point at `from`.
+    fn search_response(&self, search_target: &str, usn: &str, from: SocketAddr) -> String {
+        let location = match self.local_addr.ip().is_unspecified() {
+            true => match utils::local_ip_for(from) {
+                Ok(ip) => {
+                    let addr = SocketAddr::new(ip, self.local_addr.port());
+                    format!("http://{addr}{}", self.location.path())
+                }
+                Err(_) => self.location.to_string(),
+            },
+            false => self.location.to_string(),
+        };
+
+        format!(
+            "HTTP/1.1 200 OK\r\n\
+             CACHE-CONTROL: max-age={}\r\n\
+             EXT:\r\n\
+             LOCATION: {location}\r\n\
+             SERVER: {}\r\n\
+             ST: {search_target}\r\n\
+             USN: {usn}\r\n\
+             BOOTID.UPNP.ORG: {}\r\n\
+             CONFIGID.UPNP.ORG: 1\r\n\r\n",
+            self.max_age.as_secs(),
+            server_header(),
+            self.boot_id
+        )
+    }
+}
+
+/// Announces the device as alive, and again whenever half of its `max-age` elapsed.
+///
+/// A round of `ssdp:byebye` comes first, so control points drop what they remember of the
+/// device from before a restart (UDA 1.1, section 1.2).
+pub(super) async fn announce(advertiser: Arc<Advertiser>) {
+    let _ = advertiser.bye_bye().await;
+
+    let mut interval = tokio::time::interval(advertiser.max_age / 2);
+    loop {
+        interval.tick().await;
+        // a lost announcement is made up for by the next one
+        let _ = advertiser.notify("ssdp:alive").await;
+    }
+}
+
+/// Joins the SSDP multicast group and answers the `M-SEARCH` requests matching the device.
+pub(super) fn respond(advertiser: Arc<Advertiser>) -> Result<impl Future<Output = ()>> {
+    let socket = Arc::new(httpu::bind_multicast_v4(SSDP_MULTICAST_V4, SSDP_PORT)?);
+
+    Ok(async move {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            let Ok((read, from)) = socket.recv_from(&mut buf).await else {
+                continue;
+            };
+            let Some(message) = std::str::from_utf8(&buf[..read])
+                .ok()
+                .and_then(|text| HttpuMessage::parse(text).ok())
+            else {
+                continue;
+            };
+            if message.method() != Some("M-SEARCH")
+                || message.header("MAN") != Some("\"ssdp:discover\"")
+            {
+                continue;
+            }
+            let Some(search_target) = message.header("ST") else {
+                continue;
+            };
+
+            let responses: Vec<_> = search_responses(&advertiser.advertisements, search_target)
+                .map(|(search_target, usn)| advertiser.search_response(search_target, usn, from))
+                .collect();
+            if responses.is_empty() {
+                continue;
+            }
+
+            // multicast searches are answered after a random delay of up to `MX` seconds,
+            // so not all devices respond at once
+            let delay = message
+                .header("MX")
+                .and_then(|mx| mx.parse().ok())
+                .map(|mx| random_delay(Duration::from_secs(mx).min(MAX_RESPONSE_DELAY)))
+                .unwrap_or_default();
+            let socket = Arc::clone(&socket);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                for response in responses {
+                    let _ = socket.send_to(response.as_bytes(), from).await;
+                }
+            });
+        }
+    })
+}
+
+/// A delay between zero and `max`, random enough to spread out responses.
+fn random_delay(max: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.subsec_nanos())
+        .unwrap_or_default();
+    let millis = max.as_millis() as u64;
+    match millis {
+        0 => Duration::ZERO,
+        _ => Duration::from_millis(u64::from(nanos) % millis),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_targets() {
+        let description = r#"<root xmlns="urn:schemas-upnp-org:device-1-0">
+            <device>
+              <deviceType>urn:schemas-upnp-org:device:MediaRenderer:2</deviceType>
+              <UDN>uuid:root</UDN>
+              <serviceList>
+                <service><serviceType>urn:schemas-upnp-org:service:RenderingControl:2</serviceType></service>
+              </serviceList>
+              <deviceList>
+                <device>
+                  <deviceType>urn:schemas-upnp-org:device:Embedded:1</deviceType>
+                  <UDN>uuid:embedded</UDN>
+                </device>
+              </deviceList>
+            </device>
+          </root>"#;
+        let advertisements = advertisements(description).unwrap();
+        let usns: Vec<_> = advertisements.iter().map(|a| a.usn.as_str()).collect();
+        assert_eq!(
+            usns,
+            [
+                "uuid:root::upnp:rootdevice",
+                "uuid:root",
+                "uuid:root::urn:schemas-upnp-org:device:MediaRenderer:2",
+                "uuid:root::urn:schemas-upnp-org:service:RenderingControl:2",
+                "uuid:embedded",
+                "uuid:embedded::urn:schemas-upnp-org:device:Embedded:1",
+            ]
+        );
+
+        let search = |search_target| -> Vec<_> {
+            search_responses(&advertisements, search_target)
+                .map(|(st, usn)| (st.to_string(), usn.to_string()))
+                .collect()
+        };
+        assert_eq!(search("ssdp:all").len(), 6);
+        assert_eq!(
+            search("upnp:rootdevice"),
+            [(
+                "upnp:rootdevice".to_string(),
+                "uuid:root::upnp:rootdevice".to_string()
+            )]
+        );
+        assert_eq!(search("uuid:embedded").len(), 1);
+        assert_eq!(
+            search("urn:schemas-upnp-org:service:RenderingControl:1"),
+            [(
+                "urn:schemas-upnp-org:service:RenderingControl:1".to_string(),
+                "uuid:root::urn:schemas-upnp-org:service:RenderingControl:2".to_string()
+            )]
+        );
+        assert!(search("urn:schemas-upnp-org:device:Embedded:2").is_empty());
+    }
+
+    #[tokio::test]
+    async fn notify_from_the_hosted_interface() {
+        let advertiser = |local_addr: &str| {
+            let location = Uri::from_static("http://127.0.0.1:1400/description.xml");
+            let local_addr = local_addr.parse().unwrap();
+            Advertiser::new(Vec::new(), location, local_addr, Duration::from_secs(1800))
+        };
+
+        let socket = advertiser("127.0.0.1:1400").multicast_socket().unwrap();
+        assert_eq!(socket.local_addr().unwrap().ip(), Ipv4Addr::LOCALHOST);
+        let socket = advertiser("0.0.0.0:1400").multicast_socket().unwrap();
+        assert!(socket.local_addr().unwrap().ip().is_unspecified());
+    }
+}
@@ -0,0 +1,366 @@
+//! HTTP over UDP, as used by SSDP for `NOTIFY` and `M-SEARCH` messages.
+
+use crate::{utils::yield_try, Error, Result};
+use futures_core::stream::Stream;
+use genawaiter::sync::{Co, Gen};
+use http::Uri;
+use socket2::{Domain, Protocol, Socket, Type};
+use ssdp_client::SearchTarget;
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use tokio::{net::UdpSocket, time::Instant};
+
+/// The IPv4 multicast group SSDP messages are sent to.
+pub(crate) const SSDP_MULTICAST_V4: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+/// The link-local IPv6 multicast group SSDP messages are sent to.
+pub(crate) const SSDP_MULTICAST_V6_LINK_LOCAL: Ipv6Addr =
+    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xc);
+/// The site-local IPv6 multicast group SSDP messages are sent to.
+pub(crate) const SSDP_MULTICAST_V6_SITE_LOCAL: Ipv6Addr =
+    Ipv6Addr::new(0xff05, 0, 0, 0, 0, 0, 0, 0xc);
+pub(crate) const SSDP_PORT: u16 = 1900;
+
+const DEFAULT_SEARCH_TTL: u32 = 2;
+
+/// A single HTTPU message, either a request (`NOTIFY * HTTP/1.1`) or a response (`HTTP/1.1 200 OK`).
+#[derive(Debug)]
+pub(crate) struct HttpuMessage<'a> {
+    start_line: &'a str,
+    headers: Vec<(&'a str, &'a str)>,
+    #[cfg(feature = "subscribe")]
+    body: &'a str,
+}
+
+impl<'a> HttpuMessage<'a> {
+    pub(crate) fn parse(text: &'a str) -> Result<Self> {
+        let head = text.split_once("\r\n\r\n").map_or(text, |(head, _)| head);
+        let mut lines = head.split("\r\n");
+        let start_line = lines
+            .next()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .ok_or(Error::ParseError("httpu message is empty"))?;
+
+        let headers = lines
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim(), value.trim()))
+            })
+            .collect();
+
+        Ok(HttpuMessage {
+            start_line,
+            headers,
+            #[cfg(feature = "subscribe")]
+            body: text.split_once("\r\n\r\n").map_or("", |(_, body)| body),
+        })
+    }
+
+    /// The method of a request, `None` if this message is a response.
+    pub(crate) fn method(&self) -> Option<&'a str> {
+        if self.start_line.starts_with("HTTP/") {
+            return None;
+        }
+        self.start_line.split_whitespace().next()
+    }
+
+    /// Case-insensitive header lookup.
+    pub(crate) fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    pub(crate) fn required_header(&self, name: &'static str) -> Result<&'a str> {
+        self.header(name).ok_or(Error::MissingHeader(name))
+    }
+
+    /// Everything after the empty line ending the headers.
+    #[cfg(feature = "subscribe")]
+    pub(crate) fn body(&self) -> &'a str {
+        self.body
+    }
+}
+
+/// Extracts the `max-age` directive of a `CACHE-CONTROL` header.
+pub(crate) fn parse_max_age(cache_control: &str) -> Option<Duration> {
+    cache_control
+        .split(',')
+        .filter_map(|directive| directive.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("max-age"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Binds a UDP socket to `port` on all interfaces and joins the given multicast group.
+///
+/// The address is shared with other processes, since there usually are multiple programs
+/// interested in SSDP traffic on the same host.
+pub(crate) fn bind_multicast_v4(group: Ipv4Addr, port: u16) -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())?;
+    socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+/// Binds a UDP socket to `port` on all interfaces and joins the given IPv6 multicast group on
+/// the default interface. See [`bind_multicast_v4`].
+pub(crate) fn bind_multicast_v6(group: Ipv6Addr, port: u16) -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_only_v6(true)?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+    socket.join_multicast_v6(&group, 0)?;
+
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+/// A response to an `M-SEARCH` request.
+#[derive(Debug)]
+pub(crate) struct SearchResponse {
+    pub(crate) location: Uri,
+    pub(crate) usn: Option<String>,
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) boot_id: Option<u32>,
+}
+
+/// Sends an `M-SEARCH` request to the multicast address `group` and yields every response
+/// until `timeout` elapsed.
+pub(crate) async fn search(
+    group: SocketAddr,
+    search_target: &SearchTarget,
+    timeout: Duration,
+    mx: usize,
+    ttl: Option<u32>,
+) -> Result<impl Stream<Item = Result<SearchResponse>>> {
+    let ttl = ttl.unwrap_or(DEFAULT_SEARCH_TTL);
+    let socket = match group {
+        SocketAddr::V4(_) => {
+            let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+            socket.set_multicast_ttl_v4(ttl)?;
+            socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)).into())?;
+            socket
+        }
+        SocketAddr::V6(_) => {
+            let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))?;
+            socket.set_only_v6(true)?;
+            socket.set_multicast_hops_v6(ttl)?;
+            socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)).into())?;
+            socket
+        }
+    };
+    socket.set_nonblocking(true)?;
+    let socket = UdpSocket::from_std(socket.into())?;
+
+    let message = format!(
+        "M-SEARCH * HTTP/1.1\r\n\
+         HOST: {group}\r\n\
+         MAN: \"ssdp:discover\"\r\n\
+         MX: {mx}\r\n\
+         ST: {search_target}\r\n\r\n"
+    );
+    socket.send_to(message.as_bytes(), group).await?;
+
+    let deadline = Instant::now() + timeout;
+    Ok(Gen::new(move |co| search_stream(socket, deadline, co)))
+}
+
+async fn search_stream(socket: UdpSocket, deadline: Instant, co: Co<Result<SearchResponse>>) {
+    let mut buf = vec![0u8; 8192];
+    loop {
+        let (read, from) = match tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await
+        {
+            Err(_) => break,
+            Ok(received) => yield_try!(co => received),
+        };
+        // malformed responses, e.g. of other devices answering the search, are skipped
+        if let Some(mut response) = parse_search_response(&buf[..read]) {
+            response.location = scope_url(&response.location, from);
+            co.yield_(Ok(response)).await;
+        }
+    }
+}
+
+/// `None` if the datagram isn't a valid search response.
+fn parse_search_response(datagram: &[u8]) -> Option<SearchResponse> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    let message = HttpuMessage::parse(text).ok()?;
+    if message.method().is_some() {
+        return None;
+    }
+
+    Some(SearchResponse {
+        location: message.header("LOCATION")?.parse().ok()?,
+        usn: message.header("USN").map(str::to_string),
+        max_age: message.header("CACHE-CONTROL").and_then(parse_max_age),
+        boot_id: message
+            .header("BOOTID.UPNP.ORG")
+            .and_then(|boot_id| boot_id.parse().ok()),
+    })
+}
+
+/// Adds the interface the message was received on to link-local IPv6 URLs like
+/// `http://[fe80::1]:1400/`, which can't be connected to without one.
+/// The zone is `%25` encoded as in RFC 6874, e.g. `http://[fe80::1%253]:1400/`.
+/// Other URLs are returned as they are.
+pub(crate) fn scope_url(url: &Uri, from: SocketAddr) -> Uri {
+    let scope_id = match from {
+        SocketAddr::V6(from) if from.scope_id() != 0 => from.scope_id(),
+        _ => return url.clone(),
+    };
+    let ip = url
+        .host()
+        .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+        .and_then(|host| host.parse::<Ipv6Addr>().ok());
+    let ip = match ip {
+        // the unicast link-local prefix fe80::/10
+        Some(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => ip,
+        _ => return url.clone(),
+    };
+
+    let authority = match url.port_u16() {
+        Some(port) => format!("[{ip}%25{scope_id}]:{port}"),
+        None => format!("[{ip}%25{scope_id}]"),
+    };
+    let path = url
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+    let scheme = url.scheme_str().unwrap_or("http");
+
+    format!("{scheme}://{authority}{path}")
+        .parse()
+        .unwrap_or_else(|_| url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_notify() {
+        let text = "NOTIFY * HTTP/1.1\r\n\
+            HOST: 239.255.255.250:1900\r\n\
+            cache-control: max-age = 1800\r\n\
+            NT: upnp:rootdevice\r\n\
+            NTS: ssdp:alive\r\n\r\n";
+
+        let message = HttpuMessage::parse(text).unwrap();
+        assert_eq!(message.method(), Some("NOTIFY"));
+        assert_eq!(message.header("nts"), Some("ssdp:alive"));
+        assert_eq!(message.header("USN"), None);
+        assert_eq!(
+            message.header("CACHE-CONTROL").and_then(parse_max_age),
+            Some(Duration::from_secs(1800))
+        );
+    }
+
+    #[test]
+    fn scoped_link_local_urls() {
+        let from_v6 = |scope_id| {
+            SocketAddr::from(std::net::SocketAddrV6::new(
+                Ipv6Addr::LOCALHOST,
+                1900,
+                0,
+                scope_id,
+            ))
+        };
+        let scope = |url: &'static str, from| scope_url(&Uri::from_static(url), from).to_string();
+
+        assert_eq!(
+            scope("http://[fe80::1:2]:1400/xml/device.xml", from_v6(3)),
+            "http://[fe80::1:2%253]:1400/xml/device.xml"
+        );
+        assert_eq!(scope("http://[fe80::1]/", from_v6(0)), "http://[fe80::1]/");
+        assert_eq!(
+            scope("http://[2001:db8::1]/", from_v6(3)),
+            "http://[2001:db8::1]/"
+        );
+        assert_eq!(
+            scope("http://[fe80::1%252]/", from_v6(3)),
+            "http://[fe80::1%252]/"
+        );
+        assert_eq!(
+            scope(
+                "http://192.168.1.2/",
+                SocketAddr::from(([192, 168, 1, 2], 1900))
+            ),
+            "http://192.168.1.2/"
+        );
+    }
+
+    #[test]
+    fn search_response_headers_are_case_insensitive() {
+        let datagram = b"HTTP/1.1 200 OK\r\n\
+            Location: http://192.168.1.2:1400/xml/device.xml\r\n\
+            usn: uuid:RINCON_000E58::upnp:rootdevice\r\n\
+            Cache-Control: max-age=1800\r\n\
+            BootId.UPnP.org: 7\r\n\r\n";
+
+        let response = parse_search_response(datagram).unwrap();
+        assert_eq!(response.location, "http://192.168.1.2:1400/xml/device.xml");
+        assert_eq!(
+            response.usn.as_deref(),
+            Some("uuid:RINCON_000E58::upnp:rootdevice")
+        );
+        assert_eq!(response.max_age, Some(Duration::from_secs(1800)));
+        assert_eq!(response.boot_id, Some(7));
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn scoped_url_round_trip() {
+        let from = SocketAddr::from(std::net::SocketAddrV6::new(Ipv6Addr::LOCALHOST, 1900, 0, 3));
+        let url = scope_url(&Uri::from_static("http://[fe80::1:2]:1400/"), from);
+
+        let remote = crate::utils::remote_addr(&url).await.unwrap();
+        assert_eq!(remote.to_string(), "[fe80::1:2%3]:1400");
+    }
+
+    #[test]
+    fn parse_response() {
+        let message = HttpuMessage::parse("HTTP/1.1 200 OK\r\nST: ssdp:all\r\n\r\n").unwrap();
+        assert_eq!(message.method(), None);
+        assert_eq!(message.header("st"), Some("ssdp:all"));
+    }
+
+    #[tokio::test]
+    async fn skip_malformed_responses() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let datagrams: [&[u8]; 4] = [
+            b"\xff\xfe not utf-8",
+            b"HTTP/1.1 200 OK\r\nST: ssdp:all\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nLOCATION: not a url\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nLOCATION: http://192.168.1.2:1400/xml/device.xml\r\n\r\n",
+        ];
+        for datagram in datagrams {
+            sender.send_to(datagram, addr).await.unwrap();
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let responses = Gen::new(move |co| search_stream(socket, deadline, co));
+        let responses: Vec<_> = futures_util::TryStreamExt::try_collect(responses)
+            .await
+            .unwrap();
+        let locations: Vec<_> = responses
+            .iter()
+            .map(|response| &response.location)
+            .collect();
+        assert_eq!(locations, ["http://192.168.1.2:1400/xml/device.xml"]);
+    }
+}
@@ -36,7 +36,7 @@
 //!             .expect("searched for RenderingControl, got something else");
 //!
 //!         let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
-//!         let response = service.action(device.url(), "GetVolume", args).await?;
+//!         let response = service.action("GetVolume", args).await?;
 //!
 //!         let volume = response.get("CurrentVolume").unwrap();
 //!
@@ -48,17 +48,41 @@
 //! ```
 // TODO: doc include when it gets stable
 
+mod args;
+mod client;
 mod device;
 mod discovery;
 mod error;
+#[cfg(feature = "subscribe")]
+mod eventing;
+#[cfg(feature = "host")]
+mod host;
+mod httpu;
+mod listen;
+mod recording;
+mod registry;
 /// Service Control Protocol Description.
 pub mod scpd;
 mod service;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod utils;
 
+pub use args::{ActionArgs, ActionResponse, ArgValue, FromArgValue};
+pub use client::{Client, ClientBuilder};
 pub use device::{Device, DeviceSpec};
-pub use discovery::{discover, discover_with_properties};
-pub use error::Error;
+pub use discovery::{discover, discover_on, discover_with_properties, MulticastGroup};
+pub use error::{Error, TimeoutPhase, UPnPError};
+#[cfg(feature = "subscribe")]
+pub use eventing::{
+    listen_multicast_events, Event, EventServer, EventServerBuilder, LastChange, LastChangeState,
+    MulticastEvent, SequenceGap, ServiceState, StateChange, Subscription,
+};
+#[cfg(feature = "host")]
+pub use host::{ActionRequest, DeviceHost, DeviceHostBuilder, EventPublisher};
+pub use listen::{listen, listen_on, Alive, Announcement, ByeBye, Update};
+pub use recording::{Fixture, Recorder};
+pub use registry::{DeviceRegistry, RegistryEvent};
 pub use service::Service;
 
 pub use http;
@@ -0,0 +1,330 @@
+use crate::{
+    httpu::{self, HttpuMessage},
+    utils::yield_try,
+    Error, MulticastGroup, Result,
+};
+use futures_core::stream::Stream;
+use genawaiter::sync::{Co, Gen};
+use http::Uri;
+use ssdp_client::SearchTarget;
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::net::UdpSocket;
+
+/// A `NOTIFY` message multicast by a device to announce its presence, departure or reboot.
+/// See [`listen`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Announcement {
+    /// `ssdp:alive`, the device or service is (still) available.
+    Alive(Alive),
+    /// `ssdp:byebye`, the device or service is about to leave the network.
+    ByeBye(ByeBye),
+    /// `ssdp:update`, the device is about to change its `BOOTID.UPNP.ORG`, e.g. because it
+    /// joined another network interface.
+    Update(Update),
+}
+
+impl Announcement {
+    /// Unique Service Name, e.g. `uuid:<udn>::urn:schemas-upnp-org:service:RenderingControl:1`.
+    pub fn usn(&self) -> &str {
+        match self {
+            Announcement::Alive(alive) => alive.usn(),
+            Announcement::ByeBye(byebye) => byebye.usn(),
+            Announcement::Update(update) => update.usn(),
+        }
+    }
+
+    /// The `NT` (notification type) header.
+    pub fn notification_type(&self) -> &SearchTarget {
+        match self {
+            Announcement::Alive(alive) => alive.notification_type(),
+            Announcement::ByeBye(byebye) => byebye.notification_type(),
+            Announcement::Update(update) => update.notification_type(),
+        }
+    }
+
+    /// The `BOOTID.UPNP.ORG` header. Only UPnP 1.1+ devices send it.
+    pub fn boot_id(&self) -> Option<u32> {
+        match self {
+            Announcement::Alive(alive) => alive.boot_id(),
+            Announcement::ByeBye(byebye) => byebye.boot_id(),
+            Announcement::Update(update) => update.boot_id(),
+        }
+    }
+
+    fn from_message(message: &HttpuMessage<'_>) -> Result<Self> {
+        let usn = message.required_header("USN")?.to_string();
+        let notification_type = message
+            .required_header("NT")?
+            .parse()
+            .map_err(Error::invalid_response)?;
+        let boot_id = parse_optional_header(message, "BOOTID.UPNP.ORG")?;
+
+        let announcement = match message.required_header("NTS")? {
+            "ssdp:alive" => Announcement::Alive(Alive {
+                usn,
+                notification_type,
+                location: message.required_header("LOCATION")?.parse()?,
+                max_age: httpu::parse_max_age(message.required_header("CACHE-CONTROL")?).ok_or(
+                    Error::ParseError("`CACHE-CONTROL` header does not contain `max-age`"),
+                )?,
+                server: message.header("SERVER").map(str::to_string),
+                boot_id,
+                config_id: parse_optional_header(message, "CONFIGID.UPNP.ORG")?,
+            }),
+            "ssdp:byebye" => Announcement::ByeBye(ByeBye {
+                usn,
+                notification_type,
+                boot_id,
+            }),
+            "ssdp:update" => Announcement::Update(Update {
+                usn,
+                notification_type,
+                location: message.required_header("LOCATION")?.parse()?,
+                boot_id,
+                next_boot_id: parse_optional_header(message, "NEXTBOOTID.UPNP.ORG")?,
+            }),
+            _ => return Err(Error::ParseError("unknown `NTS` header")),
+        };
+
+        Ok(announcement)
+    }
+
+    /// Adds the interface of `from` to link-local IPv6 locations.
+    fn scope_location(&mut self, from: SocketAddr) {
+        let location = match self {
+            Announcement::Alive(alive) => &mut alive.location,
+            Announcement::Update(update) => &mut update.location,
+            Announcement::ByeBye(_) => return,
+        };
+        *location = httpu::scope_url(location, from);
+    }
+}
+
+/// Content of an `ssdp:alive` [`Announcement`].
+#[derive(Debug, Clone)]
+pub struct Alive {
+    usn: String,
+    notification_type: SearchTarget,
+    location: Uri,
+    max_age: Duration,
+    server: Option<String>,
+    boot_id: Option<u32>,
+    config_id: Option<u32>,
+}
+impl Alive {
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+    pub fn notification_type(&self) -> &SearchTarget {
+        &self.notification_type
+    }
+    /// URL of the device description, usable with [`Device::from_url`](crate::Device::from_url).
+    pub fn location(&self) -> &Uri {
+        &self.location
+    }
+    /// How long the announcement is valid, taken from the `CACHE-CONTROL` header.
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+    pub fn server(&self) -> Option<&str> {
+        self.server.as_deref()
+    }
+    pub fn boot_id(&self) -> Option<u32> {
+        self.boot_id
+    }
+    pub fn config_id(&self) -> Option<u32> {
+        self.config_id
+    }
+}
+
+/// Content of an `ssdp:byebye` [`Announcement`].
+#[derive(Debug, Clone)]
+pub struct ByeBye {
+    usn: String,
+    notification_type: SearchTarget,
+    boot_id: Option<u32>,
+}
+impl ByeBye {
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+    pub fn notification_type(&self) -> &SearchTarget {
+        &self.notification_type
+    }
+    pub fn boot_id(&self) -> Option<u32> {
+        self.boot_id
+    }
+}
+
+/// Content of an `ssdp:update` [`Announcement`].
+#[derive(Debug, Clone)]
+pub struct Update {
+    usn: String,
+    notification_type: SearchTarget,
+    location: Uri,
+    boot_id: Option<u32>,
+    next_boot_id: Option<u32>,
+}
+impl Update {
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+    pub fn notification_type(&self) -> &SearchTarget {
+        &self.notification_type
+    }
+    pub fn location(&self) -> &Uri {
+        &self.location
+    }
+    pub fn boot_id(&self) -> Option<u32> {
+        self.boot_id
+    }
+    pub fn next_boot_id(&self) -> Option<u32> {
+        self.next_boot_id
+    }
+}
+
+fn parse_optional_header(message: &HttpuMessage<'_>, name: &str) -> Result<Option<u32>> {
+    message
+        .header(name)
+        .map(|value| value.parse().map_err(Error::invalid_response))
+        .transpose()
+}
+
+/// Listens for devices announcing themselves on the network.
+///
+/// Unlike [`discover`](crate::discover), this doesn't send anything but joins the SSDP multicast
+/// group and yields every `NOTIFY` message it receives, until the stream is dropped.
+/// Search requests of other control points and malformed messages are ignored, only errors of
+/// the socket are yielded.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// use futures::prelude::*;
+/// use rupnp::Announcement;
+///
+/// # async fn listen() -> Result<(), rupnp::Error> {
+/// let announcements = rupnp::listen().await?;
+/// let mut announcements = std::pin::pin!(announcements);
+///
+/// while let Some(announcement) = announcements.try_next().await? {
+///     match announcement {
+///         Announcement::Alive(alive) => println!("{} @ {}", alive.usn(), alive.location()),
+///         Announcement::ByeBye(byebye) => println!("{} left", byebye.usn()),
+///         _ => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn listen() -> Result<impl Stream<Item = Result<Announcement>>> {
+    listen_on(MulticastGroup::Ipv4).await
+}
+
+/// Listens for devices announcing themselves on the given multicast group, e.g. for IPv6
+/// devices. See [`listen`].
+///
+/// Link-local IPv6 locations are scoped to the interface the announcement arrived on.
+pub async fn listen_on(group: MulticastGroup) -> Result<impl Stream<Item = Result<Announcement>>> {
+    let socket = match group.addr().ip() {
+        IpAddr::V4(ip) => httpu::bind_multicast_v4(ip, httpu::SSDP_PORT)?,
+        IpAddr::V6(ip) => httpu::bind_multicast_v6(ip, httpu::SSDP_PORT)?,
+    };
+
+    Ok(Gen::new(move |co| listen_stream(socket, co)))
+}
+
+async fn listen_stream(socket: UdpSocket, co: Co<Result<Announcement>>) {
+    let mut buf = vec![0u8; 8192];
+    loop {
+        let (read, from) = yield_try!(co => socket.recv_from(&mut buf).await);
+        // anyone can send to the multicast group, so malformed messages are skipped instead
+        // of ending the stream
+        if let Some(mut announcement) = parse_announcement(&buf[..read]) {
+            announcement.scope_location(from);
+            co.yield_(Ok(announcement)).await;
+        }
+    }
+}
+
+/// Parses a datagram received on the multicast group, `None` if it isn't a valid `NOTIFY`.
+pub(crate) fn parse_announcement(datagram: &[u8]) -> Option<Announcement> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    let message = HttpuMessage::parse(text).ok()?;
+    if message.method() != Some("NOTIFY") {
+        return None;
+    }
+    Announcement::from_message(&message).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    #[test]
+    fn parse_alive() {
+        let text = "NOTIFY * HTTP/1.1\r\n\
+            HOST: 239.255.255.250:1900\r\n\
+            CACHE-CONTROL: max-age=1800\r\n\
+            LOCATION: http://192.168.1.2:1400/xml/device_description.xml\r\n\
+            NT: upnp:rootdevice\r\n\
+            NTS: ssdp:alive\r\n\
+            SERVER: Linux UPnP/1.0 Sonos/70.3\r\n\
+            USN: uuid:RINCON_000E58::upnp:rootdevice\r\n\
+            BOOTID.UPNP.ORG: 7\r\n\r\n";
+        let message = HttpuMessage::parse(text).unwrap();
+
+        let Announcement::Alive(alive) = Announcement::from_message(&message).unwrap() else {
+            panic!("expected ssdp:alive");
+        };
+        assert_eq!(alive.usn(), "uuid:RINCON_000E58::upnp:rootdevice");
+        assert_eq!(alive.max_age(), Duration::from_secs(1800));
+        assert_eq!(alive.location().port_u16(), Some(1400));
+        assert_eq!(alive.boot_id(), Some(7));
+        assert_eq!(alive.config_id(), None);
+    }
+
+    #[test]
+    fn parse_byebye() {
+        let text = "NOTIFY * HTTP/1.1\r\n\
+            NT: urn:schemas-upnp-org:service:RenderingControl:1\r\n\
+            NTS: ssdp:byebye\r\n\
+            USN: uuid:RINCON_000E58::urn:schemas-upnp-org:service:RenderingControl:1\r\n\r\n";
+        let message = HttpuMessage::parse(text).unwrap();
+
+        let announcement = Announcement::from_message(&message).unwrap();
+        assert!(matches!(announcement, Announcement::ByeBye(_)));
+        assert_eq!(announcement.boot_id(), None);
+    }
+
+    #[tokio::test]
+    async fn skip_malformed_messages() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let byebye = "NOTIFY * HTTP/1.1\r\n\
+            NT: upnp:rootdevice\r\n\
+            NTS: ssdp:byebye\r\n\
+            USN: uuid:RINCON_000E58::upnp:rootdevice\r\n\r\n";
+        let datagrams: [&[u8]; 5] = [
+            b"\xff\xfe not utf-8",
+            b"\r\n\r\n",
+            b"NOTIFY * HTTP/1.1\r\nNTS: ssdp:unknown\r\n\r\n",
+            b"NOTIFY * HTTP/1.1\r\nNT: upnp:rootdevice\r\nNTS: ssdp:alive\r\n\r\n",
+            byebye.as_bytes(),
+        ];
+        for datagram in datagrams {
+            sender.send_to(datagram, addr).await.unwrap();
+        }
+
+        let announcements = Gen::new(move |co| listen_stream(socket, co));
+        let mut announcements = std::pin::pin!(announcements);
+        let announcement = announcements.try_next().await.unwrap().unwrap();
+        assert!(matches!(announcement, Announcement::ByeBye(_)));
+    }
+}
@@ -0,0 +1,674 @@
+//! Recording the HTTP traffic of a [`Client`](crate::Client) to a [`Fixture`], and replaying it
+//! without the device.
+
+#[cfg(feature = "subscribe")]
+use crate::{
+    eventing::{parse_notify, SequenceTracker},
+    Event,
+};
+use crate::{utils, Error, Result};
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use roxmltree::{Document, Node};
+use std::{
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+#[cfg(feature = "subscribe")]
+use tokio::sync::mpsc;
+
+/// The requests a [`Client`](crate::Client) made and the responses it received, and the events
+/// of its subscriptions, as captured by a [`Recorder`].
+///
+/// Fixtures are stored as XML documents, so they can be checked in next to the tests
+/// replaying them with [`ClientBuilder::replay`](crate::ClientBuilder::replay).
+/// Bodies and header values are kept byte for byte: those XML can't hold unchanged, like ones
+/// with carriage returns or invalid UTF-8, are stored base64 encoded.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn record_and_replay(url: rupnp::http::Uri) -> Result<(), rupnp::Error> {
+/// use rupnp::{Client, Fixture, Recorder};
+///
+/// // once, against the real device
+/// let recorder = Recorder::new();
+/// let client = Client::builder().record(recorder.clone()).build();
+/// let device = client.device(url.clone()).await?;
+/// let service = &device.services()[0];
+/// client.action(service, "GetVolume", "<InstanceID>0</InstanceID>").await?;
+/// recorder.fixture().save("tests/fixtures/renderer.xml")?;
+///
+/// // in a test, without the device
+/// let fixture = Fixture::load("tests/fixtures/renderer.xml")?;
+/// let client = Client::builder().replay(fixture).build();
+/// let device = client.device(url).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fixture {
+    exchanges: Vec<Exchange>,
+    notifications: Vec<Notification>,
+}
+
+/// A request and the response to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Exchange {
+    method: String,
+    url: String,
+    soap_action: Option<String>,
+    request: Vec<u8>,
+    status: u16,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+/// A notification sent to a subscription to the service with the event URL `url`, as it was
+/// received, including ones which were rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Notification {
+    url: String,
+    method: String,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Fixture::default()
+    }
+
+    /// Parses a fixture written by [`to_xml`](Fixture::to_xml).
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let document = Document::parse(xml)?;
+        let root = utils::find_root(&document, "fixture", "Fixture")?;
+
+        let mut fixture = Fixture::new();
+        for node in root.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "exchange" => fixture.exchanges.push(parse_exchange(node)?),
+                "notification" => fixture.notifications.push(parse_notification(node)?),
+                _ => {}
+            }
+        }
+        Ok(fixture)
+    }
+
+    /// Reads a fixture from a file, see [`from_xml`](Fixture::from_xml).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Fixture::from_xml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn to_xml(&self) -> String {
+        let escape = utils::escape_xml;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<fixture>\n");
+
+        for exchange in &self.exchanges {
+            let soap_action = match &exchange.soap_action {
+                Some(soap_action) => format!(" soapAction=\"{}\"", escape(soap_action)),
+                None => String::new(),
+            };
+            xml.push_str(&format!(
+                "  <exchange method=\"{}\" url=\"{}\"{soap_action}>\n    ",
+                escape(&exchange.method),
+                escape(&exchange.url),
+            ));
+            push_element(&mut xml, "request", "", &exchange.request);
+            xml.push_str(&format!(
+                "\n    <response status=\"{}\">\n",
+                exchange.status
+            ));
+            for (name, value) in &exchange.headers {
+                xml.push_str("      ");
+                let name = format!(" name=\"{}\"", escape(name));
+                push_element(&mut xml, "header", &name, value);
+                xml.push('\n');
+            }
+            xml.push_str("      ");
+            push_element(&mut xml, "body", "", &exchange.body);
+            xml.push_str("\n    </response>\n  </exchange>\n");
+        }
+
+        for notification in &self.notifications {
+            xml.push_str(&format!(
+                "  <notification method=\"{}\" url=\"{}\">\n",
+                escape(&notification.method),
+                escape(&notification.url),
+            ));
+            for (name, value) in &notification.headers {
+                xml.push_str("    ");
+                let name = format!(" name=\"{}\"", escape(name));
+                push_element(&mut xml, "header", &name, value);
+                xml.push('\n');
+            }
+            xml.push_str("    ");
+            push_element(&mut xml, "body", "", &notification.body);
+            xml.push_str("\n  </notification>\n");
+        }
+
+        xml.push_str("</fixture>\n");
+        xml
+    }
+
+    /// Writes the fixture to a file, see [`to_xml`](Fixture::to_xml).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.to_xml())?)
+    }
+
+    /// Whether nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.exchanges.is_empty() && self.notifications.is_empty()
+    }
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name).ok_or_else(|| {
+        Error::XmlMissingElement(node.tag_name().name().to_string(), name.to_string())
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+        .ok_or_else(|| {
+            Error::XmlMissingElement(node.tag_name().name().to_string(), name.to_string())
+        })
+}
+
+/// The content written by [`push_element`].
+fn content(node: Node<'_, '_>) -> Result<Vec<u8>> {
+    let text = node.text().unwrap_or_default();
+    match node.attribute("encoding") {
+        Some("base64") => {
+            base64_decode(text).ok_or(Error::ParseError("invalid base64 content in fixture"))
+        }
+        _ => Ok(text.as_bytes().to_vec()),
+    }
+}
+
+/// Appends `<tag attributes>content</tag>`, with the content as text if XML preserves it
+/// exactly and base64 encoded otherwise.
+fn push_element(xml: &mut String, tag: &str, attributes: &str, content: &[u8]) {
+    match std::str::from_utf8(content) {
+        Ok(text) if text.chars().all(is_preserved) => xml.push_str(&format!(
+            "<{tag}{attributes}>{}</{tag}>",
+            utils::escape_xml(text)
+        )),
+        _ => xml.push_str(&format!(
+            "<{tag}{attributes} encoding=\"base64\">{}</{tag}>",
+            base64_encode(content)
+        )),
+    }
+}
+
+/// Whether `c` reads back unchanged from XML text. Parsers turn carriage returns into line
+/// feeds, and most control characters aren't allowed at all.
+fn is_preserved(c: char) -> bool {
+    matches!(c, '\t' | '\n' | ' '..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize].into());
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let (mut n, mut bits) = (0u32, 0);
+    for c in encoded.trim_end().trim_end_matches('=').bytes() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        n = n << 6 | BASE64.iter().position(|&b| b == c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// The `<header>` children of `node`.
+fn parse_headers(node: Node<'_, '_>) -> Result<Vec<(String, Vec<u8>)>> {
+    node.children()
+        .filter(|child| child.tag_name().name() == "header")
+        .map(|header| Ok((attribute(header, "name")?.to_string(), content(header)?)))
+        .collect()
+}
+
+fn parse_exchange(node: Node<'_, '_>) -> Result<Exchange> {
+    let response = child(node, "response")?;
+    let headers = parse_headers(response)?;
+
+    Ok(Exchange {
+        method: attribute(node, "method")?.to_string(),
+        url: attribute(node, "url")?.to_string(),
+        soap_action: node.attribute("soapAction").map(str::to_string),
+        request: content(child(node, "request")?)?,
+        status: attribute(response, "status")?
+            .parse()
+            .map_err(Error::invalid_response)?,
+        headers,
+        body: content(child(response, "body")?)?,
+    })
+}
+
+fn parse_notification(node: Node<'_, '_>) -> Result<Notification> {
+    Ok(Notification {
+        url: attribute(node, "url")?.to_string(),
+        method: attribute(node, "method")?.to_string(),
+        headers: parse_headers(node)?,
+        body: content(child(node, "body")?)?,
+    })
+}
+
+/// Captures the traffic of the [`Client`](crate::Client)s it is passed to with
+/// [`ClientBuilder::record`](crate::ClientBuilder::record).
+///
+/// Cloning a `Recorder` is cheap and shares the recording.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    fixture: Arc<Mutex<Fixture>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    /// Everything recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().unwrap().clone()
+    }
+
+    /// Records a request sent by a client and the response it received.
+    pub(crate) async fn record_exchange(
+        &self,
+        request: Request<Full<Bytes>>,
+        response: &Response<Bytes>,
+    ) {
+        let soap_action = soap_action(&request);
+        let (parts, body) = request.into_parts();
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(infallible) => match infallible {},
+        };
+        let headers = header_pairs(response.headers());
+
+        let exchange = Exchange {
+            method: parts.method.to_string(),
+            url: parts.uri.to_string(),
+            soap_action,
+            request: body.to_vec(),
+            status: response.status().as_u16(),
+            headers,
+            body: response.body().to_vec(),
+        };
+        self.fixture.lock().unwrap().exchanges.push(exchange);
+    }
+
+    /// Records a notification received for a subscription to the service with the event URL
+    /// `url`, before it is parsed.
+    #[cfg(feature = "subscribe")]
+    pub(crate) fn record_notification(
+        &self,
+        url: &http::Uri,
+        method: &http::Method,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) {
+        let notification = Notification {
+            url: url.to_string(),
+            method: method.to_string(),
+            headers: header_pairs(headers),
+            body: body.to_vec(),
+        };
+        self.fixture
+            .lock()
+            .unwrap()
+            .notifications
+            .push(notification);
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fixture = self.fixture.lock().unwrap();
+        f.debug_struct("Recorder")
+            .field("exchanges", &fixture.exchanges.len())
+            .field("notifications", &fixture.notifications.len())
+            .finish()
+    }
+}
+
+/// A copy of `request` to record after it was sent.
+pub(crate) fn copy_request(request: &Request<Full<Bytes>>) -> Request<Full<Bytes>> {
+    let mut copy = Request::new(request.body().clone());
+    *copy.method_mut() = request.method().clone();
+    *copy.uri_mut() = request.uri().clone();
+    *copy.headers_mut() = request.headers().clone();
+    copy
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, Vec<u8>)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+        .collect()
+}
+
+/// The recorded headers, without the ones which aren't valid anymore.
+fn header_map(headers: &[(String, Vec<u8>)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_bytes(value),
+        ) {
+            map.append(name, value);
+        }
+    }
+    map
+}
+
+fn soap_action<B>(request: &Request<B>) -> Option<String> {
+    request
+        .headers()
+        .get("SOAPAction")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Where the requests of a [`Client`](crate::Client) go.
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Transport {
+    #[default]
+    Network,
+    Record(Recorder),
+    Replay(Arc<Replay>),
+}
+
+/// Serves the exchanges of a fixture in the order they were recorded.
+#[derive(Debug)]
+pub(crate) struct Replay {
+    fixture: Fixture,
+    used: Mutex<Vec<bool>>,
+    /// The event streams of replayed subscriptions, by the index in their callback URL.
+    #[cfg(feature = "subscribe")]
+    listeners: Mutex<Vec<mpsc::UnboundedSender<Result<Event>>>>,
+}
+
+/// The callback URLs of replayed subscriptions, followed by the index of their listener.
+#[cfg(feature = "subscribe")]
+const REPLAY_CALLBACK: &str = "http://replay.invalid/event/";
+
+impl Replay {
+    pub(crate) fn new(fixture: Fixture) -> Self {
+        let used = Mutex::new(vec![false; fixture.exchanges.len()]);
+        Replay {
+            fixture,
+            used,
+            #[cfg(feature = "subscribe")]
+            listeners: Mutex::default(),
+        }
+    }
+
+    /// The response to the first unused exchange with the same method, URL and `SOAPAction`,
+    /// or of the last one if all were used. Unknown requests are answered with
+    /// `404 Not Found`.
+    ///
+    /// A successful `SUBSCRIBE` request makes the recorded notifications for the SID of the
+    /// response arrive at its callback URL.
+    pub(crate) fn respond<B>(&self, request: &Request<B>) -> Response<Bytes> {
+        let soap_action = soap_action(request);
+        let url = request.uri().to_string();
+        let matching = |exchange: &&Exchange| {
+            exchange.method == request.method().as_str()
+                && exchange.url == url
+                && exchange.soap_action == soap_action
+        };
+
+        let mut used = self.used.lock().unwrap();
+        let exchange = self
+            .fixture
+            .exchanges
+            .iter()
+            .enumerate()
+            .find(|(i, exchange)| !used[*i] && matching(exchange))
+            .map(|(i, exchange)| {
+                used[i] = true;
+                exchange
+            })
+            .or_else(|| self.fixture.exchanges.iter().rfind(matching));
+
+        let Some(exchange) = exchange else {
+            let mut response = Response::new(Bytes::new());
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            return response;
+        };
+
+        let mut response = Response::new(Bytes::from(exchange.body.clone()));
+        *response.status_mut() =
+            StatusCode::from_u16(exchange.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        *response.headers_mut() = header_map(&exchange.headers);
+
+        #[cfg(feature = "subscribe")]
+        if request.method().as_str() == "SUBSCRIBE" && response.status() == StatusCode::OK {
+            self.notify(request, &response);
+        }
+        response
+    }
+
+    /// Returns a callback URL for a replayed subscription and the stream of events sent to it.
+    /// Like the one of a real subscription, the stream doesn't end after the recorded events.
+    #[cfg(feature = "subscribe")]
+    pub(crate) fn listen(&self) -> (String, impl futures_core::Stream<Item = Result<Event>>) {
+        use futures_util::StreamExt;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut listeners = self.listeners.lock().unwrap();
+        let callback = format!("{REPLAY_CALLBACK}{}", listeners.len());
+        listeners.push(tx);
+
+        let mut tracker = SequenceTracker::default();
+        let events = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx)).map(
+            move |mut event: Result<Event>| {
+                if let Ok(event) = &mut event {
+                    tracker.track(event);
+                }
+                event
+            },
+        );
+        (callback, events)
+    }
+
+    /// Sends the recorded notifications for the subscription made by `request` to the stream
+    /// of its callback URL, as the event server would have: the ones it rejected are skipped.
+    #[cfg(feature = "subscribe")]
+    fn notify<B>(&self, request: &Request<B>, response: &Response<Bytes>) {
+        fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some(value.trim())
+        }
+        let (Some(callback), Some(sid)) = (
+            header(request.headers(), "CALLBACK"),
+            header(response.headers(), "SID"),
+        ) else {
+            return;
+        };
+
+        let listeners = self.listeners.lock().unwrap();
+        let Some(listener) = callback
+            .trim_matches(['<', '>'])
+            .strip_prefix(REPLAY_CALLBACK)
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| listeners.get(index))
+        else {
+            return;
+        };
+
+        let url = request.uri().to_string();
+        for notification in &self.fixture.notifications {
+            let headers = header_map(&notification.headers);
+            if notification.url != url || header(&headers, "SID") != Some(sid) {
+                continue;
+            }
+            let Ok(method) = http::Method::from_bytes(notification.method.as_bytes()) else {
+                continue;
+            };
+            if let Ok(event) = parse_notify(&method, &headers, &notification.body) {
+                let _ = listener.send(Ok(event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fixture_round_trip() {
+        let recorder = Recorder::new();
+        let request =
+            Request::post("http://192.168.1.2:1400/MediaRenderer/RenderingControl/Control")
+                .header(
+                    "SOAPAction",
+                    "\"urn:schemas-upnp-org:service:RenderingControl:1#GetVolume\"",
+                )
+                .body(Full::from("<InstanceID>0</InstanceID>"))
+                .unwrap();
+        let response = Response::builder()
+            .status(500)
+            .header("CONTENT-TYPE", "text/xml; charset=\"utf-8\"")
+            .header("X-RAW", HeaderValue::from_bytes(b"caf\xe9").unwrap())
+            .body(Bytes::from_static(
+                b"<s:Envelope>&amp; \"fault\"\r\n\x01\xff</s:Envelope>",
+            ))
+            .unwrap();
+        recorder
+            .record_exchange(copy_request(&request), &response)
+            .await;
+        recorder
+            .fixture
+            .lock()
+            .unwrap()
+            .notifications
+            .push(Notification {
+                url: "http://192.168.1.2:1400/MediaRenderer/RenderingControl/Event".to_string(),
+                method: "NOTIFY".to_string(),
+                headers: vec![("sid".to_string(), b"uuid:RINCON_1".to_vec())],
+                body: b"<e:propertyset>\r\n<LastChange>&lt;Event/&gt;</LastChange>".to_vec(),
+            });
+
+        let fixture = recorder.fixture();
+        let parsed = Fixture::from_xml(&fixture.to_xml()).unwrap();
+        assert_eq!(parsed, fixture);
+
+        let replay = Replay::new(parsed);
+        let replayed = replay.respond(&request);
+        assert_eq!(replayed.status(), 500);
+        assert_eq!(replayed.body(), response.body());
+        assert_eq!(replayed.headers(), response.headers());
+        // repeated requests get the last matching response, unknown ones 404
+        assert_eq!(replay.respond(&request).status(), 500);
+        let get = Request::get("http://192.168.1.2:1400/xml/device_description.xml")
+            .body(())
+            .unwrap();
+        assert_eq!(replay.respond(&get).status(), 404);
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn replay_events() {
+        use futures_util::{FutureExt, StreamExt};
+
+        let url = "http://192.168.1.2:1400/RenderingControl/Event";
+        let subscribe = |sid: &str| Exchange {
+            method: "SUBSCRIBE".to_string(),
+            url: url.to_string(),
+            soap_action: None,
+            request: Vec::new(),
+            status: 200,
+            headers: vec![("sid".to_string(), sid.as_bytes().to_vec())],
+            body: Vec::new(),
+        };
+        let notification = |sid: &str, seq: u32, body: &str| Notification {
+            url: url.to_string(),
+            method: "NOTIFY".to_string(),
+            headers: vec![
+                ("nt".to_string(), b"upnp:event".to_vec()),
+                ("nts".to_string(), b"upnp:propchange".to_vec()),
+                ("sid".to_string(), sid.as_bytes().to_vec()),
+                ("seq".to_string(), seq.to_string().into_bytes()),
+            ],
+            body: body.as_bytes().to_vec(),
+        };
+        let volume = |volume: u16| {
+            format!(
+                "<e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">\
+                 <e:property><Volume>{volume}</Volume></e:property></e:propertyset>"
+            )
+        };
+        let fixture = Fixture {
+            exchanges: vec![subscribe("uuid:1"), subscribe("uuid:2")],
+            notifications: vec![
+                notification("uuid:1", 0, &volume(10)),
+                notification("uuid:2", 0, &volume(20)),
+                notification("uuid:1", 1, "<e:propertyset"),
+                notification("uuid:1", 2, &volume(12)),
+            ],
+        };
+        let replay = Replay::new(Fixture::from_xml(&fixture.to_xml()).unwrap());
+
+        let (callback, events) = replay.listen();
+        let mut events = std::pin::pin!(events);
+        let request = Request::builder()
+            .method("SUBSCRIBE")
+            .uri(url)
+            .header("CALLBACK", format!("<{callback}>"))
+            .body(())
+            .unwrap();
+        assert_eq!(replay.respond(&request).status(), 200);
+
+        // only the events of the SID the subscription got, the malformed one is rejected
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(
+            (event.sid(), event.seq(), event.get("Volume")),
+            ("uuid:1", 0, Some("10"))
+        );
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!((event.seq(), event.get("Volume")), (2, Some("12")));
+        assert!(event.gap().is_some());
+        assert!(events.next().now_or_never().is_none());
+    }
+
+    #[test]
+    fn base64() {
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"foob", b"\r\n\x00\xff"] {
+            assert_eq!(base64_decode(&base64_encode(bytes)).as_deref(), Some(bytes));
+        }
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_decode("Zm9v\n YmE=").as_deref(), Some(&b"fooba"[..]));
+        assert_eq!(base64_decode("Zm9v!"), None);
+    }
+}
@@ -0,0 +1,608 @@
+use crate::{
+    httpu::{self, SearchResponse},
+    listen,
+    utils::yield_try,
+    Announcement, Client, Device, Error, Result,
+};
+use futures_core::stream::Stream;
+use futures_util::{
+    future::{self, Either},
+    stream::{self, FuturesUnordered, StreamExt},
+};
+use genawaiter::sync::{Co, Gen};
+use http::Uri;
+use ssdp_client::SearchTarget;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::time::{interval, MissedTickBehavior};
+
+/// How often entries are checked for an elapsed `max-age`.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Used for search responses without a (valid) `CACHE-CONTROL` header.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(1800);
+
+/// A change of the set of devices tracked by a [`DeviceRegistry`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RegistryEvent {
+    /// A device was seen for the first time.
+    Added(Device),
+    /// A device said `ssdp:byebye` or its `max-age` elapsed without it announcing itself again.
+    Removed(Device),
+    /// A known device changed its location or rebooted, its description was fetched again.
+    Changed(Device),
+}
+
+#[derive(Debug)]
+struct Entry {
+    device: Device,
+    boot_id: Option<u32>,
+    expires_at: Instant,
+}
+
+/// The tracked root devices by their UDN.
+#[derive(Debug, Default)]
+struct Devices {
+    entries: HashMap<String, Entry>,
+    /// The UDNs of embedded devices, pointing to the UDN of their root device.
+    aliases: HashMap<String, String>,
+}
+
+impl Devices {
+    /// The UDN of the root device of `udn`, or of the one at `location` for a device that
+    /// isn't known by its UDN yet.
+    fn key(&self, udn: &str, location: Option<&Uri>) -> Option<String> {
+        if self.entries.contains_key(udn) {
+            return Some(udn.to_string());
+        }
+        if let Some(root) = self.aliases.get(udn) {
+            return Some(root.clone());
+        }
+        let location = location?;
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.device.url() == location)
+            .map(|(udn, _)| udn.clone())
+    }
+
+    fn find(&mut self, udn: &str, location: Option<&Uri>) -> Option<&mut Entry> {
+        let key = self.key(udn, location)?;
+        self.entries.get_mut(&key)
+    }
+
+    /// Removes the entry of the root device with the UDN `key` and its embedded devices.
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.aliases.retain(|_, root| root != key);
+        Some(entry)
+    }
+
+    /// Adds `entry` by the UDN of its device, returns the entry it replaced.
+    fn insert(&mut self, entry: Entry) -> Option<Entry> {
+        let key = entry.device.udn().to_string();
+        let previous = self.remove(&key);
+        for embedded in entry.device.devices_iter() {
+            self.aliases.insert(embedded.udn().to_string(), key.clone());
+        }
+        self.entries.insert(key, entry);
+        previous
+    }
+}
+
+/// Keeps track of the devices currently available on the network.
+///
+/// The registry combines periodic searches (like [`discover`](crate::discover)) with
+/// passively [`listen`](crate::listen)ing for announcements.
+/// Devices are identified by their UDN, so a device is still recognized when it changes its IP
+/// address, and expire when their `CACHE-CONTROL: max-age` elapses.
+/// Embedded devices are tracked as part of their root device, an announcement or `ssdp:byebye`
+/// of an embedded device applies to its root device.
+///
+/// The registry is only updated while the stream returned by [`watch`](DeviceRegistry::watch)
+/// is polled.
+///
+/// # Example usage:
+/// ```rust,no_run
+/// use futures::prelude::*;
+/// use rupnp::{ssdp::SearchTarget, DeviceRegistry, RegistryEvent};
+///
+/// # async fn registry() -> Result<(), rupnp::Error> {
+/// let registry = DeviceRegistry::new(SearchTarget::RootDevice);
+/// let events = registry.watch().await?;
+/// let mut events = std::pin::pin!(events);
+///
+/// while let Some(event) = events.try_next().await? {
+///     match event {
+///         RegistryEvent::Added(device) => println!("+ {}", device.friendly_name()),
+///         RegistryEvent::Removed(device) => println!("- {}", device.friendly_name()),
+///         _ => {}
+///     }
+///     println!("{} devices online", registry.devices().len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceRegistry {
+    search_target: SearchTarget,
+    search_interval: Duration,
+    search_timeout: Duration,
+    client: Client,
+    devices: Arc<Mutex<Devices>>,
+}
+
+impl DeviceRegistry {
+    /// Creates a registry tracking devices matching the `search_target`.
+    pub fn new(search_target: SearchTarget) -> Self {
+        DeviceRegistry {
+            search_target,
+            search_interval: Duration::from_secs(60),
+            search_timeout: Duration::from_secs(3),
+            client: Client::new(),
+            devices: Arc::default(),
+        }
+    }
+
+    /// How often an active search is sent. Defaults to 60 seconds.
+    pub fn search_interval(mut self, search_interval: Duration) -> Self {
+        self.search_interval = search_interval;
+        self
+    }
+
+    /// How long to wait for responses of each search. Defaults to 3 seconds.
+    pub fn search_timeout(mut self, search_timeout: Duration) -> Self {
+        self.search_timeout = search_timeout;
+        self
+    }
+
+    /// The client used to fetch device descriptions.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Returns all devices that are currently available.
+    pub fn devices(&self) -> Vec<Device> {
+        self.devices
+            .lock()
+            .unwrap()
+            .entries
+            .values()
+            .map(|entry| entry.device.clone())
+            .collect()
+    }
+
+    /// Returns the root device with the given UDN (`uuid:...`), if it is available.
+    pub fn get(&self, udn: &str) -> Option<Device> {
+        self.devices
+            .lock()
+            .unwrap()
+            .entries
+            .get(udn)
+            .map(|entry| entry.device.clone())
+    }
+
+    /// Starts tracking devices and returns a stream of changes.
+    ///
+    /// Errors, e.g. for a device whose description could not be fetched, are yielded without
+    /// ending the stream.
+    pub async fn watch(&self) -> Result<impl Stream<Item = Result<RegistryEvent>>> {
+        let announcements = listen().await?.map(Input::Announcement);
+
+        let search_target = self.search_target.clone();
+        let search_timeout = self.search_timeout;
+        let searches = ticks(self.search_interval)
+            .then(move |()| {
+                let search_target = search_target.clone();
+                let group = SocketAddr::from((httpu::SSDP_MULTICAST_V4, httpu::SSDP_PORT));
+                async move { httpu::search(group, &search_target, search_timeout, 3, None).await }
+            })
+            .flat_map(|responses| match responses {
+                Ok(responses) => responses.left_stream(),
+                Err(err) => stream::iter(Some(Err(err))).right_stream(),
+            })
+            .map(Input::SearchResponse);
+
+        let expiry_checks = ticks(EXPIRY_CHECK_INTERVAL).map(|()| Input::ExpiryCheck);
+
+        let inputs = stream::select(announcements, stream::select(searches, expiry_checks));
+        let registry = self.clone();
+
+        Ok(Gen::new(move |co| registry.watch_stream(inputs, co)))
+    }
+
+    async fn watch_stream(self, inputs: impl Stream<Item = Input>, co: Co<Result<RegistryEvent>>) {
+        let mut inputs = std::pin::pin!(inputs);
+        // descriptions are fetched concurrently, so a slow device doesn't hold up the others
+        let mut fetches = FuturesUnordered::new();
+        // the locations being fetched, embedded devices share the one of their root device
+        let mut fetching = HashSet::new();
+
+        loop {
+            let input = if fetches.is_empty() {
+                inputs.next().await
+            } else {
+                match future::select(inputs.next(), fetches.next()).await {
+                    Either::Left((input, _)) => input,
+                    Either::Right((fetched, _)) => {
+                        let (seen, device): (Seen, Result<Device>) = fetched.expect("not empty");
+                        fetching.remove(&seen.location);
+                        co.yield_(device.map(|device| self.insert(seen, device)))
+                            .await;
+                        continue;
+                    }
+                }
+            };
+            let Some(input) = input else { break };
+
+            let seen = match input {
+                Input::Announcement(announcement) => match yield_try!(co => announcement) {
+                    Announcement::Alive(alive) => self.seen(Seen {
+                        udn: udn_from_usn(alive.usn()).to_string(),
+                        location: alive.location().clone(),
+                        max_age: alive.max_age(),
+                        boot_id: alive.boot_id(),
+                        matches: self.matches(alive.notification_type()),
+                    }),
+                    Announcement::ByeBye(byebye) => {
+                        let removed = {
+                            let mut devices = self.devices.lock().unwrap();
+                            devices
+                                .key(udn_from_usn(byebye.usn()), None)
+                                .and_then(|key| devices.remove(&key))
+                        };
+                        if let Some(entry) = removed {
+                            co.yield_(Ok(RegistryEvent::Removed(entry.device))).await;
+                        }
+                        None
+                    }
+                    Announcement::Update(update) => {
+                        let mut devices = self.devices.lock().unwrap();
+                        let udn = udn_from_usn(update.usn());
+                        if let Some(entry) = devices.find(udn, Some(update.location())) {
+                            entry.boot_id = update.next_boot_id();
+                        }
+                        None
+                    }
+                },
+                Input::SearchResponse(response) => {
+                    let response = yield_try!(co => response);
+                    yield_try!(co => self.search_response(&response))
+                }
+                Input::ExpiryCheck => {
+                    for device in self.expire() {
+                        co.yield_(Ok(RegistryEvent::Removed(device))).await;
+                    }
+                    None
+                }
+            };
+
+            if let Some(seen) = seen {
+                if fetching.insert(seen.location.clone()) {
+                    fetches.push(self.fetch(seen));
+                }
+            }
+        }
+    }
+
+    fn search_response(&self, response: &SearchResponse) -> Result<Option<Seen>> {
+        let usn = response.usn.as_deref().ok_or(Error::MissingHeader("USN"))?;
+
+        Ok(self.seen(Seen {
+            udn: udn_from_usn(usn).to_string(),
+            location: response.location.clone(),
+            max_age: response.max_age.unwrap_or(DEFAULT_MAX_AGE),
+            boot_id: response.boot_id,
+            matches: true,
+        }))
+    }
+
+    /// Refreshes the expiry of a known device, or returns that its description needs to be
+    /// fetched because it is new, moved or rebooted.
+    fn seen(&self, seen: Seen) -> Option<Seen> {
+        let mut devices = self.devices.lock().unwrap();
+        match devices.find(&seen.udn, Some(&seen.location)) {
+            Some(entry) => {
+                entry.expires_at = Instant::now() + seen.max_age;
+
+                let moved = entry.device.url() != &seen.location;
+                let rebooted = seen.boot_id.is_some() && seen.boot_id != entry.boot_id;
+                (moved || rebooted).then_some(seen)
+            }
+            None => seen.matches.then_some(seen),
+        }
+    }
+
+    fn fetch(&self, seen: Seen) -> impl Future<Output = (Seen, Result<Device>)> {
+        let client = self.client.clone();
+        async move {
+            let device = client.device(seen.location.clone()).await;
+            (seen, device)
+        }
+    }
+
+    fn insert(&self, seen: Seen, device: Device) -> RegistryEvent {
+        let mut devices = self.devices.lock().unwrap();
+        // a device which moved is replaced, even if its description now has another UDN
+        let moved = devices
+            .key(&seen.udn, Some(&seen.location))
+            .and_then(|key| devices.remove(&key));
+        let previous = devices.insert(Entry {
+            device: device.clone(),
+            boot_id: seen.boot_id,
+            expires_at: Instant::now() + seen.max_age,
+        });
+        let previous = previous.or(moved);
+
+        match previous {
+            Some(_) => RegistryEvent::Changed(device),
+            None => RegistryEvent::Added(device),
+        }
+    }
+
+    fn expire(&self) -> Vec<Device> {
+        let now = Instant::now();
+        let mut devices = self.devices.lock().unwrap();
+
+        let expired: Vec<String> = devices
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at <= now)
+            .map(|(udn, _)| udn.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|udn| devices.remove(&udn))
+            .map(|entry| entry.device)
+            .collect()
+    }
+
+    fn matches(&self, notification_type: &SearchTarget) -> bool {
+        self.search_target == SearchTarget::All || &self.search_target == notification_type
+    }
+}
+
+enum Input {
+    Announcement(Result<Announcement>),
+    SearchResponse(Result<SearchResponse>),
+    ExpiryCheck,
+}
+
+struct Seen {
+    /// The UDN of the `USN`, which may be the one of an embedded device.
+    udn: String,
+    location: Uri,
+    max_age: Duration,
+    boot_id: Option<u32>,
+    /// Whether the announcement matches the registry's search target, so that an unknown
+    /// device should be added.
+    matches: bool,
+}
+
+fn ticks(period: Duration) -> impl Stream<Item = ()> {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    stream::unfold(interval, |mut interval| async move {
+        interval.tick().await;
+        Some(((), interval))
+    })
+}
+
+/// `uuid:device-UUID::urn:schemas-upnp-org:service:serviceType:ver` -> `uuid:device-UUID`
+fn udn_from_usn(usn: &str) -> &str {
+    usn.split("::").next().unwrap_or(usn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{listen::parse_announcement, DeviceSpec};
+    use tokio::time::timeout;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:ZonePlayer:1</deviceType>
+    <friendlyName>Living Room</friendlyName>
+    <manufacturer>rupnp</manufacturer>
+    <modelName>Player</modelName>
+    <UDN>uuid:player</UDN>
+    <deviceList>
+      <device>
+        <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+        <friendlyName>Living Room - Renderer</friendlyName>
+        <manufacturer>rupnp</manufacturer>
+        <modelName>Renderer</modelName>
+        <UDN>uuid:renderer</UDN>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+    fn announcement(nts: &str, udn: &str, location: &Uri, extra_headers: &str) -> Input {
+        let text = format!(
+            "NOTIFY * HTTP/1.1\r\n\
+            NT: upnp:rootdevice\r\n\
+            NTS: {nts}\r\n\
+            USN: {udn}::upnp:rootdevice\r\n\
+            LOCATION: {location}\r\n\
+            {extra_headers}\r\n"
+        );
+        Input::Announcement(Ok(parse_announcement(text.as_bytes()).unwrap()))
+    }
+
+    /// A registry already tracking the device described by `DESCRIPTION`, and the stream
+    /// watching `inputs`.
+    fn watch(
+        expires_in: Duration,
+        inputs: Vec<Input>,
+    ) -> (DeviceRegistry, impl Stream<Item = Result<RegistryEvent>>) {
+        let url: Uri = "http://127.0.0.1:1400/xml/device_description.xml"
+            .parse()
+            .unwrap();
+        let spec = DeviceSpec::from_xml_str(&url, DESCRIPTION, &[]).unwrap();
+        let registry = DeviceRegistry::new(SearchTarget::RootDevice);
+        registry.devices.lock().unwrap().insert(Entry {
+            device: Device::from_parts(url, spec),
+            boot_id: Some(1),
+            expires_at: Instant::now() + expires_in,
+        });
+
+        // the inputs of a registry never end
+        let inputs = stream::iter(inputs).chain(stream::pending());
+        let watching = registry.clone();
+        let events = Gen::new(move |co| watching.watch_stream(inputs, co));
+        (registry, events)
+    }
+
+    async fn next_event(
+        events: impl Stream<Item = Result<RegistryEvent>>,
+    ) -> Option<RegistryEvent> {
+        let mut events = std::pin::pin!(events);
+        let event = timeout(Duration::from_millis(200), events.next())
+            .await
+            .ok()?;
+        Some(event.unwrap().unwrap())
+    }
+
+    #[tokio::test]
+    async fn expiry() {
+        let (registry, events) = watch(Duration::ZERO, vec![Input::ExpiryCheck]);
+
+        let event = next_event(events).await;
+        assert!(
+            matches!(event, Some(RegistryEvent::Removed(device)) if device.friendly_name() == "Living Room")
+        );
+        assert!(registry.devices().is_empty());
+    }
+
+    #[tokio::test]
+    async fn alive_refreshes_expiry() {
+        let url = "http://127.0.0.1:1400/xml/device_description.xml"
+            .parse()
+            .unwrap();
+        let alive = |udn| {
+            let headers = "CACHE-CONTROL: max-age=1800\r\nBOOTID.UPNP.ORG: 1\r\n";
+            announcement("ssdp:alive", udn, &url, headers)
+        };
+        // embedded devices refresh their root device
+        let inputs = vec![alive("uuid:renderer"), Input::ExpiryCheck];
+        let (registry, events) = watch(Duration::ZERO, inputs);
+
+        assert!(next_event(events).await.is_none());
+        assert_eq!(registry.devices().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn byebye() {
+        let url = Uri::from_static("http://127.0.0.1:1400/xml/device_description.xml");
+        let inputs = vec![
+            announcement("ssdp:byebye", "uuid:unknown", &url, ""),
+            announcement("ssdp:byebye", "uuid:player", &url, ""),
+        ];
+        let (registry, events) = watch(Duration::from_secs(1800), inputs);
+
+        let event = next_event(events).await;
+        assert!(matches!(event, Some(RegistryEvent::Removed(_))));
+        assert!(registry.get("uuid:player").is_none());
+    }
+
+    #[tokio::test]
+    async fn byebye_of_embedded_device() {
+        let url = Uri::from_static("http://127.0.0.1:1400/xml/device_description.xml");
+        let inputs = vec![announcement("ssdp:byebye", "uuid:renderer", &url, "")];
+        let (registry, events) = watch(Duration::from_secs(1800), inputs);
+
+        let event = next_event(events).await;
+        assert!(
+            matches!(event, Some(RegistryEvent::Removed(device)) if device.friendly_name() == "Living Room")
+        );
+        let devices = registry.devices.lock().unwrap();
+        assert!(devices.entries.is_empty());
+        assert!(devices.aliases.is_empty());
+    }
+
+    #[tokio::test]
+    async fn update() {
+        let url = Uri::from_static("http://127.0.0.1:1400/xml/device_description.xml");
+        let headers = "BOOTID.UPNP.ORG: 1\r\nNEXTBOOTID.UPNP.ORG: 2\r\n";
+        let alive = "CACHE-CONTROL: max-age=1800\r\nBOOTID.UPNP.ORG: 2\r\n";
+        let inputs = vec![
+            announcement("ssdp:update", "uuid:player", &url, headers),
+            // not a reboot, so the description isn't fetched again
+            announcement("ssdp:alive", "uuid:player", &url, alive),
+        ];
+        let (registry, events) = watch(Duration::from_secs(1800), inputs);
+
+        assert!(next_event(events).await.is_none());
+        let devices = registry.devices.lock().unwrap();
+        assert_eq!(devices.entries["uuid:player"].boot_id, Some(2));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn embedded_devices_are_added_once() {
+        let mock = crate::testing::MockDevice::builder()
+            .description(DESCRIPTION)
+            .start()
+            .await
+            .unwrap();
+        // a device which doesn't answer doesn't hold up the others
+        let hanging = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hanging = format!("http://{}/", hanging.local_addr().unwrap());
+
+        let alive = |udn, location: &Uri| {
+            announcement(
+                "ssdp:alive",
+                udn,
+                location,
+                "CACHE-CONTROL: max-age=1800\r\n",
+            )
+        };
+        let inputs = vec![
+            alive("uuid:hanging", &hanging.parse().unwrap()),
+            // the embedded device announces itself before its root device
+            alive("uuid:renderer", mock.url()),
+            alive("uuid:player", mock.url()),
+        ];
+        let registry = DeviceRegistry::new(SearchTarget::RootDevice);
+        let watching = registry.clone();
+        let (byebyes, byebyes_rx) = futures::channel::mpsc::unbounded();
+        let inputs = stream::iter(inputs).chain(byebyes_rx);
+        let events = Gen::new(move |co| watching.watch_stream(inputs, co));
+        let mut events = std::pin::pin!(events);
+
+        let event = next_event(&mut events).await;
+        assert!(matches!(event, Some(RegistryEvent::Added(device)) if device.url() == mock.url()));
+        assert!(next_event(&mut events).await.is_none());
+        assert_eq!(registry.devices().len(), 1);
+        assert!(registry.get("uuid:player").is_some());
+        assert!(registry.get("uuid:renderer").is_none());
+
+        // the root device is removed by its own byebye
+        let byebye = announcement("ssdp:byebye", "uuid:player", mock.url(), "");
+        byebyes.unbounded_send(byebye).unwrap();
+        let event = next_event(&mut events).await;
+        assert!(
+            matches!(event, Some(RegistryEvent::Removed(device)) if device.url() == mock.url())
+        );
+        assert!(registry.devices().is_empty());
+    }
+
+    #[test]
+    fn udn_from_usn_strips_notification_type() {
+        assert_eq!(
+            udn_from_usn("uuid:RINCON_000E58::urn:schemas-upnp-org:service:AVTransport:1"),
+            "uuid:RINCON_000E58"
+        );
+        assert_eq!(udn_from_usn("uuid:RINCON_000E58"), "uuid:RINCON_000E58");
+    }
+}
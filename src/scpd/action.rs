@@ -1,10 +1,10 @@
 use crate::{
     find_in_xml,
-    scpd::{StateVariable, StateVariableKind},
-    utils, Error, Result,
+    scpd::{state_variable::InvalidValue, DataType, StateVariable, StateVariableKind, Value},
+    utils, ActionArgs, ActionResponse, Error, Result,
 };
 use roxmltree::Node;
-use std::{fmt, rc::Rc};
+use std::{collections::HashMap, fmt, sync::Arc};
 
 /// An SCPD action.
 /// The action consists of its name used in the services
@@ -43,7 +43,7 @@ impl fmt::Display for Action {
 impl Action {
     pub(crate) fn from_xml(
         node: Node<'_, '_>,
-        state_variables: &[Rc<StateVariable>],
+        state_variables: &[Arc<StateVariable>],
     ) -> Result<Self> {
         #[allow(non_snake_case)]
         let (name, arguments) = find_in_xml! { node => name, ?argumentList };
@@ -74,7 +74,183 @@ impl Action {
     pub fn output_arguments(&self) -> impl Iterator<Item = &Argument> {
         self.arguments.iter().filter(|a| a.is_output())
     }
+
+    /// Checks the `args` against the input arguments of this action, the same way the device
+    /// would: every input argument has to be present in the declared order, and each value must
+    /// be valid for the argument's [`StateVariable`](struct.StateVariable.html).
+    pub fn validate(&self, args: &ActionArgs) -> Result<(), ArgumentError> {
+        if let Some(name) = args.invalid_name() {
+            return Err(ArgumentError::InvalidName(name.to_string()));
+        }
+        let mut args = args.iter();
+
+        for argument in self.input_arguments() {
+            let (name, value) = args
+                .next()
+                .ok_or_else(|| ArgumentError::MissingArgument(argument.name().to_string()))?;
+
+            if name != argument.name() {
+                return Err(if self.input_arguments().any(|a| a.name() == name) {
+                    ArgumentError::WrongOrder {
+                        expected: argument.name().to_string(),
+                        found: name.to_string(),
+                    }
+                } else {
+                    ArgumentError::UnknownArgument(name.to_string())
+                });
+            }
+
+            let state_var = argument.related_state_variable();
+            let value = value.as_str();
+            state_var.check_value(value).map_err(|invalid| {
+                let argument = name.to_string();
+                let value = value.to_string();
+                match invalid {
+                    InvalidValue::DataType => ArgumentError::InvalidValue {
+                        argument,
+                        value,
+                        datatype: state_var.datatype(),
+                    },
+                    InvalidValue::NotAllowed => ArgumentError::NotAllowed { argument, value },
+                    InvalidValue::OutOfRange => ArgumentError::OutOfRange { argument, value },
+                }
+            })?;
+        }
+
+        match args.next() {
+            Some((name, _)) => Err(ArgumentError::UnknownArgument(name.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Decodes the response of an [`action`](../struct.Service.html#method.action) call into
+    /// typed [`Value`](enum.Value.html)s, using the datatype of each output argument.
+    /// The values are in the order of the response, like the
+    /// [`ActionResponse`](../struct.ActionResponse.html) itself: UPnP requires output arguments
+    /// to be sent in the order they are declared in, and some devices repeat an argument name.
+    /// Use [`decode_response_map`](#method.decode_response_map) to look the values up by name.
+    ///
+    /// Values which are not declared as output arguments are kept as strings.
+    /// A value which can't be parsed as its datatype is an
+    /// [`InvalidResponse`](../enum.Error.html#variant.InvalidResponse).
+    ///
+    /// # Example usage:
+    ///
+    /// ```rust,no_run
+    /// # async fn rendering_control_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+    /// let scpd = service.scpd().await?;
+    /// let action = scpd.action("GetVolume").unwrap();
+    ///
+    /// let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
+    /// let response = service.action("GetVolume", args).await?;
+    /// let response = action.decode_response(&response)?;
+    ///
+    /// let volume = response
+    ///     .iter()
+    ///     .find(|(name, _)| name == "CurrentVolume")
+    ///     .and_then(|(_, value)| value.as_u64());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decode_response(&self, response: &ActionResponse) -> Result<Vec<(String, Value)>> {
+        response
+            .iter()
+            .map(|(name, text)| {
+                let argument = self.output_arguments().find(|a| a.name() == name);
+                let value = match argument {
+                    Some(argument) => {
+                        let datatype = argument.related_state_variable().datatype();
+                        Value::parse(datatype, text).map_err(Error::invalid_response)?
+                    }
+                    None => Value::String(text.to_string()),
+                };
+                Ok((name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Like [`decode_response`](#method.decode_response), but returns the values by the name of
+    /// their argument. If a name is repeated, the last value is kept.
+    pub fn decode_response_map(&self, response: &ActionResponse) -> Result<HashMap<String, Value>> {
+        let values = self.decode_response(response)?;
+        Ok(values.into_iter().collect())
+    }
+}
+
+/// An argument list which doesn't match an action's [`Argument`](struct.Argument.html)s.
+/// See [`Action::validate`](struct.Action.html#method.validate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArgumentError {
+    /// The service doesn't have an action of that name.
+    UnknownAction(String),
+    /// The action doesn't have an input argument of that name.
+    UnknownArgument(String),
+    /// An input argument was not given.
+    MissingArgument(String),
+    /// The arguments are not in the order defined by the service.
+    WrongOrder { expected: String, found: String },
+    /// The value can't be parsed as the argument's datatype.
+    InvalidValue {
+        argument: String,
+        value: String,
+        datatype: DataType,
+    },
+    /// The value is not in the `allowedValueList` of the argument.
+    NotAllowed { argument: String, value: String },
+    /// The value is not inside the `allowedValueRange` of the argument.
+    OutOfRange { argument: String, value: String },
+    /// The name can't be used as an xml element name, see [`ActionArgs::push`].
+    InvalidName(String),
+}
+
+impl ArgumentError {
+    /// The UPnP error code a device would respond with, see
+    /// [`UPnPError::err_code_description`](../struct.UPnPError.html#method.err_code_description).
+    pub fn err_code(&self) -> u16 {
+        match self {
+            ArgumentError::UnknownAction(_) => 401,
+            ArgumentError::UnknownArgument(_)
+            | ArgumentError::InvalidName(_)
+            | ArgumentError::MissingArgument(_)
+            | ArgumentError::WrongOrder { .. } => 402,
+            ArgumentError::InvalidValue { .. } | ArgumentError::NotAllowed { .. } => 600,
+            ArgumentError::OutOfRange { .. } => 601,
+        }
+    }
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgumentError::UnknownAction(action) => write!(f, "no action called `{action}`"),
+            ArgumentError::UnknownArgument(argument) => {
+                write!(f, "unknown argument `{argument}`")
+            }
+            ArgumentError::MissingArgument(argument) => {
+                write!(f, "missing argument `{argument}`")
+            }
+            ArgumentError::WrongOrder { expected, found } => {
+                write!(f, "expected argument `{expected}`, found `{found}`")
+            }
+            ArgumentError::InvalidValue {
+                argument,
+                value,
+                datatype,
+            } => write!(f, "`{value}` is not a valid {datatype} for `{argument}`"),
+            ArgumentError::NotAllowed { argument, value } => {
+                write!(f, "`{value}` is not an allowed value for `{argument}`")
+            }
+            ArgumentError::OutOfRange { argument, value } => {
+                write!(f, "`{value}` is out of range for `{argument}`")
+            }
+            ArgumentError::InvalidName(argument) => {
+                write!(f, "`{argument}` is not a valid argument name")
+            }
+        }
+    }
 }
+impl std::error::Error for ArgumentError {}
 
 /// Every argument has its associated [`StateVariable`](struct.StateVariable.html), which contains
 /// more information about its possible values/range/etc.
@@ -83,7 +259,7 @@ pub struct Argument {
     name: String,
     // if not input, it is an output
     is_input: bool,
-    state_var: Rc<StateVariable>,
+    state_var: Arc<StateVariable>,
 }
 impl fmt::Display for Argument {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -106,7 +282,7 @@ impl fmt::Display for Argument {
 }
 
 impl Argument {
-    fn from_xml(node: Node<'_, '_>, state_variables: &[Rc<StateVariable>]) -> Result<Self> {
+    fn from_xml(node: Node<'_, '_>, state_variables: &[Arc<StateVariable>]) -> Result<Self> {
         #[allow(non_snake_case)]
         let (name, direction, related_statevar) =
             find_in_xml! { node => name, direction, relatedStateVariable };
@@ -145,7 +321,7 @@ impl Argument {
         !self.is_input
     }
 
-    pub fn related_state_variable(&self) -> &Rc<StateVariable> {
+    pub fn related_state_variable(&self) -> &Arc<StateVariable> {
         &self.state_var
     }
 }
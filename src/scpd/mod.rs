@@ -1,20 +1,14 @@
-use crate::{
-    find_in_xml,
-    utils::{self, HttpResponseExt, HyperBodyExt},
-    Error,
-};
-use bytes::Bytes;
-use http::Uri;
-use http_body_util::Empty;
-use hyper_util::rt::TokioExecutor;
+use crate::{find_in_xml, utils, ActionArgs, Error};
 use roxmltree::{Document, Node};
 use ssdp_client::URN;
-use std::rc::Rc;
+use std::sync::Arc;
 
 mod action;
 mod state_variable;
+mod value;
 pub use action::*;
 pub use state_variable::*;
+pub use value::*;
 
 /// Service Control Protocol Description.
 /// It contains information about a particular service, more specifically its actions and state
@@ -22,33 +16,36 @@ pub use state_variable::*;
 #[derive(Debug)]
 pub struct SCPD {
     urn: URN,
-    state_variables: Vec<Rc<StateVariable>>,
+    state_variables: Vec<Arc<StateVariable>>,
     actions: Vec<Action>,
 }
 impl SCPD {
     pub fn urn(&self) -> &URN {
         &self.urn
     }
-    pub fn state_variables(&self) -> &[Rc<StateVariable>] {
+    pub fn state_variables(&self) -> &[Arc<StateVariable>] {
         &self.state_variables
     }
     pub fn actions(&self) -> &[Action] {
         &self.actions
     }
 
-    /// Fetches the SCPD description.
-    /// The `urn` has to be provided because it isn't included in the description.
-    pub(crate) async fn from_url(url: &Uri, urn: URN) -> Result<Self, Error> {
-        let body = hyper_util::client::legacy::Client::builder(TokioExecutor::new())
-            .build_http::<Empty<Bytes>>()
-            .get(url.clone())
-            .await?
-            .err_if_not_200()?
-            .into_body()
-            .bytes()
-            .await?;
-        let body = std::str::from_utf8(&body)?;
+    /// Returns the action called `name`.
+    pub fn action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name() == name)
+    }
+
+    /// Checks that the service has an action called `action` and that `args` are valid for it.
+    /// See [`Action::validate`](struct.Action.html#method.validate).
+    pub fn validate(&self, action: &str, args: &ActionArgs) -> Result<(), ArgumentError> {
+        self.action(action)
+            .ok_or_else(|| ArgumentError::UnknownAction(action.to_string()))?
+            .validate(args)
+    }
 
+    /// Parses an SCPD description, e.g. one which was saved to a file.
+    /// The `urn` has to be provided because it isn't included in the description.
+    pub fn from_xml_str(urn: URN, body: &str) -> Result<Self, Error> {
         let document = Document::parse(body)?;
         let scpd = utils::find_root(&document, "scpd", "Service Control Point Definition")?;
 
@@ -59,7 +56,7 @@ impl SCPD {
             .children()
             .filter(Node::is_element)
             .map(StateVariable::from_xml)
-            .map(|sv| sv.map(Rc::new))
+            .map(|sv| sv.map(Arc::new))
             .collect::<Result<_, _>>()?;
         let actions = actions
             .children()
@@ -73,4 +70,164 @@ impl SCPD {
             actions,
         })
     }
+
+    pub(crate) fn from_xml(body: &[u8], urn: URN) -> Result<Self, Error> {
+        SCPD::from_xml_str(urn, std::str::from_utf8(body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ActionResponse;
+
+    const RENDERING_CONTROL: &str = r#"<?xml version="1.0"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+  <serviceStateTable>
+    <stateVariable sendEvents="no">
+      <name>A_ARG_TYPE_InstanceID</name>
+      <dataType>ui4</dataType>
+    </stateVariable>
+    <stateVariable sendEvents="no">
+      <name>A_ARG_TYPE_Channel</name>
+      <dataType>string</dataType>
+      <allowedValueList>
+        <allowedValue>Master</allowedValue>
+        <allowedValue>LF</allowedValue>
+      </allowedValueList>
+    </stateVariable>
+    <stateVariable sendEvents="no">
+      <name>Volume</name>
+      <dataType>ui2</dataType>
+      <allowedValueRange>
+        <minimum>0</minimum>
+        <maximum>100</maximum>
+        <step>1</step>
+      </allowedValueRange>
+    </stateVariable>
+  </serviceStateTable>
+  <actionList>
+    <action>
+      <name>SetVolume</name>
+      <argumentList>
+        <argument>
+          <name>InstanceID</name>
+          <direction>in</direction>
+          <relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable>
+        </argument>
+        <argument>
+          <name>Channel</name>
+          <direction>in</direction>
+          <relatedStateVariable>A_ARG_TYPE_Channel</relatedStateVariable>
+        </argument>
+        <argument>
+          <name>DesiredVolume</name>
+          <direction>in</direction>
+          <relatedStateVariable>Volume</relatedStateVariable>
+        </argument>
+      </argumentList>
+    </action>
+    <action>
+      <name>GetVolume</name>
+      <argumentList>
+        <argument>
+          <name>InstanceID</name>
+          <direction>in</direction>
+          <relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable>
+        </argument>
+        <argument>
+          <name>Channel</name>
+          <direction>in</direction>
+          <relatedStateVariable>A_ARG_TYPE_Channel</relatedStateVariable>
+        </argument>
+        <argument>
+          <name>CurrentVolume</name>
+          <direction>out</direction>
+          <relatedStateVariable>Volume</relatedStateVariable>
+        </argument>
+      </argumentList>
+    </action>
+  </actionList>
+</scpd>"#;
+
+    fn scpd() -> SCPD {
+        let urn = URN::service("schemas-upnp-org", "RenderingControl", 1);
+        SCPD::from_xml_str(urn, RENDERING_CONTROL).unwrap()
+    }
+
+    fn validate(args: &[(&str, &str)]) -> Result<(), ArgumentError> {
+        scpd().validate("SetVolume", &args.iter().copied().collect())
+    }
+
+    #[test]
+    fn validate_arguments() {
+        let valid = [
+            ("InstanceID", "0"),
+            ("Channel", "Master"),
+            ("DesiredVolume", "25"),
+        ];
+        assert_eq!(validate(&valid), Ok(()));
+
+        let err = validate(&[("InstanceID", "0"), ("Channel", "Master")]).unwrap_err();
+        assert_eq!(err, ArgumentError::MissingArgument("DesiredVolume".into()));
+        assert_eq!(err.err_code(), 402);
+
+        let err = validate(&[
+            ("Channel", "Master"),
+            ("InstanceID", "0"),
+            ("DesiredVolume", "25"),
+        ])
+        .unwrap_err();
+        assert!(matches!(err, ArgumentError::WrongOrder { .. }));
+
+        let err = validate(&[("InstanceID", "-1"), ("Channel", "Master")]).unwrap_err();
+        assert!(matches!(err, ArgumentError::InvalidValue { .. }));
+        assert_eq!(err.err_code(), 600);
+
+        let err = validate(&[("InstanceID", "0"), ("Channel", "RF")]).unwrap_err();
+        assert!(matches!(err, ArgumentError::NotAllowed { .. }));
+
+        let err = validate(&[
+            ("InstanceID", "0"),
+            ("Channel", "Master"),
+            ("DesiredVolume", "101"),
+        ])
+        .unwrap_err();
+        assert_eq!(err.err_code(), 601);
+
+        let mut extra = valid.to_vec();
+        extra.push(("Mute", "1"));
+        let err = validate(&extra).unwrap_err();
+        assert_eq!(err, ArgumentError::UnknownArgument("Mute".into()));
+    }
+
+    #[test]
+    fn decode_response() {
+        let values = vec![
+            ("Vendor".to_string(), "x".to_string()),
+            ("CurrentVolume".to_string(), "25".to_string()),
+        ];
+        let response = ActionResponse::new("GetVolume", values, String::new());
+
+        let action = scpd();
+        let action = action.action("GetVolume").unwrap();
+        assert_eq!(
+            action.decode_response(&response).unwrap(),
+            [
+                ("Vendor".to_string(), Value::String("x".to_string())),
+                ("CurrentVolume".to_string(), Value::Ui2(25)),
+            ]
+        );
+
+        let values = action.decode_response_map(&response).unwrap();
+        assert_eq!(values["CurrentVolume"], Value::Ui2(25));
+        assert_eq!(values["Vendor"], Value::String("x".to_string()));
+
+        let values = vec![("CurrentVolume".to_string(), "loud".to_string())];
+        let response = ActionResponse::new("GetVolume", values, String::new());
+        assert!(matches!(
+            action.decode_response(&response),
+            Err(Error::InvalidResponse(_))
+        ));
+    }
 }
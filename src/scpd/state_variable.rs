@@ -1,4 +1,4 @@
-use crate::{find_in_xml, utils, Error, Result};
+use crate::{find_in_xml, scpd::Value, utils, Error, Result};
 use roxmltree::Node;
 use std::fmt;
 use std::fmt::Formatter;
@@ -9,6 +9,7 @@ use std::fmt::Formatter;
 #[derive(Debug)]
 pub struct StateVariable {
     name: String,
+    datatype: DataType,
     default: Option<String>,
     kind: StateVariableKind,
     send_events: bool,
@@ -47,6 +48,31 @@ impl StateVariableRange {
     pub fn step(&self) -> Option<&str> {
         self.step.as_deref()
     }
+
+    /// Whether the numeric `value` lies within the bounds and is a multiple of the step.
+    /// Bounds which can't be parsed as numbers are ignored.
+    fn contains(&self, value: &str) -> bool {
+        let Ok(value) = value.trim().parse::<f64>() else {
+            return false;
+        };
+        let minimum = self.minimum.trim().parse::<f64>().ok();
+        let maximum = self.maximum.trim().parse::<f64>().ok();
+        let step = self.step().and_then(|step| step.trim().parse::<f64>().ok());
+
+        if minimum.is_some_and(|minimum| value < minimum)
+            || maximum.is_some_and(|maximum| value > maximum)
+        {
+            return false;
+        }
+
+        match step {
+            Some(step) if step > 0.0 => {
+                let steps = (value - minimum.unwrap_or(0.0)) / step;
+                (steps - steps.round()).abs() < 1e-9
+            }
+            _ => true,
+        }
+    }
 }
 
 /// The type of a state variable.
@@ -84,7 +110,7 @@ impl StateVariable {
         let range = range.map(range_from_xml).transpose()?;
 
         let name = utils::parse_node_text(name)?;
-        let datatype = utils::parse_node_text(datatype)?;
+        let datatype: DataType = utils::parse_node_text(datatype)?;
         let optional = optional.is_some();
 
         let send_events = utils::find_node_attribute(node, "sendEvents")
@@ -105,6 +131,7 @@ impl StateVariable {
 
         Ok(StateVariable {
             name,
+            datatype,
             kind,
             default,
             optional,
@@ -129,6 +156,7 @@ impl StateVariable {
         self.send_events
     }
 
+    /// Whether changes are multicast to the network, see `rupnp::listen_multicast_events`.
     pub fn is_multicast(&self) -> bool {
         self.multicast
     }
@@ -136,10 +164,42 @@ impl StateVariable {
     pub fn kind(&self) -> &StateVariableKind {
         &self.kind
     }
+
+    /// The datatype of the variable, also for enumerations and ranges.
+    pub fn datatype(&self) -> DataType {
+        self.datatype
+    }
+
+    /// Checks whether `value` is a valid value of this state variable, i.e. whether it can be
+    /// parsed as its datatype and is one of its allowed values or inside its allowed range.
+    pub(crate) fn check_value(&self, value: &str) -> Result<(), InvalidValue> {
+        if !self.datatype.is_valid(value) {
+            return Err(InvalidValue::DataType);
+        }
+
+        match &self.kind {
+            StateVariableKind::Enum(variants) if !variants.iter().any(|v| v == value) => {
+                Err(InvalidValue::NotAllowed)
+            }
+            StateVariableKind::Range(range) if !range.contains(value) => {
+                Err(InvalidValue::OutOfRange)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The reason a value was rejected by [`StateVariable::check_value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InvalidValue {
+    DataType,
+    NotAllowed,
+    OutOfRange,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[allow(non_camel_case_types)]
+#[non_exhaustive]
 pub enum DataType {
     ui1,
     ui2,
@@ -165,6 +225,7 @@ pub enum DataType {
     BinBase64,
     BinHex,
     Uri,
+    Uuid,
 }
 
 impl fmt::Display for DataType {
@@ -210,11 +271,19 @@ impl std::str::FromStr for DataType {
             "bin.base64" => Ok(DataType::BinBase64),
             "bin.hex" => Ok(DataType::BinHex),
             "uri" => Ok(DataType::Uri),
+            "uuid" => Ok(DataType::Uuid),
             _ => Err(ParseDataTypeErr(s.to_string())),
         }
     }
 }
 
+impl DataType {
+    /// Whether `value` is a valid lexical representation of this datatype.
+    fn is_valid(self, value: &str) -> bool {
+        Value::parse(self, value).is_ok()
+    }
+}
+
 fn range_from_xml(node: Node<'_, '_>) -> Result<StateVariableRange> {
     #[allow(non_snake_case)]
     let (minimum, maximum, step) = find_in_xml! { node => minimum, maximum, ?step };
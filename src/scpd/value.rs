@@ -0,0 +1,730 @@
+use crate::scpd::DataType;
+use std::{convert::TryInto, fmt};
+
+/// A typed value of a [`DataType`](enum.DataType.html).
+///
+/// Values are parsed from and formatted as their textual representation used in SOAP messages
+/// and events, see [`Value::parse`](enum.Value.html#method.parse) and the `Display` impl.
+/// `number` and `float` are parsed as `r8`.
+///
+/// # Example usage:
+/// ```rust
+/// use rupnp::scpd::{DataType, Value};
+///
+/// let mute = Value::parse(DataType::Boolean, "yes").unwrap();
+/// assert_eq!(mute, Value::Boolean(true));
+/// assert_eq!(mute.to_string(), "1");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    Ui1(u8),
+    Ui2(u16),
+    Ui4(u32),
+    Ui8(u64),
+    I1(i8),
+    I2(i16),
+    I4(i32),
+    Int(i64),
+    R4(f32),
+    R8(f64),
+    /// At most 14 digits before and 4 digits after the decimal point.
+    Fixed14_4(f64),
+    Char(char),
+    String(String),
+    Date(Date),
+    DateTime(DateTime),
+    DateTimeTz(DateTime, Option<UtcOffset>),
+    Time(Time),
+    TimeTz(Time, Option<UtcOffset>),
+    Boolean(bool),
+    BinBase64(Vec<u8>),
+    BinHex(Vec<u8>),
+    Uri(String),
+    Uuid(u128),
+}
+
+impl Value {
+    /// Parses the textual representation of a value of the given `datatype`.
+    pub fn parse(datatype: DataType, text: &str) -> Result<Value, ParseValueError> {
+        parse_value(datatype, text).ok_or_else(|| ParseValueError {
+            datatype,
+            value: text.to_string(),
+        })
+    }
+
+    /// The datatype of the value, `number` and `float` are reported as `r8`.
+    pub fn datatype(&self) -> DataType {
+        match self {
+            Value::Ui1(_) => DataType::ui1,
+            Value::Ui2(_) => DataType::ui2,
+            Value::Ui4(_) => DataType::ui4,
+            Value::Ui8(_) => DataType::ui8,
+            Value::I1(_) => DataType::i1,
+            Value::I2(_) => DataType::i2,
+            Value::I4(_) => DataType::i4,
+            Value::Int(_) => DataType::int,
+            Value::R4(_) => DataType::r4,
+            Value::R8(_) => DataType::r8,
+            Value::Fixed14_4(_) => DataType::Fixed14_4,
+            Value::Char(_) => DataType::Char,
+            Value::String(_) => DataType::String,
+            Value::Date(_) => DataType::Date,
+            Value::DateTime(_) => DataType::DateTime,
+            Value::DateTimeTz(..) => DataType::DateTimeTz,
+            Value::Time(_) => DataType::Time,
+            Value::TimeTz(..) => DataType::TimeTz,
+            Value::Boolean(_) => DataType::Boolean,
+            Value::BinBase64(_) => DataType::BinBase64,
+            Value::BinHex(_) => DataType::BinHex,
+            Value::Uri(_) => DataType::Uri,
+            Value::Uuid(_) => DataType::Uuid,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of any integer type, if it fits into an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Ui1(v) => Some(v.into()),
+            Value::Ui2(v) => Some(v.into()),
+            Value::Ui4(v) => Some(v.into()),
+            Value::Ui8(v) => v.try_into().ok(),
+            Value::I1(v) => Some(v.into()),
+            Value::I2(v) => Some(v.into()),
+            Value::I4(v) => Some(v.into()),
+            Value::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of any integer type, if it is not negative.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Ui8(v) => Some(v),
+            _ => self.as_i64().and_then(|v| v.try_into().ok()),
+        }
+    }
+
+    /// Returns the value of any numeric type as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::R4(v) => Some(v.into()),
+            Value::R8(v) | Value::Fixed14_4(v) => Some(v),
+            Value::Ui8(v) => Some(v as f64),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    /// Returns the text of a `string` or `uri`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) | Value::Uri(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the decoded content of a `bin.base64` or `bin.hex`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::BinBase64(bytes) | Value::BinHex(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Ui1(v) => write!(f, "{v}"),
+            Value::Ui2(v) => write!(f, "{v}"),
+            Value::Ui4(v) => write!(f, "{v}"),
+            Value::Ui8(v) => write!(f, "{v}"),
+            Value::I1(v) => write!(f, "{v}"),
+            Value::I2(v) => write!(f, "{v}"),
+            Value::I4(v) => write!(f, "{v}"),
+            Value::Int(v) => write!(f, "{v}"),
+            Value::R4(v) => fmt_float(f, (*v).into()),
+            Value::R8(v) => fmt_float(f, *v),
+            Value::Fixed14_4(v) => {
+                let formatted = format!("{v:.4}");
+                let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+                f.write_str(formatted)
+            }
+            Value::Char(c) => write!(f, "{c}"),
+            Value::String(s) | Value::Uri(s) => f.write_str(s),
+            Value::Date(date) => write!(f, "{date}"),
+            Value::DateTime(date_time) => write!(f, "{date_time}"),
+            Value::DateTimeTz(date_time, offset) => {
+                write!(f, "{date_time}")?;
+                offset.map_or(Ok(()), |offset| write!(f, "{offset}"))
+            }
+            Value::Time(time) => write!(f, "{time}"),
+            Value::TimeTz(time, offset) => {
+                write!(f, "{time}")?;
+                offset.map_or(Ok(()), |offset| write!(f, "{offset}"))
+            }
+            Value::Boolean(b) => f.write_str(if *b { "1" } else { "0" }),
+            Value::BinBase64(bytes) => f.write_str(&base64_encode(bytes)),
+            Value::BinHex(bytes) => bytes.iter().try_for_each(|b| write!(f, "{b:02x}")),
+            Value::Uuid(uuid) => write!(
+                f,
+                "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                uuid >> 96,
+                (uuid >> 80) & 0xffff,
+                (uuid >> 64) & 0xffff,
+                (uuid >> 48) & 0xffff,
+                uuid & 0xffff_ffff_ffff
+            ),
+        }
+    }
+}
+
+/// The value could not be parsed as the expected datatype.
+/// See [`Value::parse`](enum.Value.html#method.parse).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseValueError {
+    datatype: DataType,
+    value: String,
+}
+impl ParseValueError {
+    pub fn datatype(&self) -> DataType {
+        self.datatype
+    }
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a valid {}", self.value, self.datatype)
+    }
+}
+impl std::error::Error for ParseValueError {}
+
+#[derive(Debug)]
+pub struct ParseDateTimeErr;
+impl fmt::Display for ParseDateTimeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid ISO 8601 date or time")
+    }
+}
+impl std::error::Error for ParseDateTimeErr {}
+
+/// A calendar date, `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+impl Date {
+    /// Returns `None` if the date doesn't exist.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Date> {
+        let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap_year => 29,
+            2 => 28,
+            _ => return None,
+        };
+        (year <= 9999 && (1..=days).contains(&day)).then_some(Date { year, month, day })
+    }
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+impl std::str::FromStr for Date {
+    type Err = ParseDateTimeErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('-');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None) => (year, month, day),
+            _ => return Err(ParseDateTimeErr),
+        };
+        Date::new(
+            digits(year, 4).ok_or(ParseDateTimeErr)?,
+            digits(month, 2).ok_or(ParseDateTimeErr)?,
+            digits(day, 2).ok_or(ParseDateTimeErr)?,
+        )
+        .ok_or(ParseDateTimeErr)
+    }
+}
+
+/// A time of day, `HH:MM:SS` with optional fractional seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+}
+impl Time {
+    /// Returns `None` if any of the components is out of range.
+    pub fn new(hour: u8, minute: u8, second: u8, nanosecond: u32) -> Option<Time> {
+        (hour < 24 && minute < 60 && second < 60 && nanosecond < 1_000_000_000).then_some(Time {
+            hour,
+            minute,
+            second,
+            nanosecond,
+        })
+    }
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+    pub fn nanosecond(&self) -> u32 {
+        self.nanosecond
+    }
+}
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+impl std::str::FromStr for Time {
+    type Err = ParseDateTimeErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, fraction) = match s.split_once('.') {
+            Some((s, fraction)) => (s, Some(fraction)),
+            None => (s, None),
+        };
+        let nanosecond = match fraction {
+            Some(fraction)
+                if !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                // digits beyond nanosecond precision are truncated
+                let fraction = format!("{:0<9.9}", fraction);
+                fraction.parse().map_err(|_| ParseDateTimeErr)?
+            }
+            Some(_) => return Err(ParseDateTimeErr),
+            None => 0,
+        };
+
+        let mut parts = s.split(':');
+        let (hour, minute, second) = match (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            (Some(hour), Some(minute), Some(second), None) => (hour, minute, second),
+            _ => return Err(ParseDateTimeErr),
+        };
+        Time::new(
+            digits(hour, 2).ok_or(ParseDateTimeErr)?,
+            digits(minute, 2).ok_or(ParseDateTimeErr)?,
+            digits(second, 2).ok_or(ParseDateTimeErr)?,
+            nanosecond,
+        )
+        .ok_or(ParseDateTimeErr)
+    }
+}
+
+/// A date with an optional time, `YYYY-MM-DD[THH:MM:SS]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    date: Date,
+    time: Option<Time>,
+}
+impl DateTime {
+    pub fn new(date: Date, time: Option<Time>) -> DateTime {
+        DateTime { date, time }
+    }
+    pub fn date(&self) -> Date {
+        self.date
+    }
+    pub fn time(&self) -> Option<Time> {
+        self.time
+    }
+}
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date)?;
+        match self.time {
+            Some(time) => write!(f, "T{time}"),
+            None => Ok(()),
+        }
+    }
+}
+impl std::str::FromStr for DateTime {
+    type Err = ParseDateTimeErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('T') {
+            Some((date, time)) => Ok(DateTime::new(date.parse()?, Some(time.parse()?))),
+            None => Ok(DateTime::new(s.parse()?, None)),
+        }
+    }
+}
+
+/// The time zone of a `dateTime.tz` or `time.tz`, as an offset from UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcOffset {
+    minutes: i16,
+}
+impl UtcOffset {
+    pub const UTC: UtcOffset = UtcOffset { minutes: 0 };
+
+    /// Returns `None` if the offset is more than 14 hours.
+    pub fn from_minutes(minutes: i16) -> Option<UtcOffset> {
+        (minutes.abs() <= 14 * 60).then_some(UtcOffset { minutes })
+    }
+    pub fn minutes(&self) -> i16 {
+        self.minutes
+    }
+}
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.minutes == 0 {
+            return f.write_str("Z");
+        }
+        let sign = if self.minutes < 0 { '-' } else { '+' };
+        let minutes = self.minutes.abs();
+        write!(f, "{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+impl std::str::FromStr for UtcOffset {
+    type Err = ParseDateTimeErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "Z" {
+            return Ok(UtcOffset::UTC);
+        }
+        let sign = match s.as_bytes().first() {
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            _ => return Err(ParseDateTimeErr),
+        };
+        let s = &s[1..];
+        let (hours, minutes) = match s.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if s.len() == 4 => s.split_at(2),
+            None => (s, "00"),
+        };
+        let hours: i16 = digits(hours, 2).ok_or(ParseDateTimeErr)?;
+        let minutes: i16 = digits(minutes, 2).ok_or(ParseDateTimeErr)?;
+        if minutes >= 60 {
+            return Err(ParseDateTimeErr);
+        }
+        UtcOffset::from_minutes(sign * (hours * 60 + minutes)).ok_or(ParseDateTimeErr)
+    }
+}
+
+fn parse_value(datatype: DataType, text: &str) -> Option<Value> {
+    fn number<T: std::str::FromStr>(text: &str) -> Option<T> {
+        text.trim().parse().ok()
+    }
+
+    match datatype {
+        DataType::ui1 => number(text).map(Value::Ui1),
+        DataType::ui2 => number(text).map(Value::Ui2),
+        DataType::ui4 => number(text).map(Value::Ui4),
+        DataType::ui8 => number(text).map(Value::Ui8),
+        DataType::i1 => number(text).map(Value::I1),
+        DataType::i2 => number(text).map(Value::I2),
+        DataType::i4 => number(text).map(Value::I4),
+        DataType::int => number(text).map(Value::Int),
+        DataType::r4 => number(text).map(Value::R4),
+        DataType::r8 | DataType::Number | DataType::Float => number(text).map(Value::R8),
+        DataType::Fixed14_4 => parse_fixed14_4(text.trim()).map(Value::Fixed14_4),
+        DataType::Char => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Value::Char(c)),
+                _ => None,
+            }
+        }
+        DataType::String => Some(Value::String(text.to_string())),
+        DataType::Date => text.trim().parse().ok().map(Value::Date),
+        DataType::DateTime => text.trim().parse().ok().map(Value::DateTime),
+        DataType::DateTimeTz => {
+            let text = text.trim();
+            let time_start = text.find('T').map_or(10, |i| i + 1);
+            let (date_time, offset) = split_offset(text, time_start)?;
+            date_time
+                .parse()
+                .ok()
+                .map(|date_time| Value::DateTimeTz(date_time, offset))
+        }
+        DataType::Time => text.trim().parse().ok().map(Value::Time),
+        DataType::TimeTz => {
+            let (time, offset) = split_offset(text.trim(), 0)?;
+            time.parse().ok().map(|time| Value::TimeTz(time, offset))
+        }
+        DataType::Boolean => match text.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" => Some(Value::Boolean(true)),
+            "0" | "false" | "no" => Some(Value::Boolean(false)),
+            _ => None,
+        },
+        DataType::BinBase64 => base64_decode(text).map(Value::BinBase64),
+        DataType::BinHex => hex_decode(text.trim()).map(Value::BinHex),
+        DataType::Uri => Some(Value::Uri(text.trim().to_string())),
+        DataType::Uuid => parse_uuid(text.trim()).map(Value::Uuid),
+    }
+}
+
+/// Parses a number of exactly `len` ascii digits.
+fn digits<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Splits an optional time zone off the end, searching from `time_start` on so the dashes of a
+/// date aren't mistaken for a negative offset.
+fn split_offset(s: &str, time_start: usize) -> Option<(&str, Option<UtcOffset>)> {
+    if let Some(s) = s.strip_suffix('Z') {
+        return Some((s, Some(UtcOffset::UTC)));
+    }
+    match s.get(time_start..).and_then(|time| time.rfind(['+', '-'])) {
+        Some(i) => {
+            let (s, offset) = s.split_at(time_start + i);
+            Some((s, Some(offset.parse().ok()?)))
+        }
+        None => Some((s, None)),
+    }
+}
+
+fn parse_fixed14_4(text: &str) -> Option<f64> {
+    let unsigned = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    let valid = !integer.is_empty()
+        && integer.len() <= 14
+        && fraction.len() <= 4
+        && integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit());
+    valid.then(|| text.parse().ok()).flatten()
+}
+
+fn fmt_float(f: &mut fmt::Formatter<'_>, v: f64) -> fmt::Result {
+    if v.is_infinite() {
+        f.write_str(if v < 0.0 { "-INF" } else { "INF" })
+    } else {
+        write!(f, "{v}")
+    }
+}
+
+fn parse_uuid(text: &str) -> Option<u128> {
+    let text = text.strip_prefix("uuid:").unwrap_or(text);
+    let groups: Vec<&str> = text.split('-').collect();
+    let lengths = groups.iter().map(|group| group.len());
+    if !lengths.eq([8, 4, 4, 4, 12]) || !groups.iter().all(|g| is_hex(g)) {
+        return None;
+    }
+    u128::from_str_radix(&groups.concat(), 16).ok()
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 || !is_hex(text) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes padded base64, ignoring whitespace (line breaks are common in long values).
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text: Vec<u8> = text.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if text.len() % 4 != 0 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(text.len() / 4 * 3);
+    for (i, chunk) in text.chunks(4).enumerate() {
+        let last = i == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&b| b == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+
+        let mut n = 0u32;
+        for &b in &chunk[..4 - padding] {
+            let sextet = BASE64_ALPHABET.iter().position(|&c| c == b)?;
+            n = n << 6 | sextet as u32;
+        }
+        n <<= 6 * padding;
+
+        decoded.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(datatype: DataType, text: &str, formatted: &str) -> Value {
+        let value = Value::parse(datatype, text).unwrap();
+        assert_eq!(value.to_string(), formatted);
+        assert_eq!(Value::parse(datatype, formatted).unwrap(), value);
+        value
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(roundtrip(DataType::ui2, " 42 ", "42"), Value::Ui2(42));
+        assert_eq!(roundtrip(DataType::i1, "-128", "-128"), Value::I1(-128));
+        assert!(Value::parse(DataType::ui1, "256").is_err());
+        assert!(Value::parse(DataType::ui4, "-1").is_err());
+
+        roundtrip(DataType::r8, "1.5", "1.5");
+        roundtrip(DataType::r8, "INF", "INF");
+        roundtrip(DataType::Fixed14_4, "12.5000", "12.5");
+        assert!(Value::parse(DataType::Fixed14_4, "1.23456").is_err());
+        assert!(Value::parse(DataType::Fixed14_4, "1e5").is_err());
+    }
+
+    #[test]
+    fn booleans() {
+        for text in ["1", "true", "yes", "YES"] {
+            assert_eq!(
+                roundtrip(DataType::Boolean, text, "1"),
+                Value::Boolean(true)
+            );
+        }
+        for text in ["0", "false", "no"] {
+            assert_eq!(
+                roundtrip(DataType::Boolean, text, "0"),
+                Value::Boolean(false)
+            );
+        }
+        assert!(Value::parse(DataType::Boolean, "2").is_err());
+    }
+
+    #[test]
+    fn binary() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            let value = roundtrip(DataType::BinBase64, encoded, encoded);
+            assert_eq!(value.as_bytes(), Some(bytes));
+        }
+        assert_eq!(
+            Value::parse(DataType::BinBase64, "Zm9v\r\nYmFy").unwrap(),
+            Value::BinBase64(b"foobar".to_vec())
+        );
+        assert!(Value::parse(DataType::BinBase64, "Zg==Zm8=").is_err());
+        assert!(Value::parse(DataType::BinBase64, "Zm9").is_err());
+
+        let value = roundtrip(DataType::BinHex, "00FFa0", "00ffa0");
+        assert_eq!(value.as_bytes(), Some(&[0x00, 0xff, 0xa0][..]));
+        assert!(Value::parse(DataType::BinHex, "abc").is_err());
+    }
+
+    #[test]
+    fn dates_and_times() {
+        roundtrip(DataType::Date, "2024-02-29", "2024-02-29");
+        assert!(Value::parse(DataType::Date, "2023-02-29").is_err());
+        assert!(Value::parse(DataType::Date, "2023-1-01").is_err());
+
+        roundtrip(DataType::DateTime, "2024-05-01", "2024-05-01");
+        roundtrip(
+            DataType::DateTime,
+            "2024-05-01T13:45:00.250",
+            "2024-05-01T13:45:00.25",
+        );
+        assert!(Value::parse(DataType::DateTime, "2024-05-01T13:45:00Z").is_err());
+
+        let value = roundtrip(
+            DataType::DateTimeTz,
+            "2024-05-01T13:45:00+02:00",
+            "2024-05-01T13:45:00+02:00",
+        );
+        let Value::DateTimeTz(_, offset) = value else {
+            unreachable!()
+        };
+        assert_eq!(offset.map(|offset| offset.minutes()), Some(120));
+        roundtrip(
+            DataType::DateTimeTz,
+            "2024-05-01T13:45:00",
+            "2024-05-01T13:45:00",
+        );
+        roundtrip(DataType::DateTimeTz, "2024-05-01", "2024-05-01");
+        roundtrip(DataType::DateTimeTz, "2024-05-01-01:00", "2024-05-01-01:00");
+        roundtrip(
+            DataType::DateTimeTz,
+            "2024-05-01T13:45:00+00:00",
+            "2024-05-01T13:45:00Z",
+        );
+
+        roundtrip(DataType::Time, "23:59:59", "23:59:59");
+        assert!(Value::parse(DataType::Time, "24:00:00").is_err());
+        roundtrip(DataType::TimeTz, "08:00:00-0530", "08:00:00-05:30");
+    }
+
+    #[test]
+    fn uuids() {
+        let uuid = "5f9ec1b3-ed59-49db-9f7c-d5e1b6a7b8c9";
+        roundtrip(DataType::Uuid, uuid, uuid);
+        roundtrip(
+            DataType::Uuid,
+            &format!("uuid:{}", uuid.to_uppercase()),
+            uuid,
+        );
+        assert!(Value::parse(DataType::Uuid, "5f9ec1b3ed5949db9f7cd5e1b6a7b8c9").is_err());
+    }
+}
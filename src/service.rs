@@ -2,30 +2,20 @@ use crate::{
     error::{Error, UPnPError},
     find_in_xml,
     scpd::SCPD,
-    utils::{self, HttpResponseExt},
-    Result,
+    utils, ActionArgs, ActionResponse, Client, Result,
 };
+#[cfg(feature = "subscribe")]
+use crate::{eventing, Event, Subscription};
 
 use bytes::Bytes;
 #[cfg(feature = "subscribe")]
 use futures_core::stream::Stream;
-#[cfg(feature = "subscribe")]
-use genawaiter::sync::{Co, Gen};
-use http_body_util::Empty;
-use hyper_util::{client::legacy::Client, rt::TokioExecutor};
-#[cfg(feature = "subscribe")]
-use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    net::TcpListener,
-};
+use http_body_util::Full;
 
-use http::{uri::PathAndQuery, Request, Uri};
+use http::{Request, Uri};
 use roxmltree::{Document, Node};
 use ssdp_client::URN;
 
-use std::collections::HashMap;
-use utils::HyperBodyExt;
-
 /// A UPnP Service is the description of endpoints on a device for performing actions and reading
 /// the service definition.
 /// For a list of actions and state variables the service provides, take a look at [`scpd`](struct.Service.html#method.scpd).
@@ -33,23 +23,31 @@ use utils::HyperBodyExt;
 pub struct Service {
     service_type: URN,
     service_id: String,
-    scpd_endpoint: PathAndQuery,
-    control_endpoint: PathAndQuery,
-    event_sub_endpoint: PathAndQuery,
+    scpd_url: Uri,
+    control_url: Uri,
+    event_sub_url: Uri,
 }
 
 impl Service {
-    pub(crate) fn from_xml(node: Node<'_, '_>) -> Result<Self> {
+    /// Parses a `<service>` of a device description. Its URLs are resolved against `base`,
+    /// which is the deprecated `URLBase` if the description contained one, or the URL the
+    /// description was fetched from.
+    pub(crate) fn from_xml(node: Node<'_, '_>, base: &Uri) -> Result<Self> {
         #[allow(non_snake_case)]
         let (service_type, service_id, scpd_endpoint, control_endpoint, event_sub_endpoint) =
             find_in_xml! { node => serviceType, serviceId, SCPDURL, controlURL, eventSubURL };
 
+        let resolve = |node: Node<'_, '_>| -> Result<Uri> {
+            let endpoint: String = utils::parse_node_text(node)?;
+            utils::resolve_url(base, &endpoint)
+        };
+
         Ok(Self {
             service_type: utils::parse_node_text(service_type)?,
             service_id: utils::parse_node_text(service_id)?,
-            scpd_endpoint: utils::parse_node_text(scpd_endpoint)?,
-            control_endpoint: utils::parse_node_text(control_endpoint)?,
-            event_sub_endpoint: utils::parse_node_text(event_sub_endpoint)?,
+            scpd_url: resolve(scpd_endpoint)?,
+            control_url: resolve(control_endpoint)?,
+            event_sub_url: resolve(event_sub_endpoint)?,
         })
     }
 
@@ -63,24 +61,33 @@ impl Service {
         &self.service_id
     }
 
-    pub(crate) fn control_url(&self, url: &Uri) -> Uri {
-        replace_url_path(url, &self.control_endpoint)
+    /// Returns the URL for performing [actions](struct.Service.html#method.action) on this
+    /// service.
+    ///
+    /// The `controlURL` of the description is resolved relative to the deprecated `URLBase` if
+    /// the description contained one, or the URL of the description otherwise.
+    pub fn control_url(&self) -> &Uri {
+        &self.control_url
     }
-    pub(crate) fn scpd_url(&self, url: &Uri) -> Uri {
-        replace_url_path(url, &self.scpd_endpoint)
+    /// Returns the URL of the [`SCPD`](scpd/struct.SCPD.html), resolved like the
+    /// [`control_url`](struct.Service.html#method.control_url).
+    pub fn scpd_url(&self) -> &Uri {
+        &self.scpd_url
     }
-    pub(crate) fn event_sub_url(&self, url: &Uri) -> Uri {
-        replace_url_path(url, &self.event_sub_endpoint)
+    /// Returns the URL for event subscriptions, resolved like the
+    /// [`control_url`](struct.Service.html#method.control_url).
+    pub fn event_sub_url(&self) -> &Uri {
+        &self.event_sub_url
     }
 
     /// Fetches the [`SCPD`](scpd/struct.SCPD.html) of this service.
-    pub async fn scpd(&self, url: &Uri) -> Result<SCPD> {
-        SCPD::from_url(&self.scpd_url(url), self.service_type().clone()).await
+    pub async fn scpd(&self) -> Result<SCPD> {
+        Client::new().scpd(self).await
     }
 
     /// Execute some UPnP Action on this service.
-    /// The URL is usually obtained by the device this service was found on.
     /// The payload is xml-formatted data.
+    /// The output arguments are returned in document order as an [`ActionResponse`].
     ///
     /// # Example usage:
     ///
@@ -98,7 +105,7 @@ impl Service {
     ///     .expect("service exists");
     ///
     /// let args = "<InstanceID>0</InstanceID><Channel>Master</Channel>";
-    /// let response = service.action(device.url(), "GetVolume", args).await?;
+    /// let response = service.action("GetVolume", args).await?;
     ///
     /// let volume = response
     ///     .get("CurrentVolume")
@@ -108,12 +115,69 @@ impl Service {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn action(
+    pub async fn action(&self, action: &str, payload: &str) -> Result<ActionResponse> {
+        Client::new().action(self, action, payload).await
+    }
+
+    /// Execute some UPnP Action on this service.
+    /// Unlike [`action`](struct.Service.html#method.action), the arguments are passed as
+    /// [`ActionArgs`], whose values are escaped, so they can contain any text like URLs with
+    /// `&` or DIDL-Lite metadata.
+    ///
+    /// # Example usage:
+    ///
+    /// ```rust,no_run
+    /// # async fn rendering_control_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+    /// use rupnp::ActionArgs;
+    ///
+    /// let args = ActionArgs::new()
+    ///     .arg("InstanceID", 0)
+    ///     .arg("Channel", "Master")
+    ///     .arg("DesiredVolume", 25u16);
+    /// service.call("SetVolume", &args).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call(&self, action: &str, args: &ActionArgs) -> Result<ActionResponse> {
+        Client::new().call(self, action, args).await
+    }
+
+    /// Like [`call`](struct.Service.html#method.call), but the arguments are checked against the
+    /// action in the `scpd` first.
+    /// Unknown, missing or misordered arguments and values which don't match the datatype,
+    /// `allowedValueList` or `allowedValueRange` of their state variable are rejected with
+    /// [`Error::InvalidArguments`](enum.Error.html#variant.InvalidArguments) without sending
+    /// the request.
+    ///
+    /// # Example usage:
+    ///
+    /// ```rust,no_run
+    /// # async fn rendering_control_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+    /// use rupnp::{ActionArgs, Error};
+    ///
+    /// let scpd = service.scpd().await?;
+    /// let args = ActionArgs::new()
+    ///     .arg("InstanceID", 0)
+    ///     .arg("Channel", "Master")
+    ///     .arg("DesiredVolume", 250u16);
+    ///
+    /// match service.call_validated(&scpd, "SetVolume", &args).await {
+    ///     Err(Error::InvalidArguments(err)) => eprintln!("{} ({})", err, err.err_code()),
+    ///     other => println!("{:?}", other?),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_validated(
         &self,
-        url: &Uri,
+        scpd: &SCPD,
         action: &str,
-        payload: &str,
-    ) -> Result<HashMap<String, String>> {
+        args: &ActionArgs,
+    ) -> Result<ActionResponse> {
+        Client::new().call_validated(self, scpd, action, args).await
+    }
+
+    pub(crate) fn action_request(&self, action: &str, payload: &str) -> Request<Full<Bytes>> {
         let body = format!(
             r#"
             <s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"
@@ -131,20 +195,15 @@ impl Service {
 
         let soap_action = format!("\"{}#{}\"", &self.service_type, action);
 
-        let request = Request::post(self.control_url(url))
+        Request::post(&self.control_url)
             .header("CONTENT-TYPE", "text/xml; charset=\"utf-8\"")
             .header("SOAPAction", soap_action)
-            .body(body)
-            .expect("infallible");
-        let doc = Client::builder(TokioExecutor::new())
-            .build_http()
-            .request(request)
-            .await?
-            .err_if_not_200()?
-            .into_body()
-            .bytes()
-            .await?;
-        let doc = std::str::from_utf8(&doc)?;
+            .body(Full::from(body))
+            .expect("infallible")
+    }
+
+    pub(crate) fn parse_action_response(action: &str, body: &[u8]) -> Result<ActionResponse> {
+        let doc = std::str::from_utf8(body)?;
 
         let document = Document::parse(doc)?;
         let response = utils::find_root(&document, "Body", "UPnP Response")?
@@ -157,60 +216,46 @@ impl Service {
             return Err(UPnPError::from_fault_node(response)?.into());
         }
 
-        let values: HashMap<_, _> = response
+        let values = response
             .children()
             .filter(Node::is_element)
-            .filter_map(|node| -> Option<(String, String)> {
-                node.text()
-                    .map(|text| (node.tag_name().name().to_string(), text.to_string()))
+            .map(|node| {
+                let text = node.text().unwrap_or_default();
+                (node.tag_name().name().to_string(), text.to_string())
             })
             .collect();
 
-        Ok(values)
+        Ok(ActionResponse::new(action, values, doc.to_string()))
     }
 
     #[cfg(feature = "subscribe")]
-    async fn make_subscribe_request(
+    pub(crate) fn subscribe_request(
         &self,
-        url: &Uri,
         callback: &str,
         timeout_secs: u32,
-    ) -> Result<String> {
-        use bytes::Bytes;
-        use http_body_util::Empty;
-
-        let req = Request::builder()
-            .uri(self.event_sub_url(url))
+        state_variables: &[String],
+    ) -> Result<Request<Full<Bytes>>> {
+        let mut req = Request::builder()
+            .uri(&self.event_sub_url)
             .method("SUBSCRIBE")
             .header("CALLBACK", format!("<{callback}>"))
             .header("NT", "upnp:event")
-            .header("TIMEOUT", format!("Second-{timeout_secs}"))
-            .body(Empty::<Bytes>::new())
-            .expect("infallible");
-
-        let response = Client::builder(TokioExecutor::new())
-            .build_http()
-            .request(req)
-            .await?
-            .err_if_not_200()?;
-
-        let sid = response
-            .headers()
-            .get("sid")
-            .ok_or_else(|| Error::ParseError("missing http header `SID`"))?
-            .to_str()
-            .map_err(|_| Error::ParseError("SID header contained non-visible ASCII bytes"))?
-            .to_string();
-
-        Ok(sid)
+            .header("TIMEOUT", format!("Second-{timeout_secs}"));
+        if !state_variables.is_empty() {
+            req = req.header("STATEVAR", state_variables.join(","));
+        }
+
+        req.body(Full::default())
+            .map_err(|_| Error::ParseError("invalid SUBSCRIBE request"))
     }
 
     /// Subscribe for state variable changes.
     ///
     /// It returns the SID which can be used to unsubscribe to the service and a stream of
-    /// responses.
+    /// [`Event`](struct.Event.html)s.
     ///
-    /// Each response is a [HashMap](std::collections::HashMap) of the state variables.
+    /// Each event contains the changed state variables and its `SEQ`, gaps in the sequence are
+    /// reported by [`Event::gap`](struct.Event.html#method.gap).
     ///
     /// # Example usage:
     /// ```rust,no_run
@@ -218,10 +263,10 @@ impl Service {
     /// # async fn subscribe_example() -> Result<(), rupnp::Error> {
     /// # let device: rupnp::Device = unimplemented!();
     /// # let service: rupnp::Service = unimplemented!();
-    /// let (_sid, stream) = service.subscribe(device.url(), 300).await?;
+    /// let (_sid, stream) = service.subscribe(300).await?;
     ///
-    /// while let Some(state_vars) = stream.try_next().await? {
-    ///     for (key, value) in state_vars {
+    /// while let Some(event) = stream.try_next().await? {
+    ///     for (key, value) in event.iter() {
     ///         println!("{} => {}", key, value);
     ///     }
     /// }
@@ -231,41 +276,103 @@ impl Service {
     #[cfg(feature = "subscribe")]
     pub async fn subscribe(
         &self,
-        url: &Uri,
         timeout_secs: u32,
-    ) -> Result<(String, impl Stream<Item = Result<HashMap<String, String>>>)> {
-        let addr = utils::get_local_addr()?;
-        let listener = TcpListener::bind(addr).await?;
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        self.subscribe_with(&Client::new(), timeout_secs, &[]).await
+    }
 
-        let addr = format!("http://{}", listener.local_addr()?);
+    /// Subscribe for changes of only some state variables.
+    ///
+    /// The names are sent in the `STATEVAR` header, so devices implementing UPnP 2.0 only send
+    /// those. Other devices send all evented state variables, which are filtered out before
+    /// the events are yielded. Events without any of the state variables are skipped.
+    ///
+    /// # Example usage:
+    /// ```rust,no_run
+    /// # use futures::prelude::*;
+    /// # async fn subscribe_to_example(device: rupnp::Device, service: rupnp::Service) -> Result<(), rupnp::Error> {
+    /// let (_sid, mut stream) = service
+    ///     .subscribe_to(300, &["ZoneGroupState"])
+    ///     .await?;
+    ///
+    /// while let Some(event) = stream.try_next().await? {
+    ///     println!("{:?}", event.get("ZoneGroupState"));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "subscribe")]
+    pub async fn subscribe_to(
+        &self,
+        timeout_secs: u32,
+        state_variables: &[&str],
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        self.subscribe_with(&Client::new(), timeout_secs, state_variables)
+            .await
+    }
 
-        let sid = self
-            .make_subscribe_request(url, &addr, timeout_secs)
+    #[cfg(feature = "subscribe")]
+    pub(crate) async fn subscribe_with(
+        &self,
+        client: &Client,
+        timeout_secs: u32,
+        state_variables: &[&str],
+    ) -> Result<(String, impl Stream<Item = Result<Event>>)> {
+        let state_variables = eventing::state_variable_names(state_variables)?;
+        let (callback, events) = client.event_listener(self).await?;
+
+        let request = self.subscribe_request(&callback, timeout_secs, &state_variables)?;
+        let (sid, _) = client
+            .send_subscription_request(request, timeout_secs)
             .await?;
 
-        let stream = Gen::new(move |co: Co<Result<_>>| subscribe_stream(listener, co));
+        Ok((sid, eventing::filter_variables(events, state_variables)))
+    }
 
-        Ok((sid, stream))
+    /// Subscribe for state variable changes and keep the subscription alive in the background.
+    ///
+    /// Unlike [`subscribe`](struct.Service.html#method.subscribe), the returned
+    /// [`Subscription`](struct.Subscription.html) is renewed before it expires and
+    /// unsubscribes when dropped.
+    #[cfg(feature = "subscribe")]
+    pub async fn subscription(&self, timeout_secs: u32) -> Result<Subscription> {
+        Client::new().subscription(self, timeout_secs).await
+    }
+
+    /// Like [`subscription`](struct.Service.html#method.subscription), for changes of only some
+    /// state variables. See [`subscribe_to`](struct.Service.html#method.subscribe_to).
+    #[cfg(feature = "subscribe")]
+    pub async fn subscription_to(
+        &self,
+        timeout_secs: u32,
+        state_variables: &[&str],
+    ) -> Result<Subscription> {
+        Client::new()
+            .subscription_to(self, timeout_secs, state_variables)
+            .await
     }
 
     /// Renew a subscription made with the [subscribe](struct.Service.html#method.subscribe) method.
     ///
     /// When the sid is invalid, the control point will respond with a `412 Preconditition failed`.
-    pub async fn renew_subscription(&self, url: &Uri, sid: &str, timeout_secs: u32) -> Result<()> {
-        let req = Request::builder()
-            .uri(self.event_sub_url(url))
+    pub async fn renew_subscription(&self, sid: &str, timeout_secs: u32) -> Result<()> {
+        Client::new()
+            .renew_subscription(self, sid, timeout_secs)
+            .await
+    }
+
+    pub(crate) fn renew_subscription_request(
+        &self,
+        sid: &str,
+        timeout_secs: u32,
+    ) -> Request<Full<Bytes>> {
+        Request::builder()
+            .uri(&self.event_sub_url)
             .method("SUBSCRIBE")
             .header("SID", sid)
             .header("TIMEOUT", format!("Second-{timeout_secs}"))
-            .body(Empty::<Bytes>::new())
-            .expect("infallible");
-        Client::builder(TokioExecutor::new())
-            .build_http()
-            .request(req)
-            .await?
-            .err_if_not_200()?;
-
-        Ok(())
+            .body(Full::default())
+            .expect("infallible")
     }
 
     /// Unsubscribe from further event notifications.
@@ -273,86 +380,16 @@ impl Service {
     /// The SID is usually obtained by the [subscribe](struct.Service.html#method.subscribe) method.
     ///
     /// When the sid is invalid, the control point will respond with a `412 Preconditition failed`.
-    pub async fn unsubscribe(&self, url: &Uri, sid: &str) -> Result<()> {
-        let req = Request::builder()
-            .uri(self.event_sub_url(url))
-            .method("UNSUBSCRIBE")
-            .header("SID", sid)
-            .body(Empty::<Bytes>::new())
-            .expect("infallible");
-
-        Client::builder(TokioExecutor::new())
-            .build_http()
-            .request(req)
-            .await?
-            .err_if_not_200()?;
-
-        Ok(())
+    pub async fn unsubscribe(&self, sid: &str) -> Result<()> {
+        Client::new().unsubscribe(self, sid).await
     }
-}
-
-#[cfg(feature = "subscribe")]
-macro_rules! yield_try {
-    ( $co:expr => $expr:expr ) => {
-        match $expr {
-            Ok(val) => val,
-            Err(e) => {
-                $co.yield_(Err(e.into())).await;
-                continue;
-            }
-        }
-    };
-}
-
-#[cfg(feature = "subscribe")]
-fn propertyset_to_map(input: &str) -> Result<HashMap<String, String>, roxmltree::Error> {
-    let doc = Document::parse(input)?;
-    let hashmap: HashMap<String, String> = doc
-        .root_element() // <e:propertyset />
-        .children() // <e:property />
-        .filter_map(|child| child.first_element_child()) // actual tag
-        .filter_map(|node| {
-            node.text()
-                .map(|text| (node.tag_name().name().to_string(), text.to_string()))
-        })
-        .collect();
 
-    Ok(hashmap)
-}
-
-#[cfg(feature = "subscribe")]
-async fn subscribe_stream(listener: TcpListener, co: Co<Result<HashMap<String, String>>>) {
-    loop {
-        let (mut stream, _) = yield_try!(co => listener.accept().await);
-        let mut lines = BufReader::new(&mut stream).lines();
-
-        let mut input = String::new();
-        let mut is_xml = false;
-
-        // sometimes the xml is on one line, sometimes on multiple ones.
-        // we dont care about the http stuff before the "<e:propertyset>"
-        while let Ok(Some(line)) = lines.next_line().await {
-            if is_xml || line.starts_with("<e:propertyset") {
-                input.push_str(&line);
-                is_xml = true;
-            }
-
-            if line.ends_with("</e:propertyset>") {
-                break;
-            };
-        }
-
-        let response = "HTTP/1.1 200 OK\r\n\r\n";
-        let _ = stream.write_all(response.as_bytes()).await;
-
-        let hashmap = yield_try!(co => propertyset_to_map(&input));
-
-        co.yield_(Ok(hashmap)).await;
+    pub(crate) fn unsubscribe_request(&self, sid: &str) -> Request<Full<Bytes>> {
+        Request::builder()
+            .uri(&self.event_sub_url)
+            .method("UNSUBSCRIBE")
+            .header("SID", sid)
+            .body(Full::default())
+            .expect("infallible")
     }
 }
-
-fn replace_url_path(url: &Uri, path: &PathAndQuery) -> Uri {
-    let mut parts = url.clone().into_parts();
-    parts.path_and_query = Some(path.clone());
-    Uri::from_parts(parts).expect("infallible")
-}
@@ -0,0 +1,313 @@
+//! Helpers for testing control points without real devices.
+
+use crate::{
+    host::random_uuid, utils, ActionArgs, ActionRequest, ArgValue, Device, DeviceHost, Error,
+    EventPublisher, Result, UPnPError,
+};
+use http::Uri;
+use roxmltree::Document;
+use ssdp_client::URN;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::Ipv4Addr,
+    sync::{Arc, Mutex},
+};
+
+/// An in-process UPnP device on localhost with scripted responses, to test code using
+/// [`Device`]s and [`Service`](crate::Service)s offline.
+///
+/// Actions are answered with the responses or faults scripted with
+/// [`respond`](MockDevice::respond) and [`fault`](MockDevice::fault), after their arguments
+/// were checked against the SCPD of the service. Every call is recorded, see
+/// [`calls`](MockDevice::calls). Control points can subscribe to the services, and events are
+/// sent with [`notify`](MockDevice::notify).
+///
+/// The device isn't announced with SSDP, it is reached through its [`url`](MockDevice::url).
+///
+/// # Example usage:
+/// ```rust,no_run
+/// # async fn mock_device(scpd: &str) -> Result<(), rupnp::Error> {
+/// use rupnp::{ssdp::URN, testing::MockDevice, ActionArgs, UPnPError};
+///
+/// const RENDERING_CONTROL: URN = URN::service("schemas-upnp-org", "RenderingControl", 1);
+///
+/// let mock = MockDevice::builder()
+///     .service(RENDERING_CONTROL, scpd)
+///     .start()
+///     .await?;
+/// mock.respond("GetVolume", ActionArgs::new().arg("CurrentVolume", 25));
+/// mock.fault("SetVolume", UPnPError::new(501, "Action Failed"));
+///
+/// let device = mock.device().await?;
+/// let service = device.find_service(&RENDERING_CONTROL).unwrap();
+/// let args = ActionArgs::new().arg("InstanceID", 0).arg("Channel", "Master");
+/// let response = service.call("GetVolume", &args).await?;
+/// assert_eq!(response.get("CurrentVolume"), Some("25"));
+/// assert_eq!(mock.calls().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockDevice {
+    host: DeviceHost,
+    service_ids: Vec<(URN, String)>,
+    script: Arc<Mutex<Script>>,
+}
+
+/// Builder for a [`MockDevice`], see [`MockDevice::builder`].
+#[derive(Debug, Clone)]
+pub struct MockDeviceBuilder {
+    device_type: URN,
+    friendly_name: String,
+    description: Option<String>,
+    services: Vec<(URN, String)>,
+}
+
+#[derive(Default)]
+struct Script {
+    responses: HashMap<String, VecDeque<Result<ActionArgs, UPnPError>>>,
+    calls: Vec<ActionRequest>,
+}
+
+impl Script {
+    /// The next scripted response of the action. The last one is repeated for all further calls.
+    fn answer(&mut self, request: ActionRequest) -> Result<ActionArgs, UPnPError> {
+        let action = request.action().to_string();
+        self.calls.push(request);
+
+        let responses = self.responses.get_mut(&action);
+        match responses {
+            Some(responses) if responses.len() > 1 => responses.pop_front().expect("not empty"),
+            Some(responses) if !responses.is_empty() => responses[0].clone(),
+            _ => Err(UPnPError::new(
+                501,
+                format!("no response scripted for `{action}`"),
+            )),
+        }
+    }
+}
+
+impl MockDeviceBuilder {
+    /// The type of the device in the generated description.
+    /// Defaults to `urn:schemas-upnp-org:device:Basic:1`.
+    pub fn device_type(mut self, device_type: URN) -> Self {
+        self.device_type = device_type;
+        self
+    }
+
+    /// The friendly name of the device in the generated description.
+    pub fn friendly_name(mut self, friendly_name: impl Into<String>) -> Self {
+        self.friendly_name = friendly_name.into();
+        self
+    }
+
+    /// Serves `description` instead of a generated device description, e.g. one captured
+    /// from a real device. The services are found in it by their type.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a service of type `service_type` described by `scpd`.
+    pub fn service(mut self, service_type: URN, scpd: impl Into<String>) -> Self {
+        self.services.push((service_type, scpd.into()));
+        self
+    }
+
+    /// Starts serving the device on localhost.
+    pub async fn start(self) -> Result<MockDevice> {
+        let description = match self.description {
+            Some(description) => description,
+            None => self.generate_description(),
+        };
+
+        let script = Arc::new(Mutex::new(Script::default()));
+        let mut service_ids = Vec::new();
+        let mut host = DeviceHost::builder(description.as_str())
+            .bind_addr((Ipv4Addr::LOCALHOST, 0).into())
+            .advertise(false);
+        for (service_type, scpd) in self.services {
+            let service_id = find_service_id(&description, &service_type)?;
+            let script = Arc::clone(&script);
+            host = host.service(service_id.as_str(), scpd, move |request| {
+                let answer = script.lock().unwrap().answer(request);
+                async move { answer }
+            });
+            service_ids.push((service_type, service_id));
+        }
+
+        Ok(MockDevice {
+            host: host.start().await?,
+            service_ids,
+            script,
+        })
+    }
+
+    fn generate_description(&self) -> String {
+        let services: String = self
+            .services
+            .iter()
+            .map(|(service_type, _)| {
+                let name = service_type.typ();
+                format!(
+                    "<service>\
+                     <serviceType>{service_type}</serviceType>\
+                     <serviceId>urn:upnp-org:serviceId:{name}</serviceId>\
+                     <SCPDURL>/{name}/scpd.xml</SCPDURL>\
+                     <controlURL>/{name}/control</controlURL>\
+                     <eventSubURL>/{name}/event</eventSubURL>\
+                     </service>"
+                )
+            })
+            .collect();
+
+        format!(
+            r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+<specVersion><major>1</major><minor>0</minor></specVersion>
+<device>
+<deviceType>{}</deviceType>
+<friendlyName>{}</friendlyName>
+<manufacturer>rupnp</manufacturer>
+<modelName>MockDevice</modelName>
+<UDN>uuid:{}</UDN>
+<serviceList>{services}</serviceList>
+</device>
+</root>"#,
+            self.device_type,
+            utils::escape_xml(&self.friendly_name),
+            random_uuid()
+        )
+    }
+}
+
+/// The `serviceId` of the first service of type `service_type` in the description.
+fn find_service_id(description: &str, service_type: &URN) -> Result<String> {
+    let document = Document::parse(description)?;
+    let service_type = service_type.to_string();
+
+    document
+        .descendants()
+        .filter(|node| node.tag_name().name() == "service")
+        .find(|service| {
+            service.children().any(|node| {
+                node.tag_name().name() == "serviceType" && node.text() == Some(&service_type)
+            })
+        })
+        .and_then(|service| {
+            service
+                .children()
+                .find(|node| node.tag_name().name() == "serviceId")
+        })
+        .and_then(|node| node.text())
+        .map(|service_id| service_id.trim().to_string())
+        .ok_or_else(|| Error::XmlMissingElement("Device Description".to_string(), service_type))
+}
+
+impl MockDevice {
+    pub fn builder() -> MockDeviceBuilder {
+        MockDeviceBuilder {
+            device_type: URN::device("schemas-upnp-org", "Basic", 1),
+            friendly_name: "rupnp MockDevice".to_string(),
+            description: None,
+            services: Vec::new(),
+        }
+    }
+
+    /// The URL of the device description.
+    pub fn url(&self) -> &Uri {
+        self.host.location()
+    }
+
+    /// Fetches the device description like a control point would.
+    pub async fn device(&self) -> Result<Device> {
+        Device::from_url(self.url().clone()).await
+    }
+
+    /// Answers calls of `action` with `output`.
+    ///
+    /// Responses and faults scripted for the same action are used in order, the last one for
+    /// all further calls.
+    pub fn respond(&self, action: &str, output: ActionArgs) {
+        self.script(action, Ok(output));
+    }
+
+    /// Answers calls of `action` with a SOAP fault, see [`respond`](MockDevice::respond).
+    pub fn fault(&self, action: &str, err: UPnPError) {
+        self.script(action, Err(err));
+    }
+
+    fn script(&self, action: &str, response: Result<ActionArgs, UPnPError>) {
+        let mut script = self.script.lock().unwrap();
+        script
+            .responses
+            .entry(action.to_string())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// All actions called so far, in order, with their arguments.
+    pub fn calls(&self) -> Vec<ActionRequest> {
+        self.script.lock().unwrap().calls.clone()
+    }
+
+    /// Sends an event with the new `value` of the state variable `name` of the service of
+    /// type `service_type` to its subscribers.
+    ///
+    /// # Panics
+    /// Panics if the device has no such service, if the variable isn't evented according to the
+    /// SCPD, or if the value doesn't differ from the current one, since no event would be sent.
+    pub fn notify(&self, service_type: &URN, name: &str, value: impl Into<ArgValue>) {
+        let events = self.events(service_type);
+        if !events.is_evented(name) {
+            panic!(
+                "`{}` is not an evented state variable of `{}`",
+                name, service_type
+            );
+        }
+        let value = value.into();
+        if events.get(name).as_deref() == Some(value.as_str()) {
+            panic!(
+                "`{}` of `{}` already is `{}`, no event would be sent",
+                name,
+                service_type,
+                value.as_str()
+            );
+        }
+        events.set(name, value);
+    }
+
+    /// The number of active subscriptions to the service of type `service_type`.
+    ///
+    /// # Panics
+    /// Panics if the device has no such service.
+    pub fn subscriptions(&self, service_type: &URN) -> usize {
+        self.events(service_type).subscribers()
+    }
+
+    /// Forgets all subscriptions to the service of type `service_type`, like a device which
+    /// rebooted. Renewing them fails with `412 Precondition Failed`.
+    ///
+    /// # Panics
+    /// Panics if the device has no such service.
+    pub fn forget_subscriptions(&self, service_type: &URN) {
+        self.events(service_type).clear_subscribers();
+    }
+
+    fn events(&self, service_type: &URN) -> &EventPublisher {
+        self.service_ids
+            .iter()
+            .find(|(urn, _)| urn == service_type)
+            .and_then(|(_, service_id)| self.host.events(service_id))
+            .unwrap_or_else(|| panic!("MockDevice has no service of type `{}`", service_type))
+    }
+}
+
+impl fmt::Debug for MockDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockDevice")
+            .field("url", self.url())
+            .field("services", &self.service_ids)
+            .finish()
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{Error, Result};
-use http_body_util::BodyExt;
+use http::Uri;
 #[cfg(feature = "subscribe")]
 use if_addrs::{get_if_addrs, Interface};
 use roxmltree::{Document, Node};
+use std::borrow::Cow;
 #[cfg(feature = "subscribe")]
-use std::net::{IpAddr, SocketAddrV4};
+use std::net::SocketAddrV4;
+#[cfg(any(feature = "subscribe", feature = "host"))]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 pub(crate) trait HttpResponseExt: Sized {
     fn err_if_not_200(self) -> Result<Self>;
@@ -18,14 +21,19 @@ impl<B> HttpResponseExt for hyper::Response<B> {
         }
     }
 }
-pub(crate) trait HyperBodyExt: Sized {
-    async fn bytes(self) -> Result<bytes::Bytes>;
-}
-impl HyperBodyExt for hyper::body::Incoming {
-    async fn bytes(self) -> Result<bytes::Bytes> {
-        Ok(self.collect().await?.to_bytes())
-    }
+
+macro_rules! yield_try {
+    ( $co:expr => $expr:expr ) => {
+        match $expr {
+            Ok(val) => val,
+            Err(e) => {
+                $co.yield_(Err(e.into())).await;
+                continue;
+            }
+        }
+    };
 }
+pub(crate) use yield_try;
 
 #[macro_export]
 #[doc(hidden)]
@@ -105,6 +113,159 @@ pub fn find_node_attribute<'n, 'd: 'n>(node: Node<'d, 'n>, attr: &str) -> Option
         .map(|a| a.value())
 }
 
+/// Escapes the characters which are not allowed in xml text or attribute values.
+pub(crate) fn escape_xml(input: &str) -> Cow<'_, str> {
+    if !input.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(input);
+    }
+
+    let mut escaped = String::with_capacity(input.len() + 16);
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Whether `name` can be used as an xml element name without a namespace prefix, i.e. is an
+/// [NCName](https://www.w3.org/TR/xml-names/#NT-NCName).
+pub(crate) fn is_ncname(name: &str) -> bool {
+    fn is_start_char(c: char) -> bool {
+        matches!(c,
+            'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+            | '\u{F8}'..='\u{2FF}' | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}'
+            | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}'
+            | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{EFFFF}'
+        )
+    }
+    fn is_char(c: char) -> bool {
+        is_start_char(c)
+            || matches!(c,
+                '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}'
+            )
+    }
+
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_start_char) && chars.all(is_char)
+}
+
+/// Resolves a (possibly relative) URL reference against `base` according to
+/// [RFC 3986, section 5.2](https://www.rfc-editor.org/rfc/rfc3986#section-5.2).
+pub(crate) fn resolve_url(base: &Uri, reference: &str) -> Result<Uri> {
+    let reference = reference.trim();
+    // `Uri` can't represent fragments, and they're irrelevant for requests anyway
+    let reference = reference.split('#').next().unwrap_or_default();
+
+    if has_scheme(reference) {
+        let (scheme, rest) = reference.split_once(':').expect("has scheme");
+        let (authority, path, query) = split_hierarchical(rest);
+        let path = remove_dot_segments(path);
+        return build_url(scheme, authority.unwrap_or_default(), &path, query);
+    }
+
+    let scheme = base.scheme_str().unwrap_or("http");
+    let base_authority = base.authority().map(|a| a.as_str()).unwrap_or_default();
+    let (authority, path, query) = split_hierarchical(reference);
+
+    if let Some(authority) = authority {
+        return build_url(scheme, authority, &remove_dot_segments(path), query);
+    }
+
+    let (path, query) = if path.is_empty() {
+        (base.path().to_string(), query.or(base.query()))
+    } else if path.starts_with('/') {
+        (remove_dot_segments(path), query)
+    } else {
+        let base_path = base.path();
+        let merged = match base_path.rfind('/') {
+            Some(idx) => format!("{}{path}", &base_path[..=idx]),
+            None => format!("/{path}"),
+        };
+        (remove_dot_segments(&merged), query)
+    };
+
+    build_url(scheme, base_authority, &path, query)
+}
+
+fn has_scheme(reference: &str) -> bool {
+    match reference.find(':') {
+        Some(idx) => {
+            let scheme = &reference[..idx];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Splits `//authority/path?query` (authority optional) into its components.
+fn split_hierarchical(input: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (input, query) = match input.split_once('?') {
+        Some((input, query)) => (input, Some(query)),
+        None => (input, None),
+    };
+
+    match input.strip_prefix("//") {
+        Some(rest) => {
+            let idx = rest.find('/').unwrap_or(rest.len());
+            (Some(&rest[..idx]), &rest[idx..], query)
+        }
+        None => (None, input, query),
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').collect();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+        match *segment {
+            "." => {
+                if is_last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                if output.len() > 1 || output.first().is_some_and(|s| !s.is_empty()) {
+                    output.pop();
+                }
+                if is_last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+
+    let path = output.join("/");
+    if path.starts_with('/') || path.is_empty() {
+        path
+    } else {
+        format!("/{path}")
+    }
+}
+
+fn build_url(scheme: &str, authority: &str, path: &str, query: Option<&str>) -> Result<Uri> {
+    let path = if path.is_empty() { "/" } else { path };
+    let url = match query {
+        Some(query) => format!("{scheme}://{authority}{path}?{query}"),
+        None => format!("{scheme}://{authority}{path}"),
+    };
+    Ok(url.parse()?)
+}
+
+/// Returns the first private IPv4 address of the local network interfaces.
+/// [`get_local_addr_for`] picks the right one on hosts with multiple networks, and supports IPv6.
 #[cfg(feature = "subscribe")]
 pub fn get_local_addr() -> Result<SocketAddrV4> {
     get_if_addrs()?
@@ -119,9 +280,63 @@ pub fn get_local_addr() -> Result<SocketAddrV4> {
         .map(|addr| SocketAddrV4::new(addr, 0))
 }
 
+/// Returns the address of the local interface which the traffic to the host of `url` is
+/// routed through, by connecting a UDP socket to it. No packets are sent.
+#[cfg(feature = "subscribe")]
+pub async fn get_local_addr_for(url: &Uri) -> Result<IpAddr> {
+    Ok(local_socket_addr_for(url).await?.ip())
+}
+
+/// Like [`get_local_addr_for`], but keeps the scope id of link-local IPv6 addresses, without
+/// which they can't be bound to. The port is zero.
+#[cfg(feature = "subscribe")]
+pub(crate) async fn local_socket_addr_for(url: &Uri) -> Result<SocketAddr> {
+    let remote = remote_addr(url).await?;
+    Ok(local_addr_for(remote)?)
+}
+
+/// Resolves the host and port of `url`, including the zone of a link-local IPv6 address.
+#[cfg(feature = "subscribe")]
+pub(crate) async fn remote_addr(url: &Uri) -> Result<SocketAddr> {
+    let host = url.host().ok_or(Error::ParseError("url has no host"))?;
+    // link-local IPv6 addresses may have a zone, `%25` encoded as in RFC 6874
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .replace("%25", "%");
+    let port = url.port_u16().unwrap_or(80);
+
+    let remote = tokio::net::lookup_host((host.as_str(), port))
+        .await?
+        .next()
+        .ok_or(Error::NoLocalInterfaceOpen)?;
+    Ok(remote)
+}
+
+/// Returns the address of the local interface which the traffic to `remote` is routed through.
+#[cfg(feature = "host")]
+pub(crate) fn local_ip_for(remote: SocketAddr) -> std::io::Result<IpAddr> {
+    Ok(local_addr_for(remote)?.ip())
+}
+
+#[cfg(any(feature = "subscribe", feature = "host"))]
+fn local_addr_for(remote: SocketAddr) -> std::io::Result<SocketAddr> {
+    let unspecified: SocketAddr = match remote {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+
+    let socket = std::net::UdpSocket::bind(unspecified)?;
+    socket.connect(remote)?;
+    let mut local_addr = socket.local_addr()?;
+    local_addr.set_port(0);
+    Ok(local_addr)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::find_in_xml;
+    use super::resolve_url;
+    use http::Uri;
     use roxmltree::Document;
     use std::collections::HashMap;
 
@@ -190,4 +405,53 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_url() {
+        // examples from RFC 3986, section 5.4
+        let base = Uri::from_static("http://a/b/c/d;p?q");
+        let resolve = |reference| resolve_url(&base, reference).unwrap().to_string();
+
+        assert_eq!(resolve("g"), "http://a/b/c/g");
+        assert_eq!(resolve("./g"), "http://a/b/c/g");
+        assert_eq!(resolve("g/"), "http://a/b/c/g/");
+        assert_eq!(resolve("/g"), "http://a/g");
+        assert_eq!(resolve("//g"), "http://g/");
+        assert_eq!(resolve("?y"), "http://a/b/c/d;p?y");
+        assert_eq!(resolve("g?y"), "http://a/b/c/g?y");
+        assert_eq!(resolve("g#s"), "http://a/b/c/g");
+        assert_eq!(resolve(""), "http://a/b/c/d;p?q");
+        assert_eq!(resolve("."), "http://a/b/c/");
+        assert_eq!(resolve(".."), "http://a/b/");
+        assert_eq!(resolve("../g"), "http://a/b/g");
+        assert_eq!(resolve("../../g"), "http://a/g");
+        assert_eq!(resolve("../../../g"), "http://a/g");
+        assert_eq!(resolve("/./g"), "http://a/g");
+        assert_eq!(resolve("g/../h"), "http://a/b/c/h");
+
+        // typical UPnP descriptions
+        let base = Uri::from_static("http://192.168.1.2:1400/xml/device_description.xml");
+        let resolve = |reference| resolve_url(&base, reference).unwrap().to_string();
+
+        assert_eq!(
+            resolve("control/rc"),
+            "http://192.168.1.2:1400/xml/control/rc"
+        );
+        assert_eq!(
+            resolve("/MediaRenderer/RenderingControl/Control"),
+            "http://192.168.1.2:1400/MediaRenderer/RenderingControl/Control"
+        );
+        assert_eq!(
+            resolve(" http://other-host:49152/ctl "),
+            "http://other-host:49152/ctl"
+        );
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn local_addr_routing_to_device() {
+        let url = Uri::from_static("http://127.0.0.1:1400/description.xml");
+        let addr = super::get_local_addr_for(&url).await.unwrap();
+        assert_eq!(addr, std::net::Ipv4Addr::LOCALHOST);
+    }
 }
@@ -0,0 +1,232 @@
+use futures::prelude::*;
+use rupnp::{scpd::ArgumentError, ssdp::URN, testing::MockDevice, ActionArgs, Error, UPnPError};
+use std::time::Duration;
+
+const RENDERING_CONTROL: URN = URN::service("schemas-upnp-org", "RenderingControl", 1);
+
+const SCPD: &str = r#"<?xml version="1.0"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <actionList>
+    <action>
+      <name>GetVolume</name>
+      <argumentList>
+        <argument><name>InstanceID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable></argument>
+        <argument><name>CurrentVolume</name><direction>out</direction><relatedStateVariable>Volume</relatedStateVariable></argument>
+      </argumentList>
+    </action>
+    <action>
+      <name>SetVolume</name>
+      <argumentList>
+        <argument><name>InstanceID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable></argument>
+        <argument><name>DesiredVolume</name><direction>in</direction><relatedStateVariable>Volume</relatedStateVariable></argument>
+      </argumentList>
+    </action>
+  </actionList>
+  <serviceStateTable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_InstanceID</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="yes">
+      <name>Volume</name><dataType>ui2</dataType><defaultValue>10</defaultValue>
+      <allowedValueRange><minimum>0</minimum><maximum>100</maximum></allowedValueRange>
+    </stateVariable>
+  </serviceStateTable>
+</scpd>"#;
+
+async fn mock_device() -> MockDevice {
+    MockDevice::builder()
+        .friendly_name("Mock Renderer")
+        .service(RENDERING_CONTROL, SCPD)
+        .start()
+        .await
+        .unwrap()
+}
+
+fn err_code(result: Result<rupnp::ActionResponse, Error>) -> u16 {
+    match result {
+        Err(Error::UPnPError(err)) => err.err_code(),
+        other => panic!("expected a UPnP error, got {:?}", other),
+    }
+}
+
+async fn set_volume(
+    service: &rupnp::Service,
+    volume: &str,
+) -> Result<rupnp::ActionResponse, Error> {
+    let args = ActionArgs::new()
+        .arg("InstanceID", 0)
+        .arg("DesiredVolume", volume);
+    service.call("SetVolume", &args).await
+}
+
+#[tokio::test]
+async fn device_description() {
+    let mock = mock_device().await;
+
+    let device = rupnp::Device::from_url(mock.url().clone()).await.unwrap();
+    assert_eq!(device.friendly_name(), "Mock Renderer");
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let scpd = service.scpd().await.unwrap();
+    assert_eq!(scpd.actions().len(), 2);
+    assert!(scpd.state_variables().iter().any(|v| v.name() == "Volume"));
+}
+
+#[tokio::test]
+async fn scripted_actions() {
+    let mock = mock_device().await;
+    mock.respond("GetVolume", ActionArgs::new().arg("CurrentVolume", 25));
+    mock.respond("GetVolume", ActionArgs::new().arg("CurrentVolume", 30));
+    mock.fault("SetVolume", UPnPError::new(701, "Transition not available"));
+
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let get_volume = || service.action("GetVolume", "<InstanceID>0</InstanceID>");
+    let volume = |response: rupnp::ActionResponse| response.get_as::<u16>("CurrentVolume").unwrap();
+    assert_eq!(volume(get_volume().await.unwrap()), 25);
+    assert_eq!(volume(get_volume().await.unwrap()), 30);
+    assert_eq!(volume(get_volume().await.unwrap()), 30);
+
+    let args = ActionArgs::new()
+        .arg("InstanceID", 0)
+        .arg("DesiredVolume", 50);
+    match service.call("SetVolume", &args).await {
+        Err(Error::UPnPError(err)) => {
+            assert_eq!(err.err_code(), 701);
+            assert_eq!(err.description(), Some("Transition not available"));
+        }
+        other => panic!("expected a UPnP error, got {:?}", other),
+    }
+
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[3].action(), "SetVolume");
+    assert_eq!(calls[3].get("DesiredVolume"), Some("50"));
+}
+
+#[tokio::test]
+async fn invalid_arguments() {
+    let mock = mock_device().await;
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    assert_eq!(err_code(set_volume(service, "101").await), 601);
+    assert_eq!(err_code(set_volume(service, "loud").await), 600);
+    assert_eq!(err_code(service.action("Play", "").await), 401);
+    // unscripted actions fail
+    assert_eq!(err_code(set_volume(service, "50").await), 501);
+
+    // checked before sending, without reaching the device
+    let scpd = service.scpd().await.unwrap();
+    let args = ActionArgs::new().arg("InstanceID", 0);
+    let result = service.call_validated(&scpd, "SetVolume", &args).await;
+    assert!(matches!(
+        result,
+        Err(Error::InvalidArguments(ArgumentError::MissingArgument(_)))
+    ));
+    assert_eq!(mock.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn events() {
+    let mock = mock_device().await;
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let (sid, events) = service.subscribe(300).await.unwrap();
+    let mut events = std::pin::pin!(events);
+    let initial = events.try_next().await.unwrap().unwrap();
+    assert_eq!(initial.get("Volume"), Some("10"));
+
+    mock.notify(&RENDERING_CONTROL, "Volume", 42);
+    let event = events.try_next().await.unwrap().unwrap();
+    assert_eq!((event.seq(), event.get("Volume")), (1, Some("42")));
+
+    service.renew_subscription(&sid, 300).await.unwrap();
+    service.unsubscribe(&sid).await.unwrap();
+    assert!(service.unsubscribe(&sid).await.is_err());
+}
+
+#[tokio::test]
+#[should_panic(expected = "`Volume ` is not an evented state variable")]
+async fn notify_typo() {
+    let mock = mock_device().await;
+    mock.notify(&RENDERING_CONTROL, "Volume ", 42);
+}
+
+#[tokio::test]
+#[should_panic(expected = "already is `10`")]
+async fn notify_unchanged() {
+    let mock = mock_device().await;
+    mock.notify(&RENDERING_CONTROL, "Volume", 10);
+}
+
+#[tokio::test]
+async fn subscription_renewal() {
+    let mock = mock_device().await;
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let mut subscription = service.subscription(2).await.unwrap();
+    let sid = subscription.sid();
+    let initial = subscription.try_next().await.unwrap().unwrap();
+    assert_eq!(initial.seq(), 0);
+
+    // without renewals the subscription would have expired by now
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_eq!(mock.subscriptions(&RENDERING_CONTROL), 1);
+    mock.notify(&RENDERING_CONTROL, "Volume", 42);
+    let event = next_event(&mut subscription).await;
+    assert_eq!(
+        (event.sid(), event.get("Volume")),
+        (sid.as_str(), Some("42"))
+    );
+    assert_eq!(subscription.sid(), sid);
+}
+
+#[tokio::test]
+async fn resubscribe_when_forgotten() {
+    let mock = mock_device().await;
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let mut subscription = service.subscription(2).await.unwrap();
+    let sid = subscription.sid();
+    next_event(&mut subscription).await;
+
+    // the renewal is rejected with 412, so a new subscription is made
+    mock.forget_subscriptions(&RENDERING_CONTROL);
+    let initial = next_event(&mut subscription).await;
+    assert_ne!(initial.sid(), sid);
+    assert_eq!((initial.seq(), initial.get("Volume")), (0, Some("10")));
+    assert_eq!(subscription.sid(), initial.sid());
+    assert_eq!(mock.subscriptions(&RENDERING_CONTROL), 1);
+}
+
+#[tokio::test]
+async fn unsubscribe_on_drop() {
+    let mock = mock_device().await;
+    let device = mock.device().await.unwrap();
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+
+    let subscription = service.subscription(300).await.unwrap();
+    assert_eq!(mock.subscriptions(&RENDERING_CONTROL), 1);
+    drop(subscription);
+
+    // the `UNSUBSCRIBE` request is sent in the background
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while mock.subscriptions(&RENDERING_CONTROL) > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("unsubscribed");
+}
+
+async fn next_event(subscription: &mut rupnp::Subscription) -> rupnp::Event {
+    tokio::time::timeout(Duration::from_secs(5), subscription.try_next())
+        .await
+        .expect("event within 5 seconds")
+        .unwrap()
+        .unwrap()
+}
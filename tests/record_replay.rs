@@ -0,0 +1,113 @@
+use futures::prelude::*;
+use rupnp::{
+    ssdp::URN, testing::MockDevice, ActionArgs, Client, Error, Fixture, Recorder, UPnPError,
+};
+
+const RENDERING_CONTROL: URN = URN::service("schemas-upnp-org", "RenderingControl", 1);
+
+const SCPD: &str = r#"<?xml version="1.0"?>
+<scpd xmlns="urn:schemas-upnp-org:service-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <actionList>
+    <action>
+      <name>GetVolume</name>
+      <argumentList>
+        <argument><name>InstanceID</name><direction>in</direction><relatedStateVariable>A_ARG_TYPE_InstanceID</relatedStateVariable></argument>
+        <argument><name>CurrentVolume</name><direction>out</direction><relatedStateVariable>Volume</relatedStateVariable></argument>
+      </argumentList>
+    </action>
+  </actionList>
+  <serviceStateTable>
+    <stateVariable sendEvents="no"><name>A_ARG_TYPE_InstanceID</name><dataType>ui4</dataType></stateVariable>
+    <stateVariable sendEvents="yes"><name>Volume</name><dataType>ui2</dataType><defaultValue>10</defaultValue></stateVariable>
+  </serviceStateTable>
+</scpd>"#;
+
+/// Talks to the device like an application would, returning what it saw. `subscribed` is
+/// called once the subscription is set up.
+async fn session(
+    client: &Client,
+    url: rupnp::http::Uri,
+    subscribed: impl FnOnce(),
+) -> Result<Vec<String>, Error> {
+    let device = client.device(url).await?;
+    let service = device.find_service(&RENDERING_CONTROL).unwrap();
+    let scpd = client.scpd(service).await?;
+
+    let mut seen = vec![
+        device.friendly_name().to_string(),
+        scpd.actions().len().to_string(),
+    ];
+    for _ in 0..3 {
+        let args = "<InstanceID>0</InstanceID>";
+        match client.action(service, "GetVolume", args).await {
+            Ok(response) => seen.push(response.get("CurrentVolume").unwrap().to_string()),
+            Err(Error::UPnPError(err)) => seen.push(err.to_string()),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let (_, events) = client.subscribe(service, 300).await?;
+    subscribed();
+    let events = events.take(2).map_ok(|event| {
+        let volume = event.get("Volume").unwrap_or_default();
+        format!("{} {}", event.seq(), volume)
+    });
+    seen.extend(events.try_collect::<Vec<_>>().await?);
+    Ok(seen)
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let mock = MockDevice::builder()
+        .friendly_name("Misbehaving Renderer")
+        .service(RENDERING_CONTROL, SCPD)
+        .start()
+        .await
+        .unwrap();
+    mock.respond("GetVolume", ActionArgs::new().arg("CurrentVolume", 25));
+    mock.fault("GetVolume", UPnPError::new(714, "Illegal MIME-type"));
+    mock.respond("GetVolume", ActionArgs::new().arg("CurrentVolume", 30));
+
+    let url = mock.url().clone();
+    let recorder = Recorder::new();
+    let client = Client::builder().record(recorder.clone()).build();
+    let recorded = session(&client, url.clone(), || {
+        mock.notify(&RENDERING_CONTROL, "Volume", 42)
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        recorded,
+        [
+            "Misbehaving Renderer",
+            "1",
+            "25",
+            "UPnPError 714: Illegal MIME-type",
+            "30",
+            "0 10",
+            "1 42"
+        ]
+    );
+
+    let path = std::env::temp_dir().join(format!("rupnp-fixture-{}.xml", std::process::id()));
+    recorder.fixture().save(&path).unwrap();
+    drop(mock);
+
+    let fixture = Fixture::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(fixture, recorder.fixture());
+
+    let client = Client::builder().replay(fixture).build();
+    let replayed = session(&client, url.clone(), || {}).await.unwrap();
+    assert_eq!(replayed, recorded);
+
+    // requests which weren't recorded
+    let other: rupnp::http::Uri = format!("http://{}/other.xml", url.authority().unwrap())
+        .parse()
+        .unwrap();
+    assert!(matches!(
+        client.device(other).await,
+        Err(Error::HttpErrorCode(code)) if code == 404
+    ));
+}